@@ -2,7 +2,14 @@ extern crate audio_to_text;
 
 use audio_to_text::mws::{cors, logger};
 use audio_to_text::healthcheck;
+use audio_to_text::jobs;
+use audio_to_text::jobs::config::JobsConfig;
+use audio_to_text::jobs::store::{MemoryJobStore, SqliteJobStore};
+use audio_to_text::jobs::worker::JobQueue;
+use audio_to_text::openai;
 use audio_to_text::swagger;
+use audio_to_text::uploads::config::UploadsConfig;
+use audio_to_text::uploads::sweeper;
 use audio_to_text::whisper;
 use audio_to_text::whisper::client_async::WhisperAsyncClient;
 use audio_to_text::whisper::config::WhisperClientConfig;
@@ -28,6 +35,19 @@ async fn main() -> Result<(), anyhow::Error> {
     let whisper_config = WhisperClientConfig::from_env();
     let whisper_context = WhisperAsyncClient::new(&whisper_config);
 
+    let jobs_config = JobsConfig::from_env();
+    let job_queue = match jobs_config.get_store_dir() {
+        None => JobQueue::new(MemoryJobStore::default(), whisper_context.clone(), &jobs_config),
+        Some(store_dir) => {
+            let job_store = SqliteJobStore::open(store_dir)?;
+            JobQueue::new(job_store, whisper_context.clone(), &jobs_config)
+        }
+    };
+
+    let uploads_config = UploadsConfig::from_env();
+    std::fs::create_dir_all(uploads_config.get_upload_dir())?;
+    sweeper::spawn_sweeper(&uploads_config, job_queue.clone());
+
     HttpServer::new(move || {
         let whisper_context = whisper_context.clone();
         let whisper_box_cxt: Box<WhisperAsyncClient> = Box::new(whisper_context);
@@ -36,12 +56,17 @@ async fn main() -> Result<(), anyhow::Error> {
 
         App::new()
             .app_data(web::Data::new(whisper_box_cxt))
+            .app_data(web::Data::new(job_queue.clone()))
+            .app_data(web::Data::new(uploads_config.clone()))
             .wrap(Logger::default())
             .wrap(cors::build_cors_policy())
             .service(static_files.show_files_listing())
             .service(healthcheck::build_scope())
             .service(swagger::build_scope())
             .service(whisper::build_scope())
+            .service(whisper::build_detect_scope())
+            .service(openai::build_scope())
+            .service(jobs::build_scope())
             .service(
                 web::resource("/socket")
                     .route(web::get().to(ws::routes::stream))
@@ -1,30 +1,105 @@
+use crate::jobs::errors::JobError;
+use crate::whisper::errors::RecognizeError;
+
+use actix_web::http::header;
 use actix_web::http::StatusCode;
 use actix_web::{HttpResponse, ResponseError};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use utoipa::ToSchema;
 
+/// Seconds clients are asked to wait before retrying when all engines are busy.
+const ENGINE_BUSY_RETRY_AFTER: u64 = 5;
+
 #[derive(Debug, Error)]
 pub enum WebError {
-    #[error("Failed wile extracting text data: {0}")]
-    ExtractTextFromAudio(String),
-    #[error("Response error: {0}")]
-    ResponseError(String),
+    #[error("Invalid multipart form: {0}")]
+    InvalidMultipart(String),
+    #[error("Unsupported media: {0}")]
+    UnsupportedMedia(String),
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+    #[error("Unknown model: {0}")]
+    UnknownModel(String),
+    #[error("Invalid parameters: {0}")]
+    InvalidParameters(String),
+    #[error("Engine is busy: {0}")]
+    EngineBusy(String),
+    #[error("Failed while decoding audio: {0}")]
+    DecodeFailed(String),
+    #[error("Failed while recognizing audio: {0}")]
+    InferenceFailed(String),
+    #[error("Job not found: {0}")]
+    JobNotFound(String),
+    #[error("Job is not finished yet: {0}")]
+    JobNotReady(String),
+    #[error("Job has failed: {0}")]
+    JobFailed(String),
+    #[error("Internal error: {0}")]
+    InternalError(String),
 }
 
 impl WebError {
+    /// Stable machine-readable error code returned as `error` field of response.
     pub fn name(&self) -> String {
         match self {
-            WebError::ExtractTextFromAudio(_) => "ExtractTextFromAudio",
-            _ => "RuntimeError",
+            WebError::InvalidMultipart(_) => "InvalidMultipart",
+            WebError::UnsupportedMedia(_) => "UnsupportedMedia",
+            WebError::PayloadTooLarge(_) => "PayloadTooLarge",
+            WebError::UnknownModel(_) => "UnknownModel",
+            WebError::InvalidParameters(_) => "InvalidParameters",
+            WebError::EngineBusy(_) => "EngineBusy",
+            WebError::DecodeFailed(_) => "DecodeFailed",
+            WebError::InferenceFailed(_) => "InferenceFailed",
+            WebError::JobNotFound(_) => "JobNotFound",
+            WebError::JobNotReady(_) => "JobNotReady",
+            WebError::JobFailed(_) => "JobFailed",
+            WebError::InternalError(_) => "InternalError",
         }
         .to_string()
     }
+
+    pub(crate) fn to_response(&self) -> ErrorResponse {
+        ErrorResponse {
+            code: self.status_code().as_u16(),
+            error: self.name(),
+            message: self.to_string(),
+        }
+    }
 }
 
 impl From<serde_json::Error> for WebError {
     fn from(value: serde_json::Error) -> Self {
-        WebError::ResponseError(value.to_string())
+        WebError::InternalError(value.to_string())
+    }
+}
+
+impl From<RecognizeError> for WebError {
+    fn from(value: RecognizeError) -> Self {
+        match value {
+            RecognizeError::UnknownModel(name) => WebError::UnknownModel(name),
+            RecognizeError::UnsupportedAudio(msg) => WebError::UnsupportedMedia(msg),
+            RecognizeError::EngineBusy(msg) => WebError::EngineBusy(msg),
+            RecognizeError::DecodeAudio(msg) => WebError::DecodeFailed(msg),
+            RecognizeError::ConvertAudio(msg) => WebError::DecodeFailed(msg),
+            RecognizeError::ConverterMissing(msg) => WebError::DecodeFailed(msg),
+            RecognizeError::TooLong(msg) => WebError::PayloadTooLarge(msg),
+            RecognizeError::Whisper(err) => WebError::InferenceFailed(err.to_string()),
+            RecognizeError::Interrupted(msg) => WebError::InferenceFailed(msg),
+            RecognizeError::PoolExhausted(msg) => WebError::InferenceFailed(msg),
+        }
+    }
+}
+
+impl From<JobError> for WebError {
+    fn from(value: JobError) -> Self {
+        match value {
+            JobError::NotFound(id) => WebError::JobNotFound(id),
+            JobError::NotReady(id) => WebError::JobNotReady(id),
+            JobError::Failed(msg) => WebError::JobFailed(msg),
+            JobError::Store(msg) => WebError::InternalError(msg),
+            JobError::Recognize(err) => WebError::from(err),
+        }
     }
 }
 
@@ -38,20 +113,28 @@ pub(crate) struct ErrorResponse {
 impl ResponseError for WebError {
     fn status_code(&self) -> StatusCode {
         match self {
-            WebError::ResponseError(_) => StatusCode::BAD_REQUEST,
-            WebError::ExtractTextFromAudio(_) => StatusCode::BAD_REQUEST,
+            WebError::InvalidMultipart(_) => StatusCode::BAD_REQUEST,
+            WebError::UnsupportedMedia(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            WebError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            WebError::UnknownModel(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            WebError::InvalidParameters(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            WebError::EngineBusy(_) => StatusCode::SERVICE_UNAVAILABLE,
+            WebError::DecodeFailed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            WebError::InferenceFailed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            WebError::JobNotFound(_) => StatusCode::NOT_FOUND,
+            WebError::JobNotReady(_) => StatusCode::CONFLICT,
+            WebError::JobFailed(_) => StatusCode::CONFLICT,
+            WebError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
     fn error_response(&self) -> HttpResponse {
-        let status_code = self.status_code();
-        let response = ErrorResponse {
-            code: status_code.as_u16(),
-            message: self.to_string(),
-            error: self.name(),
-        };
+        let mut response = HttpResponse::build(self.status_code());
+        if let WebError::EngineBusy(_) = self {
+            response.insert_header((header::RETRY_AFTER, ENGINE_BUSY_RETRY_AFTER));
+        }
 
-        HttpResponse::build(status_code).json(response)
+        response.json(self.to_response())
     }
 }
 
@@ -0,0 +1,89 @@
+use std::str::FromStr;
+
+const DEFAULT_WORKERS: usize = 1;
+const DEFAULT_CALLBACK_RETRIES: u32 = 5;
+const DEFAULT_CALLBACK_BACKOFF_MS: u64 = 1000;
+
+pub struct JobsConfig {
+    workers: usize,
+    store_dir: Option<String>,
+    callback_url: Option<String>,
+    callback_secret: Option<String>,
+    callback_retries: u32,
+    callback_backoff_ms: u64,
+    audio_retention_secs: Option<u64>,
+}
+
+impl JobsConfig {
+    pub fn from_env() -> Self {
+        // Jobs processed in parallel, the rest stays queued in the store.
+        let workers = std::env::var("JOBS_WORKERS")
+            .map(|value| usize::from_str(value.as_str()).expect("incorrect JOBS_WORKERS value"))
+            .unwrap_or(DEFAULT_WORKERS);
+
+        assert!(workers > 0, "JOBS_WORKERS must be greater than zero");
+
+        // Jobs are kept in memory only unless JOBS_STORE_DIR is set.
+        let store_dir = std::env::var("JOBS_STORE_DIR").ok();
+
+        // Callback url used for jobs submitted without their own `callback_url`.
+        let callback_url = std::env::var("JOBS_CALLBACK_URL").ok();
+        let callback_secret = std::env::var("JOBS_CALLBACK_SECRET").ok();
+        let callback_retries = std::env::var("JOBS_CALLBACK_RETRIES")
+            .map(|value| u32::from_str(value.as_str()).expect("incorrect JOBS_CALLBACK_RETRIES value"))
+            .unwrap_or(DEFAULT_CALLBACK_RETRIES);
+        let callback_backoff_ms = std::env::var("JOBS_CALLBACK_BACKOFF_MS")
+            .map(|value| u64::from_str(value.as_str()).expect("incorrect JOBS_CALLBACK_BACKOFF_MS value"))
+            .unwrap_or(DEFAULT_CALLBACK_BACKOFF_MS);
+
+        // Audio of finished jobs is kept as long as the job unless retention is set.
+        let audio_retention_secs = std::env::var("JOBS_AUDIO_RETENTION_SECS")
+            .map(|value| u64::from_str(value.as_str()).expect("incorrect JOBS_AUDIO_RETENTION_SECS value"))
+            .ok();
+
+        JobsConfig {
+            workers,
+            store_dir,
+            callback_url,
+            callback_secret,
+            callback_retries,
+            callback_backoff_ms,
+            audio_retention_secs,
+        }
+    }
+    pub fn get_workers(&self) -> usize {
+        self.workers
+    }
+    pub fn get_store_dir(&self) -> Option<&str> {
+        self.store_dir.as_deref()
+    }
+    pub fn get_callback_url(&self) -> Option<&str> {
+        self.callback_url.as_deref()
+    }
+    pub fn get_callback_secret(&self) -> Option<&str> {
+        self.callback_secret.as_deref()
+    }
+    pub fn get_callback_retries(&self) -> u32 {
+        self.callback_retries
+    }
+    pub fn get_callback_backoff_ms(&self) -> u64 {
+        self.callback_backoff_ms
+    }
+    pub fn get_audio_retention_secs(&self) -> Option<u64> {
+        self.audio_retention_secs
+    }
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        JobsConfig {
+            workers: DEFAULT_WORKERS,
+            store_dir: None,
+            callback_url: None,
+            callback_secret: None,
+            callback_retries: DEFAULT_CALLBACK_RETRIES,
+            callback_backoff_ms: DEFAULT_CALLBACK_BACKOFF_MS,
+            audio_retention_secs: None,
+        }
+    }
+}
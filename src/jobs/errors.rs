@@ -0,0 +1,19 @@
+use crate::whisper::errors::RecognizeError;
+
+use thiserror::Error;
+
+pub type JobResult<T> = Result<T, JobError>;
+
+#[derive(Debug, Error)]
+pub enum JobError {
+    #[error("Job not found: {0}")]
+    NotFound(String),
+    #[error("Job is not finished yet: {0}")]
+    NotReady(String),
+    #[error("Job has failed: {0}")]
+    Failed(String),
+    #[error("Failed while accessing job store: {0}")]
+    Store(String),
+    #[error(transparent)]
+    Recognize(#[from] RecognizeError),
+}
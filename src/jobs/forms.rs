@@ -0,0 +1,186 @@
+use crate::whisper::forms::{RecognizeParameters, RecognizeResponse, ResponseFormat};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Decoding,
+    Transcribing,
+    Done,
+    Failed,
+}
+
+impl JobState {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobState::Done | JobState::Failed)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobState::Queued => "queued",
+            JobState::Decoding => "decoding",
+            JobState::Transcribing => "transcribing",
+            JobState::Done => "done",
+            JobState::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for JobState {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "queued" => Ok(JobState::Queued),
+            "decoding" => Ok(JobState::Decoding),
+            "transcribing" => Ok(JobState::Transcribing),
+            "done" => Ok(JobState::Done),
+            "failed" => Ok(JobState::Failed),
+            _ => Err(format!("unknown job state: {}", value)),
+        }
+    }
+}
+
+/// Transcription job with uploaded audio, parameters and recognized result.
+#[derive(Clone)]
+pub struct Job {
+    pub id: String,
+    pub model: String,
+    pub file_path: String,
+    pub params: RecognizeParameters,
+    pub state: JobState,
+    pub error: Option<String>,
+    pub result: Option<Vec<RecognizeResponse>>,
+    pub callback_url: Option<String>,
+    pub callback_attempts: u32,
+    pub callback_delivered: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Job {
+    pub fn new(model: &str, file_path: &str, params: RecognizeParameters) -> Self {
+        let now = Utc::now();
+        Job {
+            id: uuid::Uuid::new_v4().to_string(),
+            model: model.to_string(),
+            file_path: file_path.to_string(),
+            params,
+            state: JobState::Queued,
+            error: None,
+            result: None,
+            callback_url: None,
+            callback_attempts: 0,
+            callback_delivered: false,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn set_state(&mut self, state: JobState) {
+        self.state = state;
+        self.updated_at = Utc::now();
+    }
+
+    pub fn complete(&mut self, result: Vec<RecognizeResponse>) {
+        self.result = Some(result);
+        self.set_state(JobState::Done);
+    }
+
+    pub fn fail(&mut self, error: String) {
+        self.error = Some(error);
+        self.set_state(JobState::Failed);
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct JobStatus {
+    pub id: String,
+    pub state: JobState,
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<&Job> for JobStatus {
+    fn from(job: &Job) -> Self {
+        JobStatus {
+            id: job.id.to_owned(),
+            state: job.state,
+            model: job.model.to_owned(),
+            error: job.error.to_owned(),
+            created_at: job.created_at,
+            updated_at: job.updated_at,
+        }
+    }
+}
+
+/// Body of signed POST request sent to callback url when job is finished.
+#[derive(Serialize, ToSchema)]
+pub struct JobCallback {
+    #[serde(flatten)]
+    pub status: JobStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Vec<RecognizeResponse>>,
+}
+
+impl From<&Job> for JobCallback {
+    fn from(job: &Job) -> Self {
+        JobCallback {
+            status: JobStatus::from(job),
+            result: job.result.to_owned(),
+        }
+    }
+}
+
+#[derive(Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobParameters {
+    /// Url receiving signed POST request when job is finished or failed, server default if missing
+    callback_url: Option<String>,
+}
+
+impl JobParameters {
+    pub fn get_callback_url(&self) -> Option<&str> {
+        self.callback_url.as_deref()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let Some(callback_url) = self.get_callback_url() else {
+            return Ok(());
+        };
+
+        match reqwest::Url::parse(callback_url) {
+            Ok(url) if ["http", "https"].contains(&url.scheme()) => Ok(()),
+            Ok(url) => Err(format!("unsupported callback_url scheme: {}", url.scheme())),
+            Err(err) => Err(format!("invalid callback_url: {}", err)),
+        }
+    }
+}
+
+#[derive(Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobResultQuery {
+    /// Concatenate chunked text to common
+    #[serde(default)]
+    concatenate: bool,
+    /// Response format: `json` segments, `srt`, `vtt` or `vtt_karaoke` subtitles
+    #[serde(default)]
+    format: ResponseFormat,
+}
+
+impl JobResultQuery {
+    pub fn is_concatenate_enable(&self) -> bool {
+        self.concatenate
+    }
+    pub fn get_format(&self) -> ResponseFormat {
+        self.format
+    }
+}
@@ -0,0 +1,18 @@
+use actix_web::{Scope, web};
+
+pub mod config;
+pub mod errors;
+pub mod forms;
+pub mod progress;
+pub mod routes;
+pub mod store;
+pub mod webhook;
+pub mod worker;
+
+pub fn build_scope() -> Scope {
+    web::scope("/jobs")
+        .service(routes::submit_job)
+        .service(routes::get_job)
+        .service(routes::get_job_result)
+        .service(routes::get_job_events)
+}
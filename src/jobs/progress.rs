@@ -0,0 +1,132 @@
+use crate::jobs::forms::{Job, JobState};
+use crate::whisper::engine::RecognizeObserver;
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+use tokio::sync::watch;
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressStage {
+    Upload,
+    Resample,
+    Inference,
+}
+
+/// Progress event sent to `/jobs/{id}/events` subscribers.
+#[derive(Clone, Serialize, ToSchema)]
+pub struct JobProgress {
+    pub id: String,
+    pub state: JobState,
+    pub stage: ProgressStage,
+    /// Completion of current stage in percent.
+    pub percent: i32,
+    /// Estimated seconds left, known once inference has started.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eta_secs: Option<f64>,
+}
+
+impl JobProgress {
+    pub fn new(job: &Job, stage: ProgressStage, percent: i32) -> Self {
+        JobProgress {
+            id: job.id.to_owned(),
+            state: job.state,
+            stage,
+            percent,
+            eta_secs: None,
+        }
+    }
+}
+
+impl From<&Job> for JobProgress {
+    fn from(job: &Job) -> Self {
+        match job.state {
+            JobState::Queued => JobProgress::new(job, ProgressStage::Upload, 100),
+            JobState::Decoding => JobProgress::new(job, ProgressStage::Resample, 0),
+            JobState::Transcribing => JobProgress::new(job, ProgressStage::Inference, 0),
+            JobState::Done => JobProgress::new(job, ProgressStage::Inference, 100),
+            JobState::Failed => JobProgress::new(job, ProgressStage::Inference, 0),
+        }
+    }
+}
+
+/// Keeps progress channels of jobs which are not finished yet.
+#[derive(Clone, Default)]
+pub struct ProgressRegistry {
+    channels: Arc<Mutex<HashMap<String, Arc<watch::Sender<JobProgress>>>>>,
+}
+
+impl ProgressRegistry {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Arc<watch::Sender<JobProgress>>>> {
+        self.channels.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn register(&self, job: &Job) {
+        let (sender, _) = watch::channel(JobProgress::from(job));
+        self.lock().insert(job.id.to_owned(), Arc::new(sender));
+    }
+
+    /// Returns receiver of job progress or `None` if job is not in progress.
+    pub fn subscribe(&self, job_id: &str) -> Option<watch::Receiver<JobProgress>> {
+        self.lock().get(job_id).map(|sender| sender.subscribe())
+    }
+
+    pub fn publish(&self, progress: JobProgress) {
+        if let Some(sender) = self.lock().get(progress.id.as_str()) {
+            sender.send_replace(progress);
+        }
+    }
+
+    /// Publishes final state and closes the channel, so subscribers are done.
+    pub fn finish(&self, job: &Job) {
+        if let Some(sender) = self.lock().remove(job.id.as_str()) {
+            let stage = sender.borrow().stage;
+            let percent = match job.state {
+                JobState::Done => 100,
+                _ => sender.borrow().percent,
+            };
+            sender.send_replace(JobProgress::new(job, stage, percent));
+        }
+    }
+
+    pub fn remove(&self, job_id: &str) {
+        self.lock().remove(job_id);
+    }
+
+    /// Creates observer which reports inference progress of the job.
+    pub fn observer(&self, job: &Job) -> Option<InferenceObserver> {
+        let sender = self.lock().get(job.id.as_str())?.clone();
+        Some(InferenceObserver {
+            sender,
+            started_at: Instant::now(),
+        })
+    }
+}
+
+/// Turns whisper progress callbacks into job progress with estimated time left.
+pub struct InferenceObserver {
+    sender: Arc<watch::Sender<JobProgress>>,
+    started_at: Instant,
+}
+
+impl RecognizeObserver for InferenceObserver {
+    fn on_progress(&self, percent: i32) {
+        let percent = percent.clamp(0, 100);
+        let eta_secs = match percent {
+            0 => None,
+            _ => {
+                let elapsed = self.started_at.elapsed().as_secs_f64();
+                Some(elapsed * (100 - percent) as f64 / percent as f64)
+            }
+        };
+
+        self.sender.send_modify(|progress| {
+            progress.stage = ProgressStage::Inference;
+            progress.percent = percent;
+            progress.eta_secs = eta_secs;
+        });
+    }
+}
@@ -0,0 +1,288 @@
+use crate::errors::{ErrorResponse, WebError};
+use crate::jobs::errors::JobError;
+use crate::jobs::forms::{Job, JobParameters, JobResultQuery, JobState, JobStatus};
+use crate::jobs::progress::JobProgress;
+use crate::whisper::forms::{RecognizeParameters, RecognizeQuery, ResponseFormat, SubtitleParameters};
+use crate::whisper::{helper, layout};
+use crate::{ContextData, JobsData, UploadsData};
+
+use actix_multipart::Multipart;
+use actix_web::http::{header, StatusCode};
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use futures_util::stream;
+
+#[utoipa::path(
+    post,
+    path = "/jobs",
+    tag = "Jobs",
+    params(RecognizeQuery, RecognizeParameters, JobParameters),
+    request_body(
+        content_type = "multipart/formdata",
+        content = Multipart,
+        example = "Uploads audio file and returns job id immediately. \
+            Recognize parameters may be passed as form fields as well.",
+    ),
+    responses(
+        (
+            status = 202,
+            description = "Job has been queued",
+            body = JobStatus,
+            example = json!({
+                "id": "1b4e28ba-2fa1-11d2-883f-0016d3cca427",
+                "state": "queued",
+                "model": "default",
+                "created_at": "2024-05-01T12:00:00Z",
+                "updated_at": "2024-05-01T12:00:00Z",
+            })
+        ),
+        (
+            status = 413,
+            description = "Uploaded file or its audio duration exceeds limits",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 413,
+                error: "PayloadTooLarge".to_string(),
+                message: "Payload too large: Failed while extracting file: exceeds 1073741824 bytes".to_string(),
+            })
+        ),
+        (
+            status = 415,
+            description = "Audio container is not allowed or codec is not supported",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 415,
+                error: "UnsupportedMedia".to_string(),
+                message: "Unsupported media: unknown audio container".to_string(),
+            })
+        ),
+        (
+            status = 422,
+            description = "Invalid recognize parameters or unknown model",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 422,
+                error: "UnknownModel".to_string(),
+                message: "Unknown model: large".to_string(),
+            })
+        ),
+    )
+)]
+#[post("")]
+pub async fn submit_job(
+    cxt: ContextData,
+    jobs: JobsData,
+    uploads: UploadsData,
+    req: HttpRequest,
+    payload: Multipart,
+) -> Result<HttpResponse, WebError> {
+    let form = helper::extract_multiform_data(payload, uploads.get_ref()).await?;
+
+    let query = helper::merge_parameters::<RecognizeQuery>(req.query_string(), &form.fields)
+        .map_err(|err| WebError::InvalidParameters(err.to_string()))?;
+    let mut params = helper::merge_parameters::<RecognizeParameters>(req.query_string(), &form.fields)
+        .map_err(|err| WebError::InvalidParameters(err.to_string()))?;
+    params.validate().map_err(WebError::InvalidParameters)?;
+    // Karaoke result of the job needs words, which are not recognized later.
+    if matches!(query.get_format(), ResponseFormat::VttKaraoke) {
+        params.set_word_timestamps_enable(true);
+    }
+    let job_params = helper::merge_parameters::<JobParameters>(req.query_string(), &form.fields)
+        .map_err(|err| WebError::InvalidParameters(err.to_string()))?;
+    job_params.validate().map_err(WebError::InvalidParameters)?;
+
+    let client = cxt.get_ref().get_model(query.get_model())?;
+    // Unsupported and too long audio is rejected at once rather than failing the job later.
+    client.check_file(form.file.path()).await?;
+
+    let mut job = Job::new(client.get_name(), form.file.path(), params);
+    job.callback_url = job_params.get_callback_url().map(str::to_string);
+    jobs.submit(&mut job, form.file).await?;
+
+    let response = HttpResponse::build(StatusCode::ACCEPTED)
+        .insert_header((header::LOCATION, format!("/jobs/{}", job.id)))
+        .json(JobStatus::from(&job));
+
+    Ok(response)
+}
+
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}",
+    tag = "Jobs",
+    params(("id" = String, Path, description = "Job id")),
+    responses(
+        (
+            status = 200,
+            description = "Successful",
+            body = JobStatus,
+            example = json!({
+                "id": "1b4e28ba-2fa1-11d2-883f-0016d3cca427",
+                "state": "transcribing",
+                "model": "default",
+                "created_at": "2024-05-01T12:00:00Z",
+                "updated_at": "2024-05-01T12:00:05Z",
+            })
+        ),
+        (
+            status = 404,
+            description = "Job not found",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 404,
+                error: "JobNotFound".to_string(),
+                message: "Job not found: 1b4e28ba-2fa1-11d2-883f-0016d3cca427".to_string(),
+            })
+        ),
+    )
+)]
+#[get("/{id}")]
+pub async fn get_job(jobs: JobsData, path: web::Path<String>) -> Result<HttpResponse, WebError> {
+    let job = jobs.get(path.as_str()).await?;
+    Ok(HttpResponse::build(StatusCode::OK).json(JobStatus::from(&job)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}/result",
+    tag = "Jobs",
+    params(
+        ("id" = String, Path, description = "Job id"),
+        JobResultQuery,
+        SubtitleParameters,
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Successful",
+            content(
+                ("application/json" = [RecognizeResponse], example = json!([
+                    {
+                        "frame_id": 0,
+                        "frame_start": 0,
+                        "frame_end": 3,
+                        "text": "Hello",
+                        "model": "default",
+                    }
+                ])),
+                ("application/x-subrip" = String),
+                ("text/vtt" = String),
+            )
+        ),
+        (
+            status = 404,
+            description = "Job not found",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 404,
+                error: "JobNotFound".to_string(),
+                message: "Job not found: 1b4e28ba-2fa1-11d2-883f-0016d3cca427".to_string(),
+            })
+        ),
+        (
+            status = 409,
+            description = "Job is not finished yet or has failed",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 409,
+                error: "JobNotReady".to_string(),
+                message: "Job is not finished yet: 1b4e28ba-2fa1-11d2-883f-0016d3cca427".to_string(),
+            })
+        ),
+    )
+)]
+#[get("/{id}/result")]
+pub async fn get_job_result(
+    jobs: JobsData,
+    path: web::Path<String>,
+    req: HttpRequest,
+) -> Result<HttpResponse, WebError> {
+    let query = web::Query::<JobResultQuery>::from_query(req.query_string())
+        .map_err(|err| WebError::InvalidParameters(err.to_string()))?;
+    let subtitles = web::Query::<SubtitleParameters>::from_query(req.query_string())
+        .map_err(|err| WebError::InvalidParameters(err.to_string()))?;
+    subtitles.validate().map_err(WebError::InvalidParameters)?;
+
+    let job = jobs.get(path.as_str()).await?;
+    let mut segments = match (job.state, job.result) {
+        (JobState::Done, Some(result)) => result,
+        (JobState::Failed, _) => {
+            let error = job.error.unwrap_or_default();
+            return Err(JobError::Failed(error).into());
+        }
+        _ => return Err(JobError::NotReady(job.id).into()),
+    };
+
+    if subtitles.is_reflow_enable() {
+        segments = layout::reflow(segments, &subtitles);
+    }
+
+    let response = helper::build_response(query.get_format(), query.is_concatenate_enable(), segments);
+    Ok(response)
+}
+
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}/events",
+    tag = "Jobs",
+    params(("id" = String, Path, description = "Job id")),
+    responses(
+        (
+            status = 200,
+            description = "Stream of `progress` server-sent events, closed once job is finished",
+            content_type = "text/event-stream",
+            body = JobProgress,
+            example = json!({
+                "id": "1b4e28ba-2fa1-11d2-883f-0016d3cca427",
+                "state": "transcribing",
+                "stage": "inference",
+                "percent": 40,
+                "eta_secs": 12.5,
+            })
+        ),
+        (
+            status = 404,
+            description = "Job not found",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 404,
+                error: "JobNotFound".to_string(),
+                message: "Job not found: 1b4e28ba-2fa1-11d2-883f-0016d3cca427".to_string(),
+            })
+        ),
+    )
+)]
+#[get("/{id}/events")]
+pub async fn get_job_events(jobs: JobsData, path: web::Path<String>) -> Result<HttpResponse, WebError> {
+    let job = jobs.get(path.as_str()).await?;
+
+    // Finished jobs have no progress channel, their stored state is sent at once.
+    let mut receiver = jobs.subscribe(job.id.as_str());
+    let initial = match receiver.as_mut() {
+        Some(receiver) => receiver.borrow_and_update().clone(),
+        None => JobProgress::from(&jobs.get(job.id.as_str()).await?),
+    };
+    let events = stream::unfold((Some(initial), receiver), |(pending, mut receiver)| async move {
+        let progress = match (pending, receiver.as_mut()) {
+            (Some(progress), _) => progress,
+            (None, Some(receiver)) => {
+                receiver.changed().await.ok()?;
+                receiver.borrow_and_update().clone()
+            }
+            (None, None) => return None,
+        };
+
+        if progress.state.is_finished() {
+            receiver = None;
+        }
+
+        let event = helper::StreamFormat::EventStream.frame("progress", &progress);
+        Some((event, (None, receiver)))
+    });
+
+    let response = HttpResponse::build(StatusCode::OK)
+        .content_type(helper::EVENT_STREAM_CONTENT_TYPE)
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(events);
+
+    Ok(response)
+}
@@ -0,0 +1,359 @@
+use crate::jobs::errors::{JobError, JobResult};
+use crate::jobs::forms::{Job, JobState};
+use crate::uploads::file::TempFile;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+const DATABASE_FILE_NAME: &str = "jobs.db";
+const AUDIO_DIR_NAME: &str = "audio";
+
+/// Keeps jobs with their states and results.
+pub trait JobStore: Send + Sync {
+    fn insert(&self, job: &Job) -> JobResult<()>;
+    fn update(&self, job: &Job) -> JobResult<()>;
+    fn get(&self, id: &str) -> JobResult<Option<Job>>;
+    /// Returns queued and interrupted jobs in submission order.
+    fn list_unfinished(&self) -> JobResult<Vec<Job>>;
+    /// Returns finished jobs whose callback is not delivered yet in submission order.
+    fn list_undelivered(&self) -> JobResult<Vec<Job>>;
+
+    /// Takes uploaded audio file and returns path where it is kept for the job.
+    fn keep_audio(&self, _job_id: &str, upload: TempFile) -> JobResult<String> {
+        upload.keep().map_err(store_error)
+    }
+
+    /// Directory of audio kept by the store itself rather than in upload directory.
+    fn audio_dir(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// Keeps jobs in process memory, they are lost after restart.
+#[derive(Default)]
+pub struct MemoryJobStore {
+    jobs: Mutex<HashMap<String, Job>>,
+}
+
+impl MemoryJobStore {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Job>> {
+        self.jobs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl JobStore for MemoryJobStore {
+    fn insert(&self, job: &Job) -> JobResult<()> {
+        self.lock().insert(job.id.to_owned(), job.clone());
+        Ok(())
+    }
+
+    fn update(&self, job: &Job) -> JobResult<()> {
+        match self.lock().get_mut(job.id.as_str()) {
+            Some(stored) => {
+                *stored = job.clone();
+                Ok(())
+            }
+            None => Err(JobError::NotFound(job.id.to_owned())),
+        }
+    }
+
+    fn get(&self, id: &str) -> JobResult<Option<Job>> {
+        Ok(self.lock().get(id).cloned())
+    }
+
+    fn list_unfinished(&self) -> JobResult<Vec<Job>> {
+        let mut jobs = self
+            .lock()
+            .values()
+            .filter(|job| !job.state.is_finished())
+            .cloned()
+            .collect::<Vec<Job>>();
+
+        jobs.sort_by_key(|job| job.created_at);
+        Ok(jobs)
+    }
+
+    fn list_undelivered(&self) -> JobResult<Vec<Job>> {
+        let mut jobs = self
+            .lock()
+            .values()
+            .filter(|job| job.state.is_finished() && !job.callback_delivered)
+            .cloned()
+            .collect::<Vec<Job>>();
+
+        jobs.sort_by_key(|job| job.created_at);
+        Ok(jobs)
+    }
+}
+
+/// Keeps jobs in SQLite database and their audio files next to it, so jobs
+/// survive restarts and finished transcripts may be fetched later. Audio of
+/// finished jobs is kept until `JOBS_AUDIO_RETENTION_SECS` passes.
+pub struct SqliteJobStore {
+    connection: Mutex<Connection>,
+    audio_dir: PathBuf,
+}
+
+impl SqliteJobStore {
+    pub fn open(store_dir: &str) -> JobResult<Self> {
+        let audio_dir = Path::new(store_dir).join(AUDIO_DIR_NAME);
+        std::fs::create_dir_all(audio_dir.as_path()).map_err(store_error)?;
+
+        let connection = Connection::open(Path::new(store_dir).join(DATABASE_FILE_NAME))
+            .map_err(store_error)?;
+
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS jobs (
+                    id          TEXT PRIMARY KEY,
+                    model       TEXT NOT NULL,
+                    file_path   TEXT NOT NULL,
+                    params      TEXT NOT NULL,
+                    state       TEXT NOT NULL,
+                    error       TEXT,
+                    result      TEXT,
+                    created_at  TEXT NOT NULL,
+                    updated_at  TEXT NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS jobs_state ON jobs (state, created_at);",
+            )
+            .map_err(store_error)?;
+
+        Self::add_column(&connection, "callback_url", "TEXT")?;
+        Self::add_column(&connection, "callback_attempts", "INTEGER NOT NULL DEFAULT 0")?;
+        // Callbacks of jobs stored by previous versions are not tracked, so they are not resumed.
+        Self::add_column(&connection, "callback_delivered", "INTEGER NOT NULL DEFAULT 1")?;
+
+        Ok(SqliteJobStore {
+            connection: Mutex::new(connection),
+            audio_dir,
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Adds column missing in databases created by previous versions.
+    fn add_column(connection: &Connection, name: &str, definition: &str) -> JobResult<()> {
+        let mut statement = connection
+            .prepare("SELECT name FROM pragma_table_info('jobs')")
+            .map_err(store_error)?;
+        let columns = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(store_error)?
+            .collect::<rusqlite::Result<Vec<String>>>()
+            .map_err(store_error)?;
+
+        if !columns.iter().any(|column| column == name) {
+            let query = format!("ALTER TABLE jobs ADD COLUMN {} {}", name, definition);
+            connection.execute(query.as_str(), []).map_err(store_error)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_job(row: &Row) -> rusqlite::Result<JobRow> {
+        Ok(JobRow {
+            id: row.get("id")?,
+            model: row.get("model")?,
+            file_path: row.get("file_path")?,
+            params: row.get("params")?,
+            state: row.get("state")?,
+            error: row.get("error")?,
+            result: row.get("result")?,
+            callback_url: row.get("callback_url")?,
+            callback_attempts: row.get("callback_attempts")?,
+            callback_delivered: row.get("callback_delivered")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+}
+
+impl JobStore for SqliteJobStore {
+    fn insert(&self, job: &Job) -> JobResult<()> {
+        let row = JobRow::try_from(job)?;
+        self.lock()
+            .execute(
+                "INSERT INTO jobs (id, model, file_path, params, state, error, result, callback_url,
+                    callback_attempts, callback_delivered, created_at, updated_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    row.id, row.model, row.file_path, row.params, row.state, row.error, row.result,
+                    row.callback_url, row.callback_attempts, row.callback_delivered, row.created_at, row.updated_at
+                ],
+            )
+            .map_err(store_error)?;
+
+        Ok(())
+    }
+
+    fn update(&self, job: &Job) -> JobResult<()> {
+        let row = JobRow::try_from(job)?;
+        let updated = self
+            .lock()
+            .execute(
+                "UPDATE jobs SET state = ?2, error = ?3, result = ?4, callback_attempts = ?5,
+                    callback_delivered = ?6, updated_at = ?7 WHERE id = ?1",
+                params![
+                    row.id, row.state, row.error, row.result,
+                    row.callback_attempts, row.callback_delivered, row.updated_at
+                ],
+            )
+            .map_err(store_error)?;
+
+        match updated {
+            0 => Err(JobError::NotFound(job.id.to_owned())),
+            _ => Ok(()),
+        }
+    }
+
+    fn get(&self, id: &str) -> JobResult<Option<Job>> {
+        let row = self
+            .lock()
+            .query_row("SELECT * FROM jobs WHERE id = ?1", [id], Self::read_job)
+            .optional()
+            .map_err(store_error)?;
+
+        row.map(Job::try_from).transpose()
+    }
+
+    fn list_unfinished(&self) -> JobResult<Vec<Job>> {
+        let connection = self.lock();
+        let mut statement = connection
+            .prepare("SELECT * FROM jobs WHERE state NOT IN (?1, ?2) ORDER BY created_at")
+            .map_err(store_error)?;
+
+        let finished = [JobState::Done.as_str(), JobState::Failed.as_str()];
+        let rows = statement
+            .query_map(finished, Self::read_job)
+            .map_err(store_error)?
+            .collect::<rusqlite::Result<Vec<JobRow>>>()
+            .map_err(store_error)?;
+
+        rows.into_iter().map(Job::try_from).collect()
+    }
+
+    fn list_undelivered(&self) -> JobResult<Vec<Job>> {
+        let connection = self.lock();
+        let mut statement = connection
+            .prepare("SELECT * FROM jobs WHERE state IN (?1, ?2) AND callback_delivered = 0 ORDER BY created_at")
+            .map_err(store_error)?;
+
+        let finished = [JobState::Done.as_str(), JobState::Failed.as_str()];
+        let rows = statement
+            .query_map(finished, Self::read_job)
+            .map_err(store_error)?
+            .collect::<rusqlite::Result<Vec<JobRow>>>()
+            .map_err(store_error)?;
+
+        rows.into_iter().map(Job::try_from).collect()
+    }
+
+    fn keep_audio(&self, job_id: &str, upload: TempFile) -> JobResult<String> {
+        let file_path = upload.path();
+        let extension = Path::new(file_path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| format!(".{}", extension))
+            .unwrap_or_default();
+
+        let audio_path = self.audio_dir.join(format!("{}{}", job_id, extension));
+        move_file(Path::new(file_path), audio_path.as_path()).map_err(store_error)?;
+        Ok(audio_path.to_string_lossy().to_string())
+    }
+
+    fn audio_dir(&self) -> Option<&Path> {
+        Some(self.audio_dir.as_path())
+    }
+}
+
+/// Plain column values of `jobs` table.
+struct JobRow {
+    id: String,
+    model: String,
+    file_path: String,
+    params: String,
+    state: String,
+    error: Option<String>,
+    result: Option<String>,
+    callback_url: Option<String>,
+    callback_attempts: u32,
+    callback_delivered: bool,
+    created_at: chrono::DateTime<chrono::Utc>,
+    updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl TryFrom<&Job> for JobRow {
+    type Error = JobError;
+
+    fn try_from(job: &Job) -> Result<Self, Self::Error> {
+        let result = job
+            .result
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(store_error)?;
+
+        Ok(JobRow {
+            id: job.id.to_owned(),
+            model: job.model.to_owned(),
+            file_path: job.file_path.to_owned(),
+            params: serde_json::to_string(&job.params).map_err(store_error)?,
+            state: job.state.as_str().to_string(),
+            error: job.error.to_owned(),
+            result,
+            callback_url: job.callback_url.to_owned(),
+            callback_attempts: job.callback_attempts,
+            callback_delivered: job.callback_delivered,
+            created_at: job.created_at,
+            updated_at: job.updated_at,
+        })
+    }
+}
+
+impl TryFrom<JobRow> for Job {
+    type Error = JobError;
+
+    fn try_from(row: JobRow) -> Result<Self, Self::Error> {
+        let result = row
+            .result
+            .as_deref()
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(store_error)?;
+
+        Ok(Job {
+            id: row.id,
+            model: row.model,
+            file_path: row.file_path,
+            params: serde_json::from_str(row.params.as_str()).map_err(store_error)?,
+            state: JobState::from_str(row.state.as_str()).map_err(JobError::Store)?,
+            error: row.error,
+            result,
+            callback_url: row.callback_url,
+            callback_attempts: row.callback_attempts,
+            callback_delivered: row.callback_delivered,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+/// Renames file falling back to copying when directories are on different devices.
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    std::fs::copy(from, to)?;
+    std::fs::remove_file(from)
+}
+
+fn store_error<T: std::fmt::Display>(err: T) -> JobError {
+    JobError::Store(err.to_string())
+}
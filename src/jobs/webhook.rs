@@ -0,0 +1,142 @@
+use crate::jobs::config::JobsConfig;
+use crate::jobs::errors::JobError;
+use crate::jobs::forms::{Job, JobCallback};
+use crate::jobs::store::JobStore;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+
+const REQUEST_TIMEOUT_SECS: u64 = 30;
+const JOB_ID_HEADER: &str = "X-Job-Id";
+const SIGNATURE_HEADER: &str = "X-Signature";
+
+/// Posts finished jobs to their callback urls. When secret is set, the body
+/// is signed with HMAC-SHA256 and passed as `X-Signature: sha256=<hex>`.
+#[derive(Clone)]
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    store: Option<Arc<dyn JobStore>>,
+    default_url: Option<String>,
+    secret: Option<String>,
+    retries: u32,
+    backoff_ms: u64,
+}
+
+impl WebhookNotifier {
+    pub fn new(cfg: &JobsConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()
+            .expect("failed to build webhook http client");
+
+        WebhookNotifier {
+            client,
+            store: None,
+            default_url: cfg.get_callback_url().map(str::to_string),
+            secret: cfg.get_callback_secret().map(str::to_string),
+            retries: cfg.get_callback_retries(),
+            backoff_ms: cfg.get_callback_backoff_ms(),
+        }
+    }
+
+    /// Stores delivery state of jobs after every attempt, so delivery interrupted
+    /// by restart is resumed with attempts left.
+    pub fn with_store(mut self, store: Arc<dyn JobStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Returns callback url of the job or the default one.
+    pub fn get_url<'a>(&'a self, job: &'a Job) -> Option<&'a str> {
+        job.callback_url.as_deref().or(self.default_url.as_deref())
+    }
+
+    /// Checks the job has callback which is neither delivered nor out of attempts.
+    pub fn is_pending(&self, job: &Job) -> bool {
+        !job.callback_delivered && job.callback_attempts <= self.retries && self.get_url(job).is_some()
+    }
+
+    /// Sends job status with transcript, retrying failed attempts with exponential backoff.
+    /// Attempts made before, e.g. by previous process, are counted too.
+    pub async fn notify(&self, job: &mut Job) {
+        if !self.is_pending(job) {
+            return;
+        }
+
+        let url = self.get_url(job).unwrap_or_default().to_string();
+        let body = match serde_json::to_vec(&JobCallback::from(&*job)) {
+            Ok(body) => body,
+            Err(err) => {
+                log::error!("Failed while serializing callback of job {}: {}", job.id, err);
+                return;
+            }
+        };
+
+        loop {
+            let send_res = self.send(url.as_str(), job.id.as_str(), &body).await;
+            job.callback_attempts += 1;
+            job.callback_delivered = send_res.is_ok();
+            self.store_delivery(job).await;
+
+            let Err(err) = send_res else {
+                return;
+            };
+
+            if job.callback_attempts > self.retries {
+                log::error!("Failed while sending callback of job {} to {}: {}", job.id, url, err);
+                return;
+            }
+
+            let delay = self.backoff_ms.saturating_mul(1 << (job.callback_attempts - 1).min(16));
+            log::warn!(
+                "Callback of job {} failed: {}, retrying in {} ms",
+                job.id,
+                err,
+                delay
+            );
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+        }
+    }
+
+    async fn store_delivery(&self, job: &Job) {
+        let Some(store) = self.store.clone() else {
+            return;
+        };
+
+        let stored = job.clone();
+        let store_res = tokio::task::spawn_blocking(move || store.update(&stored))
+            .await
+            .map_err(|err| JobError::Store(err.to_string()))
+            .and_then(|store_res| store_res);
+        if let Err(err) = store_res {
+            log::error!("Failed while storing callback state of job {}: {}", job.id, err);
+        }
+    }
+
+    async fn send(&self, url: &str, job_id: &str, body: &[u8]) -> Result<(), String> {
+        let mut request = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(JOB_ID_HEADER, job_id)
+            .body(body.to_vec());
+
+        if let Some(secret) = self.secret.as_deref() {
+            request = request.header(SIGNATURE_HEADER, format!("sha256={}", sign(secret, body)));
+        }
+
+        let response = request.send().await.map_err(|err| err.to_string())?;
+        match response.status().is_success() {
+            true => Ok(()),
+            false => Err(format!("unexpected response status {}", response.status())),
+        }
+    }
+}
+
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
@@ -0,0 +1,243 @@
+use crate::jobs::config::JobsConfig;
+use crate::jobs::errors::{JobError, JobResult};
+use crate::jobs::forms::{Job, JobState};
+use crate::jobs::progress::{JobProgress, ProgressRegistry, ProgressStage};
+use crate::jobs::store::JobStore;
+use crate::jobs::webhook::WebhookNotifier;
+use crate::uploads::file::TempFile;
+use crate::whisper::client_async::WhisperAsyncClient;
+use crate::whisper::engine::RecognizeObserver;
+use crate::whisper::forms::RecognizeResponse;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch, Semaphore};
+
+/// Accepts jobs into the store and hands them over to the worker loop.
+#[derive(Clone)]
+pub struct JobQueue {
+    store: Arc<dyn JobStore>,
+    sender: mpsc::UnboundedSender<String>,
+    progress: ProgressRegistry,
+    audio_retention: Option<Duration>,
+}
+
+impl JobQueue {
+    /// Spawns worker loop which processes at most `JOBS_WORKERS` jobs in parallel.
+    pub fn new<S: JobStore + 'static>(store: S, client: WhisperAsyncClient, cfg: &JobsConfig) -> Self {
+        let store: Arc<dyn JobStore> = Arc::new(store);
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let progress = ProgressRegistry::default();
+
+        let notifier = WebhookNotifier::new(cfg).with_store(store.clone());
+
+        let worker = JobWorker {
+            store: store.clone(),
+            client,
+            progress: progress.clone(),
+            notifier: notifier.clone(),
+            permits: Arc::new(Semaphore::new(cfg.get_workers())),
+        };
+        tokio::spawn(worker.run(receiver));
+
+        let queue = JobQueue {
+            store,
+            sender,
+            progress,
+            audio_retention: cfg.get_audio_retention_secs().map(Duration::from_secs),
+        };
+        if let Err(err) = queue.resume() {
+            log::error!("Failed while resuming unfinished jobs: {}", err);
+        }
+        if let Err(err) = queue.resume_callbacks(&notifier) {
+            log::error!("Failed while resuming undelivered callbacks: {}", err);
+        }
+
+        queue
+    }
+
+    /// Moves uploaded audio into the store and queues the job.
+    pub async fn submit(&self, job: &mut Job, upload: TempFile) -> JobResult<()> {
+        let job_id = job.id.to_owned();
+        job.file_path = run_blocking(&self.store, move |store| store.keep_audio(job_id.as_str(), upload)).await?;
+
+        let stored = job.clone();
+        run_blocking(&self.store, move |store| store.insert(&stored)).await?;
+        self.enqueue(job)
+    }
+
+    pub async fn get(&self, id: &str) -> JobResult<Job> {
+        let job_id = id.to_string();
+        run_blocking(&self.store, move |store| store.get(job_id.as_str()))
+            .await?
+            .ok_or_else(|| JobError::NotFound(id.to_string()))
+    }
+
+    /// Returns audio paths of jobs which are not finished yet.
+    pub async fn list_audio_paths(&self) -> JobResult<Vec<String>> {
+        let jobs = run_blocking(&self.store, |store| store.list_unfinished()).await?;
+        Ok(jobs.into_iter().map(|job| job.file_path).collect())
+    }
+
+    /// Returns directory of audio kept by the store, if it is not in upload directory.
+    pub fn get_audio_dir(&self) -> Option<PathBuf> {
+        self.store.audio_dir().map(Path::to_path_buf)
+    }
+
+    /// Returns how long audio of finished jobs is kept, it is kept forever if not set.
+    pub fn get_audio_retention(&self) -> Option<Duration> {
+        self.audio_retention
+    }
+
+    /// Returns progress receiver of the job unless it is finished already.
+    pub fn subscribe(&self, id: &str) -> Option<watch::Receiver<JobProgress>> {
+        self.progress.subscribe(id)
+    }
+
+    /// Queues jobs left unfinished by previous process again. It runs once
+    /// on start before requests are served, so the store is called directly.
+    fn resume(&self) -> JobResult<()> {
+        let jobs = self.store.list_unfinished()?;
+        if !jobs.is_empty() {
+            log::info!("Resuming {} unfinished jobs", jobs.len());
+        }
+
+        for mut job in jobs {
+            job.set_state(JobState::Queued);
+            self.store.update(&job)?;
+            self.enqueue(&job)?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends callbacks of finished jobs which were not delivered by previous process.
+    fn resume_callbacks(&self, notifier: &WebhookNotifier) -> JobResult<()> {
+        let jobs = self
+            .store
+            .list_undelivered()?
+            .into_iter()
+            .filter(|job| notifier.is_pending(job))
+            .collect::<Vec<Job>>();
+        if !jobs.is_empty() {
+            log::info!("Resuming {} undelivered callbacks", jobs.len());
+        }
+
+        for mut job in jobs {
+            let notifier = notifier.clone();
+            tokio::spawn(async move { notifier.notify(&mut job).await });
+        }
+
+        Ok(())
+    }
+
+    fn enqueue(&self, job: &Job) -> JobResult<()> {
+        self.progress.register(job);
+        self.sender
+            .send(job.id.to_owned())
+            .map_err(|err| JobError::Store(err.to_string()))
+    }
+}
+
+struct JobWorker {
+    store: Arc<dyn JobStore>,
+    client: WhisperAsyncClient,
+    progress: ProgressRegistry,
+    notifier: WebhookNotifier,
+    permits: Arc<Semaphore>,
+}
+
+impl JobWorker {
+    async fn run(self, mut receiver: mpsc::UnboundedReceiver<String>) {
+        let worker = Arc::new(self);
+        while let Some(job_id) = receiver.recv().await {
+            let Ok(permit) = worker.permits.clone().acquire_owned().await else {
+                break;
+            };
+
+            let worker = worker.clone();
+            tokio::spawn(async move {
+                worker.process(job_id.as_str()).await;
+                drop(permit);
+            });
+        }
+    }
+
+    async fn process(&self, job_id: &str) {
+        let stored_id = job_id.to_string();
+        let mut job = match run_blocking(&self.store, move |store| store.get(stored_id.as_str())).await {
+            Ok(Some(job)) => job,
+            Ok(None) => {
+                log::warn!("Skipped job {} which is missing in store", job_id);
+                self.progress.remove(job_id);
+                return;
+            }
+            Err(err) => {
+                log::error!("Failed while loading job {}: {}", job_id, err);
+                self.progress.remove(job_id);
+                return;
+            }
+        };
+
+        match self.transcribe(&mut job).await {
+            Ok(result) => job.complete(result),
+            Err(err) => {
+                log::error!("Failed while processing job {}: {}", job_id, err);
+                job.fail(err.to_string());
+            }
+        }
+
+        // Jobs without callback url have nothing to deliver, so they are never resumed.
+        job.callback_delivered = self.notifier.get_url(&job).is_none();
+        if let Err(err) = self.update(&job).await {
+            log::error!("Failed while storing job {}: {}", job_id, err);
+        }
+
+        self.progress.finish(&job);
+
+        let notifier = self.notifier.clone();
+        tokio::spawn(async move { notifier.notify(&mut job).await });
+    }
+
+    async fn transcribe(&self, job: &mut Job) -> JobResult<Vec<RecognizeResponse>> {
+        let client = self.client.get_model(Some(job.model.as_str()))?;
+
+        job.set_state(JobState::Decoding);
+        self.update(job).await?;
+        self.progress.publish(JobProgress::new(job, ProgressStage::Resample, 0));
+        let channels = client.decode_channels(job.file_path.as_str(), &job.params).await?;
+        self.progress.publish(JobProgress::new(job, ProgressStage::Resample, 100));
+
+        job.set_state(JobState::Transcribing);
+        self.update(job).await?;
+        self.progress.publish(JobProgress::new(job, ProgressStage::Inference, 0));
+        let observer = self
+            .progress
+            .observer(job)
+            .map(|observer| Arc::new(observer) as Arc<dyn RecognizeObserver>);
+        let result = client
+            .recognize_channels(channels, &job.params, observer)
+            .await?;
+
+        Ok(result)
+    }
+
+    async fn update(&self, job: &Job) -> JobResult<()> {
+        let job = job.clone();
+        run_blocking(&self.store, move |store| store.update(&job)).await
+    }
+}
+
+/// Runs store call on blocking threads, so disk I/O of SQLite store does not
+/// stall async workers.
+async fn run_blocking<F, T>(store: &Arc<dyn JobStore>, task: F) -> JobResult<T>
+where
+    F: FnOnce(&dyn JobStore) -> JobResult<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let store = store.clone();
+    tokio::task::spawn_blocking(move || task(store.as_ref()))
+        .await
+        .map_err(|err| JobError::Store(err.to_string()))?
+}
@@ -1,14 +1,21 @@
 pub mod errors;
 pub mod mws;
+pub mod openai;
 pub mod swagger;
 pub mod ws;
 pub mod healthcheck;
+pub mod jobs;
+pub mod uploads;
 pub mod whisper;
 
 use crate::errors::WebError;
+use crate::jobs::worker::JobQueue;
+use crate::uploads::config::UploadsConfig;
 use crate::whisper::client_async::WhisperAsyncClient;
 
 use actix_web::web::{Data, Json};
 
 pub type ContextData = Data<Box<WhisperAsyncClient>>;
+pub type JobsData = Data<JobQueue>;
+pub type UploadsData = Data<UploadsConfig>;
 pub type RecognizeData<T> = Result<Json<T>, WebError>;
@@ -0,0 +1,62 @@
+use crate::errors::WebError;
+use crate::whisper::errors::RecognizeError;
+
+use actix_web::body::BoxBody;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+/// Wraps service errors into error envelope expected by OpenAI clients.
+#[derive(Debug, Error)]
+#[error(transparent)]
+pub struct OpenAiError(#[from] WebError);
+
+impl From<RecognizeError> for OpenAiError {
+    fn from(value: RecognizeError) -> Self {
+        OpenAiError(WebError::from(value))
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct OpenAiErrorResponse {
+    pub error: OpenAiErrorDetails,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct OpenAiErrorDetails {
+    pub message: String,
+    #[serde(rename = "type")]
+    pub error_type: String,
+    pub param: Option<String>,
+    pub code: String,
+}
+
+impl ResponseError for OpenAiError {
+    fn status_code(&self) -> StatusCode {
+        self.0.status_code()
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let error_type = match self.status_code().is_server_error() {
+            true => "server_error",
+            false => "invalid_request_error",
+        };
+
+        let response = OpenAiErrorResponse {
+            error: OpenAiErrorDetails {
+                message: self.0.to_string(),
+                error_type: error_type.to_string(),
+                param: None,
+                code: self.0.name(),
+            },
+        };
+
+        // Keeps status and headers like `Retry-After` of the wrapped error.
+        match serde_json::to_string(&response) {
+            Ok(body) => self.0.error_response().set_body(BoxBody::new(body)),
+            Err(_) => self.0.error_response(),
+        }
+    }
+}
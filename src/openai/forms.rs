@@ -0,0 +1,164 @@
+use crate::whisper::forms::{RecognizeParameters, RecognizeResponse, RecognizedWord};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+pub const GRANULARITY_WORD: &str = "word";
+pub const GRANULARITY_SEGMENT: &str = "segment";
+
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptionFormat {
+    #[default]
+    Json,
+    Text,
+    Srt,
+    VerboseJson,
+    Vtt,
+}
+
+/// Multipart fields of OpenAI transcription and translation requests.
+#[derive(Default, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct TranscriptionForm {
+    /// Audio file to recognize
+    #[schema(value_type = String, format = Binary)]
+    #[serde(skip)]
+    file: (),
+    /// Name of loaded model, unknown names like `whisper-1` fall back to the default model
+    model: Option<String>,
+    /// Spoken language code like `en`, detected automatically if missing
+    language: Option<String>,
+    /// Text to guide style or vocabulary of transcription, only its last 224 tokens are used
+    prompt: Option<String>,
+    /// Output format: `json`, `text`, `srt`, `verbose_json` or `vtt`
+    response_format: TranscriptionFormat,
+    /// Sampling temperature from 0.0 to 1.0
+    temperature: f32,
+    /// Comma separated timestamp granularities `segment`, `word` for `verbose_json` format
+    #[serde(rename = "timestamp_granularities[]", alias = "timestamp_granularities")]
+    timestamp_granularities: Option<String>,
+}
+
+impl TranscriptionForm {
+    pub fn get_model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+    pub fn get_response_format(&self) -> TranscriptionFormat {
+        self.response_format
+    }
+    pub fn get_timestamp_granularities(&self) -> Vec<&str> {
+        self.timestamp_granularities
+            .as_deref()
+            .map(|values| values.split(',').map(str::trim).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let granularities = self.get_timestamp_granularities();
+        let unknown = granularities
+            .iter()
+            .find(|value| ![GRANULARITY_WORD, GRANULARITY_SEGMENT].contains(value));
+        if let Some(value) = unknown {
+            return Err(format!("unsupported timestamp granularity: {}", value));
+        }
+
+        if !granularities.is_empty() && self.response_format != TranscriptionFormat::VerboseJson {
+            return Err("timestamp_granularities requires verbose_json response format".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Maps OpenAI fields onto whisper recognize parameters.
+    pub fn to_parameters(&self, enable_translate: bool) -> RecognizeParameters {
+        let mut params = RecognizeParameters::default();
+        params.set_lang(self.language.to_owned());
+        params.set_translate_enable(enable_translate);
+        params.set_initial_prompt(self.prompt.to_owned());
+        params.set_temperature(self.temperature);
+        params.set_word_timestamps_enable(self.is_granularity_requested(GRANULARITY_WORD));
+        params.set_confidence_enable(self.response_format == TranscriptionFormat::VerboseJson);
+        params
+    }
+
+    /// Segments are returned by default, so they are omitted only if just words are requested.
+    pub fn is_granularity_requested(&self, granularity: &str) -> bool {
+        let granularities = self.get_timestamp_granularities();
+        match granularity {
+            GRANULARITY_SEGMENT => granularities.is_empty() || granularities.contains(&granularity),
+            _ => granularities.contains(&granularity),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Transcription {
+    pub text: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct VerboseTranscription {
+    pub task: String,
+    pub language: String,
+    /// Duration of decoded audio in seconds
+    pub duration: f64,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segments: Option<Vec<TranscriptionSegment>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub words: Option<Vec<TranscriptionWord>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TranscriptionWord {
+    pub word: String,
+    /// Start of word in seconds
+    pub start: f64,
+    /// End of word in seconds
+    pub end: f64,
+}
+
+impl From<&RecognizedWord> for TranscriptionWord {
+    fn from(word: &RecognizedWord) -> Self {
+        TranscriptionWord {
+            word: word.word.to_owned(),
+            start: word.start as f64 / 100.0,
+            end: word.end as f64 / 100.0,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TranscriptionSegment {
+    pub id: i32,
+    pub seek: i64,
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    pub tokens: Vec<i32>,
+    pub temperature: f32,
+    pub avg_logprob: f32,
+    pub compression_ratio: f32,
+    /// Omitted since whisper.cpp does not expose probability of no speech token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_speech_prob: Option<f32>,
+}
+
+impl TranscriptionSegment {
+    pub fn new(segment: &RecognizeResponse, temperature: f32) -> Self {
+        let confidence = segment.confidence.as_ref();
+        TranscriptionSegment {
+            id: segment.frame_id,
+            seek: segment.frame_start,
+            start: segment.frame_start as f64 / 100.0,
+            end: segment.frame_end as f64 / 100.0,
+            text: segment.text.to_owned(),
+            tokens: Vec::new(),
+            temperature,
+            avg_logprob: confidence.map_or(0.0, |confidence| confidence.avg_logprob),
+            compression_ratio: confidence.map_or(0.0, |confidence| confidence.compression_ratio),
+            no_speech_prob: confidence.and_then(|confidence| confidence.no_speech_prob),
+        }
+    }
+}
@@ -0,0 +1,11 @@
+use actix_web::{Scope, web};
+
+pub mod errors;
+pub mod forms;
+pub mod routes;
+
+pub fn build_scope() -> Scope {
+    web::scope("/v1/audio")
+        .service(routes::transcriptions)
+        .service(routes::translations)
+}
@@ -0,0 +1,243 @@
+use crate::errors::WebError;
+use crate::openai::errors::OpenAiError;
+use crate::openai::forms::*;
+use crate::whisper::client_async::ModelClient;
+use crate::whisper::forms::{RecognizeParameters, RecognizeResponse};
+use crate::whisper::{helper, subtitles};
+use crate::{ContextData, UploadsData};
+
+use actix_multipart::Multipart;
+use actix_web::http::StatusCode;
+use actix_web::{post, HttpResponse};
+
+const TEXT_CONTENT_TYPE: &str = "text/plain; charset=utf-8";
+
+#[utoipa::path(
+    post,
+    path = "/v1/audio/transcriptions",
+    tag = "OpenAI",
+    request_body(
+        content_type = "multipart/form-data",
+        content = TranscriptionForm,
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Successful",
+            content(
+                ("application/json" = Transcription, example = json!({
+                    "text": "Hello world",
+                })),
+                ("text/plain" = String, example = json!("Hello world")),
+            )
+        ),
+        (
+            status = 413,
+            description = "Uploaded file or audio duration exceeds limits",
+            body = OpenAiErrorResponse,
+            example = json!({
+                "error": {
+                    "message": "Payload too large: Audio is too long: 7260 seconds exceed 3600 seconds",
+                    "type": "invalid_request_error",
+                    "param": null,
+                    "code": "PayloadTooLarge",
+                }
+            })
+        ),
+        (
+            status = 415,
+            description = "Audio container is not allowed or codec is not supported",
+            body = OpenAiErrorResponse,
+            example = json!({
+                "error": {
+                    "message": "Unsupported media: unknown audio container",
+                    "type": "invalid_request_error",
+                    "param": null,
+                    "code": "UnsupportedMedia",
+                }
+            })
+        ),
+        (
+            status = 422,
+            description = "Invalid request fields",
+            body = OpenAiErrorResponse,
+            example = json!({
+                "error": {
+                    "message": "Invalid parameters: unsupported timestamp granularity: char",
+                    "type": "invalid_request_error",
+                    "param": null,
+                    "code": "InvalidParameters",
+                }
+            })
+        ),
+    )
+)]
+#[post("/transcriptions")]
+pub async fn transcriptions(
+    cxt: ContextData,
+    uploads: UploadsData,
+    payload: Multipart,
+) -> Result<HttpResponse, OpenAiError> {
+    recognize(cxt, uploads, payload, false).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/audio/translations",
+    tag = "OpenAI",
+    request_body(
+        content_type = "multipart/form-data",
+        content = TranscriptionForm,
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Successful",
+            content(
+                ("application/json" = Transcription, example = json!({
+                    "text": "Hello world",
+                })),
+                ("text/plain" = String, example = json!("Hello world")),
+            )
+        ),
+        (
+            status = 413,
+            description = "Uploaded file or audio duration exceeds limits",
+            body = OpenAiErrorResponse,
+            example = json!({
+                "error": {
+                    "message": "Payload too large: Audio is too long: 7260 seconds exceed 3600 seconds",
+                    "type": "invalid_request_error",
+                    "param": null,
+                    "code": "PayloadTooLarge",
+                }
+            })
+        ),
+        (
+            status = 415,
+            description = "Audio container is not allowed or codec is not supported",
+            body = OpenAiErrorResponse,
+            example = json!({
+                "error": {
+                    "message": "Unsupported media: unknown audio container",
+                    "type": "invalid_request_error",
+                    "param": null,
+                    "code": "UnsupportedMedia",
+                }
+            })
+        ),
+        (
+            status = 422,
+            description = "Invalid request fields",
+            body = OpenAiErrorResponse,
+            example = json!({
+                "error": {
+                    "message": "Invalid parameters: temperature must be between 0.0 and 1.0",
+                    "type": "invalid_request_error",
+                    "param": null,
+                    "code": "InvalidParameters",
+                }
+            })
+        ),
+    )
+)]
+#[post("/translations")]
+pub async fn translations(
+    cxt: ContextData,
+    uploads: UploadsData,
+    payload: Multipart,
+) -> Result<HttpResponse, OpenAiError> {
+    recognize(cxt, uploads, payload, true).await
+}
+
+async fn recognize(
+    cxt: ContextData,
+    uploads: UploadsData,
+    payload: Multipart,
+    enable_translate: bool,
+) -> Result<HttpResponse, OpenAiError> {
+    let form = helper::extract_multiform_data(payload, uploads.get_ref()).await?;
+    let fields = helper::merge_parameters::<TranscriptionForm>("", &form.fields)
+        .map_err(|err| WebError::InvalidParameters(err.to_string()))?;
+    fields.validate().map_err(WebError::InvalidParameters)?;
+
+    let params = fields.to_parameters(enable_translate);
+    params.validate().map_err(WebError::InvalidParameters)?;
+
+    let client = select_model(&cxt, fields.get_model())?;
+    let (segments, duration_secs) = client.recognize_file_timed(form.file.path(), &params).await?;
+
+    let mut response = HttpResponse::build(StatusCode::OK);
+    let response = match fields.get_response_format() {
+        TranscriptionFormat::Json => response.json(Transcription {
+            text: join_text(&segments),
+        }),
+        TranscriptionFormat::Text => response
+            .content_type(TEXT_CONTENT_TYPE)
+            .body(join_text(&segments)),
+        TranscriptionFormat::Srt => response
+            .content_type(subtitles::SRT_CONTENT_TYPE)
+            .body(subtitles::to_srt(&segments)),
+        TranscriptionFormat::Vtt => response
+            .content_type(subtitles::VTT_CONTENT_TYPE)
+            .body(subtitles::to_vtt(&segments)),
+        TranscriptionFormat::VerboseJson => {
+            let task = match enable_translate {
+                true => "translate",
+                false => "transcribe",
+            };
+
+            response.json(VerboseTranscription {
+                task: task.to_string(),
+                language: detected_language(&segments, &params),
+                duration: duration_secs,
+                text: join_text(&segments),
+                segments: fields.is_granularity_requested(GRANULARITY_SEGMENT).then(|| {
+                    segments
+                        .iter()
+                        .map(|segment| TranscriptionSegment::new(segment, params.get_temperature()))
+                        .collect()
+                }),
+                words: fields.is_granularity_requested(GRANULARITY_WORD).then(|| {
+                    segments
+                        .iter()
+                        .flat_map(|segment| segment.words.iter().flatten())
+                        .map(TranscriptionWord::from)
+                        .collect()
+                }),
+            })
+        }
+    };
+
+    Ok(response)
+}
+
+/// OpenAI clients pass their own model names, so unknown ones are served by the default model.
+fn select_model<'a>(cxt: &'a ContextData, name: Option<&str>) -> Result<&'a ModelClient, OpenAiError> {
+    let whisper_client = cxt.get_ref();
+    let client = match name.and_then(|name| whisper_client.get_model(Some(name)).ok()) {
+        Some(client) => client,
+        None => whisper_client.get_model(None)?,
+    };
+
+    Ok(client)
+}
+
+fn join_text(segments: &[RecognizeResponse]) -> String {
+    segments
+        .iter()
+        .map(|segment| segment.text.trim())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// OpenAI reports detected language if request does not pass it.
+fn detected_language(segments: &[RecognizeResponse], params: &RecognizeParameters) -> String {
+    segments
+        .iter()
+        .find_map(|segment| segment.language.as_deref())
+        .or(params.get_lang())
+        .unwrap_or_default()
+        .to_string()
+}
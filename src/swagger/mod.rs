@@ -1,5 +1,7 @@
 use crate::errors;
 use crate::healthcheck;
+use crate::jobs;
+use crate::openai;
 use crate::whisper;
 
 use utoipa::OpenApi;
@@ -14,13 +16,42 @@ use utoipa_swagger_ui::SwaggerUi;
         healthcheck::routes::check_health,
         whisper::routes::upload_form,
         whisper::routes::recognize_file,
+        whisper::routes::detect_language,
+        openai::routes::transcriptions,
+        openai::routes::translations,
+        jobs::routes::submit_job,
+        jobs::routes::get_job,
+        jobs::routes::get_job_result,
+        jobs::routes::get_job_events,
     ),
     components(
         schemas(
             errors::ErrorResponse,
             errors::SuccessfulResponse,
+            whisper::forms::ChannelMode,
+            whisper::forms::DecodingStrategy,
+            whisper::forms::ResponseFormat,
             whisper::forms::RecognizeParameters,
             whisper::forms::RecognizeResponse,
+            whisper::forms::RecognizedWord,
+            whisper::forms::SegmentConfidence,
+            whisper::forms::SpeechRegion,
+            whisper::forms::DetectLanguageParameters,
+            whisper::forms::DetectLanguageResponse,
+            whisper::forms::LanguageProbability,
+            openai::errors::OpenAiErrorResponse,
+            openai::errors::OpenAiErrorDetails,
+            openai::forms::TranscriptionFormat,
+            openai::forms::TranscriptionForm,
+            openai::forms::Transcription,
+            openai::forms::VerboseTranscription,
+            openai::forms::TranscriptionSegment,
+            openai::forms::TranscriptionWord,
+            jobs::forms::JobState,
+            jobs::forms::JobStatus,
+            jobs::forms::JobCallback,
+            jobs::progress::JobProgress,
+            jobs::progress::ProgressStage,
         )
     ),
     tags ((
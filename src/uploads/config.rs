@@ -0,0 +1,91 @@
+use crate::uploads::media::MediaType;
+
+use std::str::FromStr;
+
+const DEFAULT_UPLOAD_DIR: &str = "./upload";
+const DEFAULT_MAX_AGE_SECS: u64 = 24 * 60 * 60;
+const DEFAULT_SWEEP_INTERVAL_SECS: u64 = 60 * 60;
+const DEFAULT_MAX_BYTES: u64 = 1024 * 1024 * 1024;
+
+#[derive(Clone)]
+pub struct UploadsConfig {
+    upload_dir: String,
+    max_age_secs: u64,
+    sweep_interval_secs: u64,
+    max_bytes: u64,
+    allowed_types: Vec<MediaType>,
+    unknown_allowed: bool,
+}
+
+impl UploadsConfig {
+    pub fn from_env() -> Self {
+        let upload_dir = std::env::var("UPLOAD_DIR").unwrap_or_else(|_| DEFAULT_UPLOAD_DIR.to_string());
+
+        // Uploads left by crashed or killed process are removed once they are older.
+        let max_age_secs = std::env::var("UPLOAD_MAX_AGE_SECS")
+            .map(|value| u64::from_str(value.as_str()).expect("incorrect UPLOAD_MAX_AGE_SECS value"))
+            .unwrap_or(DEFAULT_MAX_AGE_SECS);
+
+        let sweep_interval_secs = std::env::var("UPLOAD_SWEEP_INTERVAL_SECS")
+            .map(|value| u64::from_str(value.as_str()).expect("incorrect UPLOAD_SWEEP_INTERVAL_SECS value"))
+            .unwrap_or(DEFAULT_SWEEP_INTERVAL_SECS);
+
+        assert!(sweep_interval_secs > 0, "UPLOAD_SWEEP_INTERVAL_SECS must be greater than zero");
+
+        // Size of uploaded file is not limited if zero.
+        let max_bytes = std::env::var("UPLOAD_MAX_BYTES")
+            .map(|value| u64::from_str(value.as_str()).expect("incorrect UPLOAD_MAX_BYTES value"))
+            .unwrap_or(DEFAULT_MAX_BYTES);
+
+        // UPLOAD_ALLOWED_TYPES holds a list of containers like `wav,mp3,ogg`, all known ones by default.
+        // Containers unknown to sniffing are left to ffprobe unless the list is set.
+        let unknown_allowed = std::env::var("UPLOAD_ALLOWED_TYPES").is_err();
+        let allowed_types = match std::env::var("UPLOAD_ALLOWED_TYPES") {
+            Err(_) => MediaType::ALL.to_vec(),
+            Ok(value) => value
+                .split(',')
+                .map(|media_type| MediaType::from_str(media_type.trim()).expect("incorrect UPLOAD_ALLOWED_TYPES value"))
+                .collect::<Vec<MediaType>>(),
+        };
+
+        UploadsConfig {
+            upload_dir,
+            max_age_secs,
+            sweep_interval_secs,
+            max_bytes,
+            allowed_types,
+            unknown_allowed,
+        }
+    }
+    pub fn get_upload_dir(&self) -> &str {
+        self.upload_dir.as_str()
+    }
+    pub fn get_max_age_secs(&self) -> u64 {
+        self.max_age_secs
+    }
+    pub fn get_sweep_interval_secs(&self) -> u64 {
+        self.sweep_interval_secs
+    }
+    pub fn get_max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+    pub fn is_type_allowed(&self, media_type: MediaType) -> bool {
+        self.allowed_types.contains(&media_type)
+    }
+    pub fn is_unknown_allowed(&self) -> bool {
+        self.unknown_allowed
+    }
+}
+
+impl Default for UploadsConfig {
+    fn default() -> Self {
+        UploadsConfig {
+            upload_dir: DEFAULT_UPLOAD_DIR.to_string(),
+            max_age_secs: DEFAULT_MAX_AGE_SECS,
+            sweep_interval_secs: DEFAULT_SWEEP_INTERVAL_SECS,
+            max_bytes: DEFAULT_MAX_BYTES,
+            allowed_types: MediaType::ALL.to_vec(),
+            unknown_allowed: true,
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Uploads which outlive request, like audio of queued jobs, are moved into
+/// this subdirectory of upload dir, the sweeper keeps those in use by jobs.
+pub(crate) const KEPT_DIR_NAME: &str = "kept";
+/// Files of system temp dir created by the service start with it, so the
+/// sweeper tells them apart from files of other processes.
+pub(crate) const TEMP_FILE_PREFIX: &str = "audio-to-text-";
+/// Longer extensions of client filenames are dropped.
+const MAX_EXTENSION_LEN: usize = 8;
+
+/// File which is removed once it is dropped, so it is cleaned up on every exit
+/// path including errors and cancelled requests.
+pub struct TempFile {
+    path: Option<PathBuf>,
+}
+
+impl TempFile {
+    pub fn new(path: PathBuf) -> Self {
+        TempFile { path: Some(path) }
+    }
+
+    /// Unique server generated path within directory. Only a sanitized
+    /// extension of client filename is kept since decoders rely on it.
+    pub fn unique(dir: &str, filename: Option<&str>) -> Self {
+        let extension = filename
+            .and_then(|filename| Path::new(filename).extension())
+            .and_then(|extension| extension.to_str())
+            .filter(|extension| extension.len() <= MAX_EXTENSION_LEN)
+            .filter(|extension| extension.chars().all(|ch| ch.is_ascii_alphanumeric()))
+            .map(|extension| format!(".{}", extension.to_ascii_lowercase()))
+            .unwrap_or_default();
+
+        let file_name = format!("{}{}", uuid::Uuid::new_v4(), extension);
+        TempFile::new(Path::new(dir).join(file_name))
+    }
+
+    /// Unique path within system temp dir, like the one of ffmpeg output.
+    pub(crate) fn temp(extension: &str) -> Self {
+        let file_name = format!("{}{}.{}", TEMP_FILE_PREFIX, uuid::Uuid::new_v4(), extension);
+        TempFile::new(std::env::temp_dir().join(file_name))
+    }
+
+    pub fn path(&self) -> &str {
+        self.path
+            .as_deref()
+            .and_then(Path::to_str)
+            .unwrap_or_default()
+    }
+
+    /// Moves file out of reach of the sweeper and stops removing it on drop,
+    /// the returned path is owned by the caller since then.
+    pub fn keep(mut self) -> std::io::Result<String> {
+        let path = self.path.take().unwrap_or_default();
+        let kept_dir = path.parent().unwrap_or(Path::new(".")).join(KEPT_DIR_NAME);
+        let kept_path = kept_dir.join(path.file_name().unwrap_or_default());
+
+        let moved = std::fs::create_dir_all(kept_dir.as_path())
+            .and_then(|_| std::fs::rename(path.as_path(), kept_path.as_path()));
+        if let Err(err) = moved {
+            self.path = Some(path);
+            return Err(err);
+        }
+
+        Ok(kept_path.to_string_lossy().to_string())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let Some(path) = self.path.take() else {
+            return;
+        };
+
+        match std::fs::remove_file(path.as_path()) {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                log::warn!("Failed while removing temporary file {}: {}", path.display(), err);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::str::FromStr;
+
+/// Bytes of file start which are enough to tell all known containers apart.
+pub(crate) const MEDIA_HEADER_LEN: usize = 12;
+
+/// Audio containers recognized by signature of uploaded file. Only the
+/// container is checked while uploading, codec of its audio is checked
+/// once the file is probed before decoding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MediaType {
+    Wav,
+    Aiff,
+    Mp3,
+    Aac,
+    Ogg,
+    Flac,
+    Mp4,
+    Webm,
+}
+
+impl MediaType {
+    pub const ALL: [MediaType; 8] = [
+        MediaType::Wav,
+        MediaType::Aiff,
+        MediaType::Mp3,
+        MediaType::Aac,
+        MediaType::Ogg,
+        MediaType::Flac,
+        MediaType::Mp4,
+        MediaType::Webm,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaType::Wav => "wav",
+            MediaType::Aiff => "aiff",
+            MediaType::Mp3 => "mp3",
+            MediaType::Aac => "aac",
+            MediaType::Ogg => "ogg",
+            MediaType::Flac => "flac",
+            MediaType::Mp4 => "mp4",
+            MediaType::Webm => "webm",
+        }
+    }
+
+    /// Detects container by magic bytes of file start, extensions and content
+    /// types passed by clients are not trusted.
+    pub fn sniff(header: &[u8]) -> Option<MediaType> {
+        match header {
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Some(MediaType::Wav),
+            [b'R', b'F', b'6', b'4', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Some(MediaType::Wav),
+            [b'F', b'O', b'R', b'M', _, _, _, _, b'A', b'I', b'F', _, ..] => Some(MediaType::Aiff),
+            [b'I', b'D', b'3', ..] => Some(MediaType::Mp3),
+            // MPEG frame sync, layer bits are zero for ADTS stream of AAC.
+            [0xFF, second, ..] if second & 0xF6 == 0xF0 => Some(MediaType::Aac),
+            [0xFF, second, ..] if second & 0xE0 == 0xE0 && second & 0x06 != 0 => Some(MediaType::Mp3),
+            [b'O', b'g', b'g', b'S', ..] => Some(MediaType::Ogg),
+            [b'f', b'L', b'a', b'C', ..] => Some(MediaType::Flac),
+            [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some(MediaType::Mp4),
+            [0x1A, 0x45, 0xDF, 0xA3, ..] => Some(MediaType::Webm),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for MediaType {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        MediaType::ALL
+            .into_iter()
+            .find(|media_type| media_type.as_str() == value)
+            .ok_or_else(|| format!("unknown media type: {}", value))
+    }
+}
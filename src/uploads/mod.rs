@@ -0,0 +1,4 @@
+pub mod config;
+pub mod file;
+pub mod media;
+pub mod sweeper;
@@ -0,0 +1,100 @@
+use crate::jobs::worker::JobQueue;
+use crate::uploads::config::UploadsConfig;
+use crate::uploads::file::{KEPT_DIR_NAME, TEMP_FILE_PREFIX};
+
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Spawns task which periodically removes orphaned files. Uploads and ffmpeg
+/// outputs are removed by requests which own them, so files older than
+/// `UPLOAD_MAX_AGE_SECS` are left by a crashed or killed process only. Kept
+/// uploads are removed unless an unfinished job still refers to them, audio
+/// kept by job store is removed the same way once `JOBS_AUDIO_RETENTION_SECS` passes.
+pub fn spawn_sweeper(cfg: &UploadsConfig, jobs: JobQueue) {
+    let upload_dir = PathBuf::from(cfg.get_upload_dir());
+    let max_age = Duration::from_secs(cfg.get_max_age_secs());
+    let mut interval = tokio::time::interval(Duration::from_secs(cfg.get_sweep_interval_secs()));
+
+    tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+            sweep_uploads(upload_dir.as_path(), max_age, &jobs).await;
+        }
+    });
+}
+
+async fn sweep_uploads(upload_dir: &Path, max_age: Duration, jobs: &JobQueue) {
+    let sweep_res = sweep(upload_dir, max_age, |_| true).await;
+    log_sweep(upload_dir, sweep_res);
+
+    let temp_dir = std::env::temp_dir();
+    let sweep_res = sweep(temp_dir.as_path(), max_age, |path| {
+        path.file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with(TEMP_FILE_PREFIX))
+    })
+    .await;
+    log_sweep(temp_dir.as_path(), sweep_res);
+
+    let used_paths = match jobs.list_audio_paths().await {
+        Ok(paths) => paths.into_iter().map(PathBuf::from).collect::<HashSet<PathBuf>>(),
+        Err(err) => {
+            log::warn!("Failed while listing audio of unfinished jobs: {}", err);
+            return;
+        }
+    };
+
+    let kept_dir = upload_dir.join(KEPT_DIR_NAME);
+    let sweep_res = sweep(kept_dir.as_path(), max_age, |path| !used_paths.contains(path)).await;
+    match sweep_res {
+        Err(err) if err.kind() == ErrorKind::NotFound => {}
+        sweep_res => log_sweep(kept_dir.as_path(), sweep_res),
+    }
+
+    if let (Some(audio_dir), Some(retention)) = (jobs.get_audio_dir(), jobs.get_audio_retention()) {
+        let sweep_res = sweep(audio_dir.as_path(), retention, |path| !used_paths.contains(path)).await;
+        log_sweep(audio_dir.as_path(), sweep_res);
+    }
+}
+
+fn log_sweep(dir: &Path, sweep_res: std::io::Result<usize>) {
+    match sweep_res {
+        Ok(0) => {}
+        Ok(removed) => log::info!("Removed {} orphaned files from {}", removed, dir.display()),
+        Err(err) => log::warn!("Failed while sweeping orphaned files in {}: {}", dir.display(), err),
+    }
+}
+
+/// Removes files of directory modified earlier than `max_age` ago which match
+/// the filter. Hidden files and subdirectories are skipped.
+async fn sweep<F>(dir: &Path, max_age: Duration, filter: F) -> std::io::Result<usize>
+where
+    F: Fn(&Path) -> bool,
+{
+    let mut removed = 0;
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let is_hidden = entry.file_name().to_string_lossy().starts_with('.');
+        let metadata = entry.metadata().await?;
+        if is_hidden || !metadata.is_file() || !filter(entry.path().as_path()) {
+            continue;
+        }
+
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .unwrap_or_default();
+        if age < max_age {
+            continue;
+        }
+
+        match tokio::fs::remove_file(entry.path()).await {
+            Ok(_) => removed += 1,
+            Err(err) => log::warn!("Failed while removing orphaned file {}: {}", entry.path().display(), err),
+        }
+    }
+
+    Ok(removed)
+}
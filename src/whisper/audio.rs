@@ -0,0 +1,164 @@
+use crate::whisper::errors::{RecognizeError, RecognizeResult};
+
+use hound::{SampleFormat, WavReader};
+use std::io::Read;
+
+#[cfg(feature = "enable-native-decoding")]
+pub(crate) use crate::whisper::decoder::resample;
+#[cfg(not(feature = "enable-native-decoding"))]
+use std::f64::consts::PI;
+
+/// Whisper models are trained on 16 kHz mono audio.
+pub const WHISPER_SAMPLE_RATE: u32 = 16000;
+
+/// Properties of audio file read from its headers before decoding.
+pub(crate) struct AudioProbe {
+    /// Unknown for containers which do not store it, like mp3 without xing header.
+    pub duration_secs: Option<f64>,
+}
+
+/// Zero crossings of the lanczos kernel on each side of the interpolated sample.
+#[cfg(not(feature = "enable-native-decoding"))]
+const RESAMPLE_KERNEL_SIZE: f64 = 8.0;
+
+/// Reads duration from header of wav file, other files are not probed.
+#[cfg(not(feature = "enable-native-decoding"))]
+pub(crate) fn probe_wav(file_path: &str) -> Option<AudioProbe> {
+    let reader = WavReader::open(file_path).ok()?;
+    let spec = reader.spec();
+    let duration_secs = match spec.sample_rate {
+        0 => None,
+        sample_rate => Some(reader.duration() as f64 / sample_rate as f64),
+    };
+
+    Some(AudioProbe { duration_secs })
+}
+
+/// Reads wav audio of any supported bit depth, sample rate and channel count
+/// and converts it to 16 kHz mono float samples expected by whisper.
+#[cfg(not(feature = "enable-native-decoding"))]
+pub(crate) fn normalize_wav<R: Read>(reader: WavReader<R>) -> RecognizeResult<Vec<f32>> {
+    let mut channels = normalize_wav_channels(reader, false)?;
+    Ok(channels.remove(0))
+}
+
+/// Same as `normalize_wav`, keeping every channel apart if `split_channels` is passed.
+pub(crate) fn normalize_wav_channels<R: Read>(reader: WavReader<R>, split_channels: bool) -> RecognizeResult<Vec<Vec<f32>>> {
+    let spec = reader.spec();
+    if spec.channels == 0 || spec.sample_rate == 0 {
+        let msg = format!("{} channels at {} Hz", spec.channels, spec.sample_rate);
+        return Err(RecognizeError::UnsupportedAudio(msg));
+    }
+
+    let samples = read_samples(reader)?;
+    let channels = match split_channels {
+        true => deinterleave(&samples, spec.channels as usize),
+        false => vec![downmix(&samples, spec.channels as usize)],
+    };
+
+    channels
+        .iter()
+        .map(|channel| resample(channel, spec.sample_rate, WHISPER_SAMPLE_RATE))
+        .collect()
+}
+
+/// Reads interleaved samples scaled to [-1.0, 1.0].
+fn read_samples<R: Read>(reader: WavReader<R>) -> RecognizeResult<Vec<f32>> {
+    let spec = reader.spec();
+    let samples = match (spec.sample_format, spec.bits_per_sample) {
+        (SampleFormat::Float, 32) => reader.into_samples::<f32>().collect::<Result<Vec<_>, _>>(),
+        (SampleFormat::Int, 8) => scale_samples::<_, i8>(reader, 8),
+        (SampleFormat::Int, 16) => scale_samples::<_, i16>(reader, 16),
+        (SampleFormat::Int, 24) => scale_samples::<_, i32>(reader, 24),
+        (SampleFormat::Int, 32) => scale_samples::<_, i32>(reader, 32),
+        (sample_format, bits) => {
+            let msg = format!("{}-bit {:?} wav samples", bits, sample_format);
+            return Err(RecognizeError::UnsupportedAudio(msg));
+        }
+    };
+
+    samples.map_err(|err| RecognizeError::DecodeAudio(err.to_string()))
+}
+
+fn scale_samples<R, S>(reader: WavReader<R>, bits: u32) -> hound::Result<Vec<f32>>
+where
+    R: Read,
+    S: hound::Sample + Into<i32>,
+{
+    let scale = (1_i64 << (bits - 1)) as f32;
+    reader
+        .into_samples::<S>()
+        .map(|sample| sample.map(|value| value.into() as f32 / scale))
+        .collect()
+}
+
+/// Averages interleaved channels into a single one.
+pub(crate) fn downmix(samples: &[f32], channels: usize) -> Vec<f32> {
+    if channels == 1 {
+        return samples.to_vec();
+    }
+
+    samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+/// Splits interleaved samples into separate channels.
+pub(crate) fn deinterleave(samples: &[f32], channels: usize) -> Vec<Vec<f32>> {
+    (0..channels)
+        .map(|channel| {
+            samples
+                .chunks_exact(channels)
+                .map(|frame| frame[channel])
+                .collect()
+        })
+        .collect()
+}
+
+/// Band-limited resampling with a lanczos windowed sinc kernel.
+#[cfg(not(feature = "enable-native-decoding"))]
+pub(crate) fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> RecognizeResult<Vec<f32>> {
+    if from_rate == to_rate || samples.is_empty() {
+        return Ok(samples.to_vec());
+    }
+
+    let ratio = to_rate as f64 / from_rate as f64;
+    // Lower the cutoff while downsampling to suppress aliasing.
+    let cutoff = ratio.min(1.0);
+    let half_width = (RESAMPLE_KERNEL_SIZE / cutoff).ceil() as isize;
+    let last_index = samples.len() as isize - 1;
+    let output_len = (samples.len() as f64 * ratio).round() as usize;
+
+    let resampled = (0..output_len)
+        .map(|index| {
+            let center = index as f64 / ratio;
+            let first = (center.floor() as isize - half_width).max(0);
+            let last = (center.floor() as isize + half_width).min(last_index);
+
+            let (mut acc, mut weights) = (0.0, 0.0);
+            for position in first..=last {
+                let weight = lanczos((center - position as f64) * cutoff);
+                acc += samples[position as usize] as f64 * weight;
+                weights += weight;
+            }
+
+            if weights == 0.0 { 0.0 } else { (acc / weights) as f32 }
+        })
+        .collect();
+
+    Ok(resampled)
+}
+
+#[cfg(not(feature = "enable-native-decoding"))]
+fn lanczos(x: f64) -> f64 {
+    if x == 0.0 {
+        return 1.0;
+    }
+    if x.abs() >= RESAMPLE_KERNEL_SIZE {
+        return 0.0;
+    }
+
+    let pi_x = PI * x;
+    RESAMPLE_KERNEL_SIZE * pi_x.sin() * (pi_x / RESAMPLE_KERNEL_SIZE).sin() / (pi_x * pi_x)
+}
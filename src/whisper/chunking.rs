@@ -0,0 +1,130 @@
+use crate::whisper::audio::WHISPER_SAMPLE_RATE;
+use crate::whisper::client::{ms_to_samples, samples_to_centis};
+use crate::whisper::engine::RecognizeObserver;
+use crate::whisper::forms::{RecognizeParameters, RecognizeResponse};
+use crate::whisper::vad;
+
+use std::ops::Range;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Part of chunk duration at its end where the quietest frame is looked for.
+const BOUNDARY_SEARCH_DIVISOR: usize = 5;
+
+/// Part of audio recognized separately. Window is the core extended by overlap
+/// on both sides, segments are kept only if they are centered within the core.
+pub(crate) struct Chunk {
+    pub core: Range<usize>,
+    pub window: Range<usize>,
+}
+
+/// Splits audio into chunks of about `chunk_secs` at the quietest frames near
+/// chunk ends, so boundaries fall on pauses rather than on words.
+pub(crate) fn split_chunks(audio: &[f32], params: &RecognizeParameters) -> Vec<Chunk> {
+    let chunk_len = params.get_chunk_secs() as usize * WHISPER_SAMPLE_RATE as usize;
+    let search_len = chunk_len / BOUNDARY_SEARCH_DIVISOR;
+    let overlap = ms_to_samples(params.get_chunk_overlap_ms());
+    let frames = vad::analyze_frames(audio);
+
+    let mut cores = Vec::new();
+    let mut start = 0;
+    // The last chunk takes the rest if it is too short to be recognized alone.
+    while start + chunk_len + search_len < audio.len() {
+        let target = start + chunk_len;
+        let search = (target - search_len) / vad::FRAME_SIZE..target / vad::FRAME_SIZE;
+        let boundary = search
+            .clone()
+            .min_by(|a, b| frames[*a].energy_db.total_cmp(&frames[*b].energy_db))
+            .unwrap_or(search.end)
+            * vad::FRAME_SIZE;
+
+        cores.push(start..boundary);
+        start = boundary;
+    }
+    cores.push(start..audio.len());
+
+    cores
+        .into_iter()
+        .map(|core| Chunk {
+            window: core.start.saturating_sub(overlap)..(core.end + overlap).min(audio.len()),
+            core,
+        })
+        .collect()
+}
+
+/// Joins segments of chunks which are already moved to the common timeline.
+/// Segments of overlapping parts are taken from the chunk whose core holds
+/// their middle, repeated text at chunk boundaries is dropped.
+pub(crate) fn stitch(chunks: &[Chunk], recognized: Vec<Vec<RecognizeResponse>>) -> Vec<RecognizeResponse> {
+    let mut stitched = Vec::<RecognizeResponse>::new();
+    for (chunk_id, (chunk, segments)) in chunks.iter().zip(recognized).enumerate() {
+        let lower = match chunk_id {
+            0 => i64::MIN,
+            _ => samples_to_centis(chunk.core.start),
+        };
+        let upper = match chunk_id + 1 == chunks.len() {
+            true => i64::MAX,
+            false => samples_to_centis(chunk.core.end),
+        };
+
+        for mut segment in segments {
+            let middle = (segment.frame_start + segment.frame_end) / 2;
+            if middle < lower || middle >= upper {
+                continue;
+            }
+
+            let is_repeated = stitched.last().is_some_and(|last| {
+                segment.frame_start < last.frame_end && segment.text.trim() == last.text.trim()
+            });
+            if is_repeated {
+                continue;
+            }
+
+            segment.frame_id = stitched.len() as i32;
+            stitched.push(segment);
+        }
+    }
+
+    stitched
+}
+
+/// Averages inference progress of audio parts like chunks or channels which
+/// are recognized concurrently.
+pub(crate) struct PartsProgress {
+    observer: Arc<dyn RecognizeObserver>,
+    percents: Mutex<Vec<i32>>,
+}
+
+impl PartsProgress {
+    pub fn new(observer: Arc<dyn RecognizeObserver>, parts_count: usize) -> Self {
+        PartsProgress {
+            observer,
+            percents: Mutex::new(vec![0; parts_count]),
+        }
+    }
+}
+
+/// Reports progress of a single part. Segments are not passed on since their
+/// ids and order are known only once all parts are joined.
+pub(crate) struct PartObserver {
+    pub progress: Arc<PartsProgress>,
+    pub part_id: usize,
+}
+
+impl RecognizeObserver for PartObserver {
+    fn on_progress(&self, percent: i32) {
+        let average = {
+            let mut percents = self
+                .progress
+                .percents
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            percents[self.part_id] = percent.clamp(0, 100);
+            percents.iter().sum::<i32>() / percents.len() as i32
+        };
+        self.progress.observer.on_progress(average);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.progress.observer.is_cancelled()
+    }
+}
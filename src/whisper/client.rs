@@ -1,155 +1,334 @@
-use crate::whisper::config::WhisperClientConfig;
-use crate::whisper::forms::{RecognizeParameters, RecognizeResponse};
-use crate::whisper::resampler::resample_audio;
+use crate::whisper::audio::{self, AudioProbe, WHISPER_SAMPLE_RATE};
+use crate::whisper::config::{WhisperClientConfig, WhisperModelConfig};
+use crate::whisper::engine::{RecognizeObserver, SpeechEngine, WhisperEngine};
+use crate::whisper::errors::{RecognizeError, RecognizeResult};
+use crate::whisper::forms::{LanguageProbability, RecognizeParameters, RecognizeResponse, SpeechRegion};
+use crate::whisper::resampler::{self, resample_audio};
+use crate::whisper::speakers::{self, SpeakerTurns, SpeakersObserver};
+use crate::whisper::vad;
 
-use std::ffi::c_int;
-use std::io::BufReader;
-use hound::{WavReader, WavSpec};
-use tokio::io;
+#[cfg(feature = "enable-native-decoding")]
+use crate::whisper::decoder;
 
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperError, WhisperState};
-
-type RecognizeResult<T> = Result<T, WhisperError>;
+use hound::WavReader;
+#[cfg(not(feature = "enable-native-decoding"))]
+use std::io::Cursor;
+use std::ops::Range;
+use std::sync::Mutex;
 
 pub struct WhisperClient {
-    client: WhisperContext,
+    engine: Box<dyn SpeechEngine>,
 }
 
 impl WhisperClient {
-    pub fn new(cfg: &WhisperClientConfig) -> Self {
-        let mut cxt_params = WhisperContextParameters::default();
-        cxt_params.use_gpu(cfg.is_gpu_enabled());
-
-        let whisper_ctx = WhisperContext::new_with_params(cfg.get_model_path(), cxt_params)
-            .expect("Failed while loading whisper model...");
+    pub fn new(cfg: &WhisperClientConfig, model: &WhisperModelConfig) -> Self {
+        WhisperClient::with_engine(WhisperEngine::new(cfg, model))
+    }
 
+    pub fn with_engine<E: SpeechEngine + 'static>(engine: E) -> Self {
         WhisperClient {
-            client: whisper_ctx,
+            engine: Box::new(engine),
+        }
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.engine.capacity()
+    }
+
+    pub(crate) fn recognize(&self, audio: &[f32], params: &RecognizeParameters) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let mut recognized = match params.is_vad_enable() {
+            true => self.recognize_speech(audio, params, None)?,
+            false => self.engine.recognize(audio, params)?,
+        };
+
+        if params.is_speaker_turns_enable() {
+            speakers::label_speakers(&mut recognized, params);
+        }
+
+        Ok(recognized)
+    }
+
+    pub(crate) fn recognize_observed(
+        &self,
+        audio: &[f32],
+        params: &RecognizeParameters,
+        observer: &dyn RecognizeObserver,
+    ) -> RecognizeResult<Vec<RecognizeResponse>> {
+        if !params.is_speaker_turns_enable() {
+            return self.recognize_observed_speech(audio, params, observer);
+        }
+
+        let speakers_observer = SpeakersObserver {
+            observer,
+            turns: Mutex::new(SpeakerTurns::new(params)),
+        };
+        let mut recognized = self.recognize_observed_speech(audio, params, &speakers_observer)?;
+        speakers::label_speakers(&mut recognized, params);
+        Ok(recognized)
+    }
+
+    fn recognize_observed_speech(
+        &self,
+        audio: &[f32],
+        params: &RecognizeParameters,
+        observer: &dyn RecognizeObserver,
+    ) -> RecognizeResult<Vec<RecognizeResponse>> {
+        match params.is_vad_enable() {
+            true => self.recognize_speech(audio, params, Some(observer)),
+            false => self.engine.recognize_observed(audio, params, observer),
         }
     }
 
+    /// Recognizes speech regions one by one so long silences are not fed to
+    /// whisper, then maps segments back to the original timeline. Offset and
+    /// duration are applied before detection since regions are cut from audio.
+    fn recognize_speech(
+        &self,
+        audio: &[f32],
+        params: &RecognizeParameters,
+        observer: Option<&dyn RecognizeObserver>,
+    ) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let requested = requested_range(audio.len(), params);
+        let start = requested.start;
+        let audio = &audio[requested];
+
+        let mut region_params = params.clone();
+        region_params.set_offset_ms(0);
+        region_params.set_duration_ms(0);
+
+        let regions = vad::detect_speech(audio, params);
+        let mut recognized = Vec::new();
+        for (region_id, region) in regions.iter().enumerate() {
+            if observer.is_some_and(|observer| observer.is_cancelled()) {
+                return Err(RecognizeError::Interrupted("recognition has been cancelled".to_string()));
+            }
+
+            let speech_region = SpeechRegion {
+                start: samples_to_centis(start + region.start),
+                end: samples_to_centis(start + region.end),
+            };
+            let region_observer = observer.map(|observer| RegionObserver {
+                observer,
+                region: speech_region,
+                params,
+                first_id: recognized.len() as i32,
+                region_id,
+                regions_count: regions.len(),
+            });
+
+            let region_audio = &audio[region.clone()];
+            let segments = match region_observer.as_ref() {
+                Some(region_observer) => self.engine.recognize_observed(region_audio, &region_params, region_observer)?,
+                None => self.engine.recognize(region_audio, &region_params)?,
+            };
+
+            let first_id = recognized.len() as i32;
+            recognized.extend(
+                segments
+                    .into_iter()
+                    .map(|segment| shift_region_segment(segment, speech_region, first_id, params)),
+            );
+        }
+
+        if let Some(observer) = observer {
+            observer.on_progress(100);
+        }
+
+        Ok(recognized)
+    }
+
+    pub(crate) fn detect_language(&self, audio: &[f32], threads: i32) -> RecognizeResult<Vec<LanguageProbability>> {
+        self.engine.detect_language(audio, threads)
+    }
+
     pub(crate) fn recognize_chunk(&self, audio_data: &[u8], params: &RecognizeParameters) -> RecognizeResult<Vec<RecognizeResponse>> {
-        let strategy = SamplingStrategy::Greedy { best_of: 1 };
-        let mut full_params = FullParams::new(strategy);
-        full_params.set_language(params.get_lang());
-        full_params.set_n_threads(params.get_threads());
-        full_params.set_translate(params.is_translate_enable());
-        full_params.set_print_special(params.is_print_spec_enable());
-        full_params.set_print_progress(params.is_print_progress_enable());
-        full_params.set_print_realtime(params.is_print_realtime_enable());
-        full_params.set_print_timestamps(params.is_print_timestamp_enable());
-
-        let buffered = BufReader::new(audio_data);
-        let mut reader = WavReader::new(buffered)
+        let audio = Self::decode_chunk(audio_data)?;
+        self.recognize(&audio, params)
+    }
+
+    #[cfg(feature = "enable-native-decoding")]
+    fn decode_chunk(audio_data: &[u8]) -> RecognizeResult<Vec<f32>> {
+        decoder::decode_bytes(audio_data.to_vec())
+            .inspect_err(|err| log::error!("Failed while decoding audio chunk: {}", err))
+    }
+
+    #[cfg(not(feature = "enable-native-decoding"))]
+    fn decode_chunk(audio_data: &[u8]) -> RecognizeResult<Vec<f32>> {
+        let reader = WavReader::new(Cursor::new(audio_data))
             .map_err(|err| {
-                log::error!("Failed while reading resampled audio file: {}", err);
-                WhisperError::FailedToDecode
+                log::error!("Failed while reading audio chunk: {}", err);
+                RecognizeError::UnsupportedAudio(err.to_string())
             })?;
 
-        let recognize_res = self.recognize(&mut reader, full_params);
-        recognize_res
+        audio::normalize_wav(reader)
     }
 
-    pub(crate) async fn recognize_file(&self, file_path: &str, params: &RecognizeParameters) -> RecognizeResult<Vec<RecognizeResponse>> {
-        let audio_file_path = resample_audio(file_path, params.get_resample_rate())
+    /// Reads duration of audio file from its headers and checks its codec is
+    /// supported, ffprobe is used only for formats unknown to native decoder.
+    #[cfg(feature = "enable-native-decoding")]
+    pub(crate) async fn probe_file(file_path: &str) -> RecognizeResult<AudioProbe> {
+        let path = file_path.to_string();
+        let probe_result = tokio::task::spawn_blocking(move || decoder::probe_file(&path))
             .await
-            .map_err(|err| {
-                log::error!("Failed while resampling audio file: {}", err);
-                WhisperError::DecodeNotComplete
-            })?;
+            .map_err(|err| RecognizeError::Interrupted(err.to_string()))?;
 
-        let mut reader = WavReader::open(audio_file_path.as_str())
-            .map_err(|err| {
-                log::error!("Failed while reading resampled audio file: {}", err);
-                WhisperError::FailedToDecode
-            })?;
+        match probe_result {
+            Err(RecognizeError::UnsupportedAudio(err)) => resampler::probe_audio(file_path)
+                .await
+                .map_err(|probe_err| match probe_err {
+                    RecognizeError::ConverterMissing(_) => RecognizeError::UnsupportedAudio(err),
+                    probe_err => probe_err,
+                }),
+            probe_result => probe_result,
+        }
+    }
 
-        let strategy = SamplingStrategy::Greedy { best_of: 1 };
-        let mut full_params = FullParams::new(strategy);
-        full_params.set_language(params.get_lang());
-        full_params.set_n_threads(params.get_threads());
-        full_params.set_translate(params.is_translate_enable());
-        full_params.set_print_special(params.is_print_spec_enable());
-        full_params.set_print_progress(params.is_print_progress_enable());
-        full_params.set_print_realtime(params.is_print_realtime_enable());
-        full_params.set_print_timestamps(params.is_print_timestamp_enable());
-
-        let recognize_res = self.recognize(&mut reader, full_params);
-        let removing_result = Self::remove_tmp_files(audio_file_path.as_str()).await;
-        if removing_result.is_err() {
-            let err = removing_result.err().unwrap();
-            log::warn!(
-                "Failed while removing temporary file {}: {}",
-                audio_file_path,
-                err
-            );
+    /// Reads duration of audio file from wav header or with ffprobe and checks
+    /// ffmpeg is able to decode other formats.
+    #[cfg(not(feature = "enable-native-decoding"))]
+    pub(crate) async fn probe_file(file_path: &str) -> RecognizeResult<AudioProbe> {
+        match audio::probe_wav(file_path) {
+            Some(probed) => Ok(probed),
+            None => resampler::probe_audio(file_path).await,
         }
+    }
 
-        recognize_res
+    pub(crate) async fn load_file(file_path: &str, probed: Option<&AudioProbe>) -> RecognizeResult<Vec<f32>> {
+        let mut channels = Self::load_file_channels(file_path, false, probed).await?;
+        Ok(channels.remove(0))
     }
 
-    fn recognize<T>(
-        &self,
-        reader: &mut WavReader<BufReader<T>>,
-        full_params: FullParams,
-    ) -> RecognizeResult<Vec<RecognizeResponse>>
-    where
-        T: std::io::Read
-    {
-        #[allow(unused_variables)]
-            let WavSpec {
-            sample_rate,
-            channels,
-            bits_per_sample,
-            ..
-        } = reader.spec();
-
-        if channels > 2 {
-            panic!(">2 channels unsupported");
+    /// Decodes audio file to 16 kHz samples in-process, every channel apart if
+    /// `split_channels` is passed. ffmpeg is used only for formats which are
+    /// not supported by native decoder. File is probed again unless `probed` is passed.
+    #[cfg(feature = "enable-native-decoding")]
+    pub(crate) async fn load_file_channels(
+        file_path: &str,
+        split_channels: bool,
+        probed: Option<&AudioProbe>,
+    ) -> RecognizeResult<Vec<Vec<f32>>> {
+        let path = file_path.to_string();
+        let decode_result = tokio::task::spawn_blocking(move || decoder::decode_file_channels(&path, split_channels))
+            .await
+            .map_err(|err| RecognizeError::Interrupted(err.to_string()))?;
+
+        match decode_result {
+            Err(RecognizeError::UnsupportedAudio(err)) => {
+                log::warn!("Failed while decoding {} natively, using ffmpeg: {}", file_path, err);
+                // Without ffmpeg binary the audio is unsupported rather than failed.
+                Self::convert_file(file_path, split_channels, probed)
+                    .await
+                    .map_err(|convert_err| match convert_err {
+                        RecognizeError::ConverterMissing(_) => RecognizeError::UnsupportedAudio(err),
+                        convert_err => convert_err,
+                    })
+            }
+            decode_result => decode_result,
         }
+    }
 
-        let sampled_reader = &reader
-            .samples::<i16>()
-            .map(|s| s.expect("invalid sample"))
-            .collect::<Vec<_>>();
+    /// Converts audio file to 16 kHz samples with ffmpeg, every channel apart
+    /// if `split_channels` is passed. File is probed again unless `probed` is passed.
+    #[cfg(not(feature = "enable-native-decoding"))]
+    pub(crate) async fn load_file_channels(
+        file_path: &str,
+        split_channels: bool,
+        probed: Option<&AudioProbe>,
+    ) -> RecognizeResult<Vec<Vec<f32>>> {
+        Self::convert_file(file_path, split_channels, probed).await
+    }
 
-        let mut audio = whisper_rs::convert_integer_to_float_audio(sampled_reader);
-        if channels == 2 {
-            audio = whisper_rs::convert_stereo_to_mono_audio(&audio).map_err(|err| {
-                log::error!("Failed while converting stereo to mono: {}", err);
-                WhisperError::DecodeNotComplete
-            })?;
-        }
+    async fn convert_file(
+        file_path: &str,
+        split_channels: bool,
+        probed: Option<&AudioProbe>,
+    ) -> RecognizeResult<Vec<Vec<f32>>> {
+        let audio_file_path = resample_audio(file_path, split_channels, probed)
+            .await
+            .inspect_err(|err| log::error!("Failed while resampling audio file: {}", err))?;
+
+        WavReader::open(audio_file_path.path())
+            .map_err(|err| {
+                log::error!("Failed while reading resampled audio file: {}", err);
+                RecognizeError::DecodeAudio(err.to_string())
+            })
+            .and_then(|reader| audio::normalize_wav_channels(reader, split_channels))
+    }
+}
 
-        let mut state = self.client.create_state()?;
-        state.full(full_params, &audio[..])?;
+/// Passes events of speech region recognition to the observer on the original timeline.
+struct RegionObserver<'a> {
+    observer: &'a dyn RecognizeObserver,
+    region: SpeechRegion,
+    params: &'a RecognizeParameters,
+    first_id: i32,
+    region_id: usize,
+    regions_count: usize,
+}
 
-        let num_segments = state.full_n_segments()?;
-        let collected_results = (0..num_segments)
-            .into_iter()
-            .filter_map(|id| Self::extract_segment(&state, id).ok())
-            .collect::<Vec<RecognizeResponse>>();
+impl RecognizeObserver for RegionObserver<'_> {
+    fn on_progress(&self, percent: i32) {
+        let passed = self.region_id as i32 * 100 + percent.clamp(0, 100);
+        self.observer.on_progress(passed / self.regions_count as i32);
+    }
 
-        Ok(collected_results)
+    fn on_segment(&self, segment: &RecognizeResponse) {
+        let segment = shift_region_segment(segment.clone(), self.region, self.first_id, self.params);
+        self.observer.on_segment(&segment);
     }
 
-    fn extract_segment(state: &WhisperState, segment_id: c_int) -> RecognizeResult<RecognizeResponse> {
-        let start_timestamp = state.full_get_segment_t0(segment_id)?;
-        let end_timestamp = state.full_get_segment_t1(segment_id)?;
-        let segment = state.full_get_segment_text(segment_id)?;
-        Ok(RecognizeResponse {
-            frame_id: segment_id,
-            frame_start: start_timestamp,
-            frame_end: end_timestamp,
-            text: segment,
-        })
+    fn is_cancelled(&self) -> bool {
+        self.observer.is_cancelled()
     }
+}
 
-    async fn remove_tmp_files(file_path: &str) -> Result<bool, io::Error> {
-        let path_to_remove = std::path::Path::new(file_path);
-        match tokio::fs::remove_file(path_to_remove).await {
-            Ok(_) => Ok(true),
-            Err(err) => Err(err),
-        }
+/// Moves segment recognized within a part of audio starting at `shift` centiseconds
+/// to the original timeline, ids of segments continue from `first_id`.
+pub(crate) fn shift_segment(mut segment: RecognizeResponse, shift: i64, first_id: i32) -> RecognizeResponse {
+    segment.frame_id += first_id;
+    segment.frame_start += shift;
+    segment.frame_end += shift;
+    segment.words.iter_mut().flatten().for_each(|word| {
+        word.start += shift;
+        word.end += shift;
+    });
+    segment.speech_region.iter_mut().for_each(|region| {
+        region.start += shift;
+        region.end += shift;
+    });
+
+    segment
+}
+
+fn shift_region_segment(
+    segment: RecognizeResponse,
+    region: SpeechRegion,
+    first_id: i32,
+    params: &RecognizeParameters,
+) -> RecognizeResponse {
+    let mut segment = shift_segment(segment, region.start, first_id);
+    if params.is_speech_regions_enable() {
+        segment.speech_region = Some(region);
     }
+
+    segment
+}
+
+/// Samples selected by `offset_ms` and `duration_ms` parameters.
+pub(crate) fn requested_range(audio_len: usize, params: &RecognizeParameters) -> Range<usize> {
+    let start = ms_to_samples(params.get_offset_ms()).min(audio_len);
+    let end = match params.get_duration_ms() {
+        0 => audio_len,
+        duration_ms => (start + ms_to_samples(duration_ms)).min(audio_len),
+    };
+
+    start..end
+}
+
+pub(crate) fn ms_to_samples(millis: i32) -> usize {
+    millis.max(0) as usize * WHISPER_SAMPLE_RATE as usize / 1000
+}
+
+pub(crate) fn samples_to_centis(samples: usize) -> i64 {
+    (samples * 100 / WHISPER_SAMPLE_RATE as usize) as i64
 }
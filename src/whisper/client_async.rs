@@ -1,23 +1,483 @@
-use crate::whisper::client::WhisperClient;
-use crate::whisper::config::WhisperClientConfig;
+use crate::whisper::audio::{AudioProbe, WHISPER_SAMPLE_RATE};
+use crate::whisper::chunking::{self, PartObserver, PartsProgress};
+use crate::whisper::client::{self, WhisperClient};
+use crate::whisper::config::{WhisperClientConfig, DEFAULT_MODEL_NAME};
+use crate::whisper::engine::{RecognizeObserver, SpeechEngine};
+use crate::whisper::errors::{RecognizeError, RecognizeResult};
+use crate::whisper::forms::{LanguageProbability, RecognizeParameters, RecognizeResponse};
+use crate::whisper::speakers;
 
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::{RwLock, RwLockWriteGuard};
+use tokio::sync::{mpsc, Semaphore};
 
+/// Registry of loaded models shared between actix workers.
 #[derive(Clone)]
 pub struct WhisperAsyncClient {
-    client: Arc<RwLock<WhisperClient>>,
+    models: HashMap<String, ModelClient>,
+    default_model: String,
 }
 
 impl WhisperAsyncClient {
     pub fn new(cfg: &WhisperClientConfig) -> Self {
-        let whisper_client = WhisperClient::new(cfg);
+        let models = cfg
+            .get_models()
+            .iter()
+            .map(|model| {
+                let whisper_client = WhisperClient::new(cfg, model);
+                let model_client = ModelClient::new(model.get_name(), whisper_client)
+                    .with_queue_size(cfg.get_queue_size())
+                    .with_max_duration(cfg.get_max_duration_secs());
+                (model.get_name().to_string(), model_client)
+            })
+            .collect::<HashMap<String, ModelClient>>();
 
         WhisperAsyncClient {
-            client: Arc::new(RwLock::new(whisper_client)),
+            models,
+            default_model: cfg.get_default_model().to_string(),
         }
     }
-    pub async fn get_client(&self) -> RwLockWriteGuard<'_, WhisperClient> {
-        self.client.write().await
+
+    pub fn with_engine<E: SpeechEngine + 'static>(engine: E) -> Self {
+        let whisper_client = WhisperClient::with_engine(engine);
+        let model_client = ModelClient::new(DEFAULT_MODEL_NAME, whisper_client);
+        WhisperAsyncClient {
+            models: HashMap::from([(DEFAULT_MODEL_NAME.to_string(), model_client)]),
+            default_model: DEFAULT_MODEL_NAME.to_string(),
+        }
+    }
+
+    /// Rejects audio longer than `max_duration_secs` for every registered model.
+    pub fn with_max_duration(mut self, max_duration_secs: Option<u64>) -> Self {
+        self.models = self
+            .models
+            .into_iter()
+            .map(|(name, model_client)| (name, model_client.with_max_duration(max_duration_secs)))
+            .collect();
+        self
+    }
+
+    pub fn with_queue_size(mut self, queue_size: Option<usize>) -> Self {
+        self.models = self
+            .models
+            .into_iter()
+            .map(|(name, model_client)| (name, model_client.with_queue_size(queue_size)))
+            .collect();
+        self
+    }
+
+    pub fn register_engine<E: SpeechEngine + 'static>(&mut self, name: &str, engine: E) {
+        let whisper_client = WhisperClient::with_engine(engine);
+        let model_client = ModelClient::new(name, whisper_client);
+        self.models.insert(name.to_string(), model_client);
+    }
+
+    pub fn get_default_model(&self) -> &str {
+        self.default_model.as_str()
+    }
+
+    /// Returns the model registered under the name or the default one if no name passed.
+    pub fn get_model(&self, name: Option<&str>) -> RecognizeResult<&ModelClient> {
+        let name = name.unwrap_or(self.default_model.as_str());
+        self.models
+            .get(name)
+            .ok_or_else(|| RecognizeError::UnknownModel(name.to_string()))
+    }
+}
+
+/// Shares one loaded engine between actix workers. Every recognition holds
+/// a permit while it runs, so at most `capacity` inferences are decoded in
+/// parallel and the rest wait in FIFO order. Audio preparation (ffmpeg,
+/// wav parsing) happens before a permit is requested. When queue size is
+/// set, requests exceeding `capacity + queue_size` are rejected as busy.
+/// Audio longer than max duration is rejected once its headers are probed.
+#[derive(Clone)]
+pub struct ModelClient {
+    name: String,
+    client: Arc<WhisperClient>,
+    permits: Arc<Semaphore>,
+    capacity: usize,
+    queue_size: Option<usize>,
+    max_duration_secs: Option<u64>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl ModelClient {
+    fn new(name: &str, whisper_client: WhisperClient) -> Self {
+        let capacity = whisper_client.capacity();
+        ModelClient {
+            name: name.to_string(),
+            client: Arc::new(whisper_client),
+            permits: Arc::new(Semaphore::new(capacity)),
+            capacity,
+            queue_size: None,
+            max_duration_secs: None,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn with_queue_size(mut self, queue_size: Option<usize>) -> Self {
+        self.queue_size = queue_size;
+        self
+    }
+
+    fn with_max_duration(mut self, max_duration_secs: Option<u64>) -> Self {
+        self.max_duration_secs = max_duration_secs;
+        self
+    }
+
+    pub fn get_name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub async fn recognize_file(&self, file_path: &str, params: &RecognizeParameters) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let (segments, _) = self.recognize_file_timed(file_path, params).await?;
+        Ok(segments)
+    }
+
+    /// Same as `recognize_file`, also returns duration of decoded audio in seconds.
+    pub async fn recognize_file_timed(
+        &self,
+        file_path: &str,
+        params: &RecognizeParameters,
+    ) -> RecognizeResult<(Vec<RecognizeResponse>, f64)> {
+        let _in_flight = self.enter_queue()?;
+        let channels = self.decode_channels(file_path, params).await?;
+        let duration_secs = channels.first().map_or(0, Vec::len) as f64 / WHISPER_SAMPLE_RATE as f64;
+        let segments = self.recognize_channels(channels, params, None).await?;
+        Ok((segments, duration_secs))
+    }
+
+    /// Decodes audio file and starts recognition in background. Segments are
+    /// sent to the returned receiver as soon as they are decoded, recognition
+    /// error is sent last. Queue and decoding errors are returned at once.
+    /// Recognition is aborted once the receiver is dropped.
+    pub async fn stream_file(
+        &self,
+        file_path: &str,
+        params: &RecognizeParameters,
+    ) -> RecognizeResult<mpsc::UnboundedReceiver<RecognizeResult<RecognizeResponse>>> {
+        let in_flight = self.enter_queue()?;
+        let audio = self.decode_file(file_path).await?;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let observer = Arc::new(SegmentSender {
+            model: self.name.to_owned(),
+            sender: sender.clone(),
+        });
+
+        let client = self.clone();
+        let params = params.clone();
+        tokio::spawn(async move {
+            let _in_flight = in_flight;
+            if let Err(err) = client.recognize_audio_observed(audio, &params, observer).await {
+                let _ = sender.send(Err(err));
+            }
+        });
+
+        Ok(receiver)
+    }
+
+    /// Decodes audio file to 16 kHz mono samples without waiting for a permit.
+    pub async fn decode_file(&self, file_path: &str) -> RecognizeResult<Vec<f32>> {
+        let probed = self.probe_limited(file_path).await?;
+        let audio = WhisperClient::load_file(file_path, probed.as_ref()).await?;
+        self.check_duration(audio.len() as f64 / WHISPER_SAMPLE_RATE as f64)?;
+        Ok(audio)
+    }
+
+    /// Decodes audio file to 16 kHz samples of every channel in `split` channels
+    /// mode or of the single mixed channel otherwise.
+    pub async fn decode_channels(&self, file_path: &str, params: &RecognizeParameters) -> RecognizeResult<Vec<Vec<f32>>> {
+        let probed = self.probe_limited(file_path).await?;
+        let split_channels = params.is_split_channels_enable();
+        let channels = WhisperClient::load_file_channels(file_path, split_channels, probed.as_ref()).await?;
+        let samples_count = channels.first().map_or(0, Vec::len);
+        self.check_duration(samples_count as f64 / WHISPER_SAMPLE_RATE as f64)?;
+        Ok(channels)
+    }
+
+    /// Probes file headers, so audio of unsupported codec or longer than max
+    /// duration is rejected before it is decoded. Duration of decoded audio is
+    /// checked again since some containers do not store it.
+    pub async fn check_file(&self, file_path: &str) -> RecognizeResult<()> {
+        self.probe_checked(file_path).await?;
+        Ok(())
+    }
+
+    /// Checks file before decoding if max duration is set, the probe is passed
+    /// on to decoding so the file is not probed twice.
+    async fn probe_limited(&self, file_path: &str) -> RecognizeResult<Option<AudioProbe>> {
+        match self.max_duration_secs {
+            Some(_) => self.probe_checked(file_path).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    async fn probe_checked(&self, file_path: &str) -> RecognizeResult<AudioProbe> {
+        let probed = WhisperClient::probe_file(file_path).await?;
+        if let Some(duration_secs) = probed.duration_secs {
+            self.check_duration(duration_secs)?;
+        }
+
+        Ok(probed)
+    }
+
+    fn check_duration(&self, duration_secs: f64) -> RecognizeResult<()> {
+        match self.max_duration_secs {
+            Some(max_duration_secs) if duration_secs > max_duration_secs as f64 => {
+                let msg = format!("{:.0} seconds exceed {} seconds", duration_secs, max_duration_secs);
+                Err(RecognizeError::TooLong(msg))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Recognizes channels returned by `decode_channels`. Channels are recognized
+    /// concurrently as far as engine pool allows, their segments are labeled with
+    /// channel and interleaved by time. Observer gets segments once all channels are done.
+    pub async fn recognize_channels(
+        &self,
+        channels: Vec<Vec<f32>>,
+        params: &RecognizeParameters,
+        observer: Option<Arc<dyn RecognizeObserver>>,
+    ) -> RecognizeResult<Vec<RecognizeResponse>> {
+        if !params.is_split_channels_enable() {
+            let audio = channels.into_iter().next().unwrap_or_default();
+            return match observer {
+                Some(observer) => self.recognize_audio_observed(audio, params, observer).await,
+                None => self.recognize_audio(audio, params).await,
+            };
+        }
+
+        let progress = observer
+            .clone()
+            .map(|observer| Arc::new(PartsProgress::new(observer, channels.len())));
+        let tasks = channels.into_iter().enumerate().map(|(channel, audio)| {
+            let channel_observer = progress.clone().map(|progress| PartObserver { progress, part_id: channel });
+            async move {
+                let segments = match channel_observer {
+                    Some(channel_observer) => {
+                        self.recognize_audio_observed(audio, params, Arc::new(channel_observer))
+                            .await?
+                    }
+                    None => self.recognize_audio(audio, params).await?,
+                };
+
+                let segments = segments
+                    .into_iter()
+                    .map(|segment| RecognizeResponse {
+                        channel: Some(channel),
+                        channel_name: params.get_channel_name(channel).map(str::to_string),
+                        ..segment
+                    })
+                    .collect::<Vec<RecognizeResponse>>();
+                RecognizeResult::Ok(segments)
+            }
+        });
+
+        let mut dialog = futures_util::future::try_join_all(tasks)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<RecognizeResponse>>();
+        dialog.sort_by_key(|segment| (segment.frame_start, segment.channel));
+        dialog
+            .iter_mut()
+            .enumerate()
+            .for_each(|(id, segment)| segment.frame_id = id as i32);
+
+        if let Some(observer) = observer {
+            dialog.iter().for_each(|segment| observer.on_segment(segment));
+            observer.on_progress(100);
+        }
+
+        Ok(dialog)
+    }
+
+    /// Recognizes decoded samples. Unlike `recognize_file` it is not rejected
+    /// when the queue is full, callers are expected to bound concurrency.
+    pub async fn recognize_audio(&self, audio: Vec<f32>, params: &RecognizeParameters) -> RecognizeResult<Vec<RecognizeResponse>> {
+        if params.is_parallel_enable() {
+            return self.recognize_parallel(audio, params, None).await;
+        }
+
+        let params = params.clone();
+        self.run_recognition(move |client| client.recognize(&audio, &params))
+            .await
+    }
+
+    /// Same as `recognize_audio`, reporting inference progress to the observer.
+    pub async fn recognize_audio_observed(
+        &self,
+        audio: Vec<f32>,
+        params: &RecognizeParameters,
+        observer: Arc<dyn RecognizeObserver>,
+    ) -> RecognizeResult<Vec<RecognizeResponse>> {
+        if params.is_parallel_enable() {
+            return self.recognize_parallel(audio, params, Some(observer)).await;
+        }
+
+        let params = params.clone();
+        self.run_recognition(move |client| client.recognize_observed(&audio, &params, observer.as_ref()))
+            .await
+    }
+
+    /// Recognizes chunks of long audio concurrently as far as engine pool allows
+    /// and stitches their segments. Observer gets segments once all chunks are done.
+    async fn recognize_parallel(
+        &self,
+        mut audio: Vec<f32>,
+        params: &RecognizeParameters,
+        observer: Option<Arc<dyn RecognizeObserver>>,
+    ) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let requested = client::requested_range(audio.len(), params);
+        let offset = client::samples_to_centis(requested.start);
+        audio.truncate(requested.end);
+        audio.drain(..requested.start);
+        let audio = Arc::new(audio);
+
+        let mut chunk_params = params.clone();
+        chunk_params.set_offset_ms(0);
+        chunk_params.set_duration_ms(0);
+
+        let chunks = chunking::split_chunks(&audio, params);
+        let progress = observer
+            .clone()
+            .map(|observer| Arc::new(PartsProgress::new(observer, chunks.len())));
+        let tasks = chunks.iter().enumerate().map(|(chunk_id, chunk)| {
+            let audio = audio.clone();
+            let params = chunk_params.clone();
+            let window = chunk.window.clone();
+            let shift = client::samples_to_centis(window.start);
+            let chunk_observer = progress.clone().map(|progress| PartObserver { progress, part_id: chunk_id });
+            async move {
+                let segments = self
+                    .run_recognition(move |client| match chunk_observer.as_ref() {
+                        Some(chunk_observer) => client.recognize_observed(&audio[window], &params, chunk_observer),
+                        None => client.recognize(&audio[window], &params),
+                    })
+                    .await?;
+
+                let segments = segments
+                    .into_iter()
+                    .map(|segment| client::shift_segment(segment, shift, 0))
+                    .collect::<Vec<RecognizeResponse>>();
+                RecognizeResult::Ok(segments)
+            }
+        });
+
+        let recognized = futures_util::future::try_join_all(tasks).await?;
+        let mut stitched = chunking::stitch(&chunks, recognized)
+            .into_iter()
+            .map(|segment| client::shift_segment(segment, offset, 0))
+            .collect::<Vec<RecognizeResponse>>();
+
+        // Speakers of every chunk are counted from zero, so they are counted
+        // again over the whole audio.
+        if params.is_speaker_turns_enable() {
+            speakers::label_speakers(&mut stitched, params);
+        }
+
+        if let Some(observer) = observer {
+            stitched.iter().for_each(|segment| observer.on_segment(segment));
+            observer.on_progress(100);
+        }
+
+        Ok(stitched)
+    }
+
+    pub async fn recognize_chunk(&self, audio_data: Vec<u8>, params: &RecognizeParameters) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let _in_flight = self.enter_queue()?;
+        let params = params.clone();
+        self.run_recognition(move |client| client.recognize_chunk(&audio_data, &params))
+            .await
+    }
+
+    fn enter_queue(&self) -> RecognizeResult<InFlightGuard> {
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let guard = InFlightGuard(self.in_flight.clone());
+        match self.queue_size {
+            Some(queue_size) if in_flight >= self.capacity + queue_size => {
+                let msg = format!("{} requests are waiting for model {}", in_flight - self.capacity, self.name);
+                Err(RecognizeError::EngineBusy(msg))
+            }
+            _ => Ok(guard),
+        }
+    }
+
+    /// Decodes audio file and detects spoken language within its first seconds.
+    pub async fn detect_language(
+        &self,
+        file_path: &str,
+        duration_secs: u32,
+        threads: i32,
+    ) -> RecognizeResult<Vec<LanguageProbability>> {
+        let _in_flight = self.enter_queue()?;
+        let mut audio = self.decode_file(file_path).await?;
+        audio.truncate(duration_secs as usize * WHISPER_SAMPLE_RATE as usize);
+
+        self.run_blocking(move |client| client.detect_language(&audio, threads))
+            .await
+    }
+
+    async fn run_recognition<F>(&self, task: F) -> RecognizeResult<Vec<RecognizeResponse>>
+    where
+        F: FnOnce(&WhisperClient) -> RecognizeResult<Vec<RecognizeResponse>> + Send + 'static,
+    {
+        let mut recognized = self.run_blocking(task).await?;
+        recognized
+            .iter_mut()
+            .for_each(|segment| segment.model = self.name.to_owned());
+
+        Ok(recognized)
+    }
+
+    async fn run_blocking<F, T>(&self, task: F) -> RecognizeResult<T>
+    where
+        F: FnOnce(&WhisperClient) -> RecognizeResult<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let _permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|err| RecognizeError::Interrupted(err.to_string()))?;
+
+        let client = self.client.clone();
+        tokio::task::spawn_blocking(move || task(&client))
+            .await
+            .map_err(|err| RecognizeError::Interrupted(err.to_string()))?
+    }
+}
+
+/// Passes decoded segments to the streaming response.
+struct SegmentSender {
+    model: String,
+    sender: mpsc::UnboundedSender<RecognizeResult<RecognizeResponse>>,
+}
+
+impl RecognizeObserver for SegmentSender {
+    fn on_segment(&self, segment: &RecognizeResponse) {
+        let mut segment = segment.clone();
+        segment.model = self.model.to_owned();
+        let _ = self.sender.send(Ok(segment));
+    }
+
+    /// Receiver is dropped once client has disconnected, so the engine
+    /// is released for queued requests instead of decoding till the end.
+    fn is_cancelled(&self) -> bool {
+        self.sender.is_closed()
+    }
+}
+
+/// Counts the request as in progress until it is dropped.
+struct InFlightGuard(Arc<AtomicUsize>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
     }
 }
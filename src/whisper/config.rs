@@ -1,37 +1,236 @@
+use crate::whisper::forms::{self, DecodingStrategy};
+
+use std::fmt::Debug;
 use std::str::FromStr;
 
-pub struct WhisperClientConfig {
+const DEFAULT_POOL_SIZE: usize = 1;
+/// Name of the model loaded from `WHISPER_MODEL_PATH` or wrapping a custom engine.
+pub(crate) const DEFAULT_MODEL_NAME: &str = "default";
+
+#[derive(Clone)]
+pub struct WhisperModelConfig {
+    name: String,
     model_path: String,
+}
+
+impl WhisperModelConfig {
+    pub fn new(name: &str, model_path: &str) -> Self {
+        WhisperModelConfig {
+            name: name.to_string(),
+            model_path: model_path.to_string(),
+        }
+    }
+    pub fn get_name(&self) -> &str {
+        self.name.as_str()
+    }
+    pub fn get_model_path(&self) -> &str {
+        self.model_path.as_str()
+    }
+}
+
+/// Server defaults of decoding parameters which requests do not pass.
+#[derive(Clone)]
+pub struct DecodingConfig {
+    strategy: DecodingStrategy,
+    best_of: i32,
+    beam_size: i32,
+    patience: f32,
+    temperature_inc: f32,
+    entropy_thold: f32,
+    logprob_thold: f32,
+}
+
+impl DecodingConfig {
+    pub fn from_env() -> Self {
+        let default = DecodingConfig::default();
+        let strategy = match std::env::var("WHISPER_DECODING_STRATEGY").as_deref() {
+            Err(_) => default.strategy,
+            Ok("greedy") => DecodingStrategy::Greedy,
+            Ok("beam_search") => DecodingStrategy::BeamSearch,
+            Ok(_) => panic!("incorrect WHISPER_DECODING_STRATEGY value, expected greedy or beam_search"),
+        };
+
+        let decoding = DecodingConfig {
+            strategy,
+            best_of: parse_env("WHISPER_BEST_OF").unwrap_or(default.best_of),
+            beam_size: parse_env("WHISPER_BEAM_SIZE").unwrap_or(default.beam_size),
+            patience: parse_env("WHISPER_PATIENCE").unwrap_or(default.patience),
+            temperature_inc: parse_env("WHISPER_TEMPERATURE_INC").unwrap_or(default.temperature_inc),
+            entropy_thold: parse_env("WHISPER_ENTROPY_THOLD").unwrap_or(default.entropy_thold),
+            logprob_thold: parse_env("WHISPER_LOGPROB_THOLD").unwrap_or(default.logprob_thold),
+        };
+
+        let validate_res = forms::validate_decoding(
+            Some(decoding.best_of),
+            Some(decoding.beam_size),
+            Some(decoding.patience),
+            Some(decoding.temperature_inc),
+            Some(decoding.entropy_thold),
+            Some(decoding.logprob_thold),
+        );
+        if let Err(err) = validate_res {
+            panic!("incorrect whisper decoding defaults: {}", err);
+        }
+
+        decoding
+    }
+    pub fn get_strategy(&self) -> DecodingStrategy {
+        self.strategy
+    }
+    pub fn get_best_of(&self) -> i32 {
+        self.best_of
+    }
+    pub fn get_beam_size(&self) -> i32 {
+        self.beam_size
+    }
+    pub fn get_patience(&self) -> f32 {
+        self.patience
+    }
+    pub fn get_temperature_inc(&self) -> f32 {
+        self.temperature_inc
+    }
+    pub fn get_entropy_thold(&self) -> f32 {
+        self.entropy_thold
+    }
+    pub fn get_logprob_thold(&self) -> f32 {
+        self.logprob_thold
+    }
+}
+
+/// Greedy decoding with whisper.cpp fallback thresholds.
+impl Default for DecodingConfig {
+    fn default() -> Self {
+        DecodingConfig {
+            strategy: DecodingStrategy::Greedy,
+            best_of: 1,
+            beam_size: 5,
+            patience: -1.0,
+            temperature_inc: 0.2,
+            entropy_thold: 2.4,
+            logprob_thold: -1.0,
+        }
+    }
+}
+
+pub struct WhisperClientConfig {
+    models: Vec<WhisperModelConfig>,
+    default_model: String,
     enable_gpu: bool,
+    pool_size: usize,
+    queue_size: Option<usize>,
+    max_duration_secs: Option<u64>,
+    decoding: DecodingConfig,
 }
 
 impl WhisperClientConfig {
     pub fn from_env() -> Self {
-        let model_path = std::env::var("WHISPER_MODEL_PATH")
-            .expect("failed while getting WHISPER_MODEL_PATH value");
+        // WHISPER_MODELS holds a list of named models like `tiny=./models/ggml-tiny.bin,base=...`.
+        // The single WHISPER_MODEL_PATH is still accepted and registered as the `default` model.
+        let models = match std::env::var("WHISPER_MODELS") {
+            Ok(models_data) => Self::parse_models(models_data.as_str()),
+            Err(_) => {
+                let model_path = std::env::var("WHISPER_MODEL_PATH")
+                    .expect("failed while getting WHISPER_MODELS or WHISPER_MODEL_PATH value");
+                vec![WhisperModelConfig::new(DEFAULT_MODEL_NAME, model_path.as_str())]
+            }
+        };
+
+        let default_model = std::env::var("WHISPER_DEFAULT_MODEL")
+            .unwrap_or_else(|_| models[0].get_name().to_string());
+        assert!(
+            models.iter().any(|model| model.get_name() == default_model),
+            "WHISPER_DEFAULT_MODEL does not match any of loaded models"
+        );
+
         let enable_gpu_data = std::env::var("WHISPER_ENABLE_GPU")
             .expect("failed while getting WHISPER_ENABLE_GPU value");
         let enable_gpu = bool::from_str(enable_gpu_data.as_str())
             .expect("incorrect WHISPER_ENABLE_GPU value");
+        let pool_size = std::env::var("WHISPER_POOL_SIZE")
+            .map(|value| usize::from_str(value.as_str()).expect("incorrect WHISPER_POOL_SIZE value"))
+            .unwrap_or(DEFAULT_POOL_SIZE);
+
+        assert!(pool_size > 0, "WHISPER_POOL_SIZE must be greater than zero");
+
+        // Requests waiting for a free engine above WHISPER_QUEUE_SIZE are rejected as busy.
+        let queue_size = std::env::var("WHISPER_QUEUE_SIZE")
+            .ok()
+            .map(|value| usize::from_str(value.as_str()).expect("incorrect WHISPER_QUEUE_SIZE value"));
+
+        // Audio longer than WHISPER_MAX_DURATION_SECS is rejected once its headers are probed.
+        let max_duration_secs = std::env::var("WHISPER_MAX_DURATION_SECS")
+            .ok()
+            .map(|value| u64::from_str(value.as_str()).expect("incorrect WHISPER_MAX_DURATION_SECS value"));
 
         WhisperClientConfig {
-            model_path,
+            models,
+            default_model,
             enable_gpu,
+            pool_size,
+            queue_size,
+            max_duration_secs,
+            decoding: DecodingConfig::from_env(),
         }
     }
-    pub fn get_model_path(&self) -> &str {
-        self.model_path.as_str()
+    pub fn get_models(&self) -> &[WhisperModelConfig] {
+        self.models.as_slice()
+    }
+    pub fn get_default_model(&self) -> &str {
+        self.default_model.as_str()
     }
     pub fn is_gpu_enabled(&self) -> bool {
         self.enable_gpu
     }
+    pub fn get_pool_size(&self) -> usize {
+        self.pool_size
+    }
+    pub fn get_queue_size(&self) -> Option<usize> {
+        self.queue_size
+    }
+    pub fn get_max_duration_secs(&self) -> Option<u64> {
+        self.max_duration_secs
+    }
+    pub fn get_decoding(&self) -> &DecodingConfig {
+        &self.decoding
+    }
+
+    fn parse_models(models_data: &str) -> Vec<WhisperModelConfig> {
+        let models = models_data
+            .split(',')
+            .map(str::trim)
+            .filter(|model_data| !model_data.is_empty())
+            .map(|model_data| {
+                let (name, model_path) = model_data
+                    .split_once('=')
+                    .expect("incorrect WHISPER_MODELS value, expected name=path pairs");
+                WhisperModelConfig::new(name.trim(), model_path.trim())
+            })
+            .collect::<Vec<WhisperModelConfig>>();
+
+        assert!(!models.is_empty(), "WHISPER_MODELS must contain at least one model");
+        models
+    }
 }
 
 impl Default for WhisperClientConfig {
     fn default() -> Self {
         WhisperClientConfig {
-            model_path: "./models/ggml-base.en.bin".to_string(),
+            models: vec![WhisperModelConfig::new(DEFAULT_MODEL_NAME, "./models/ggml-base.en.bin")],
+            default_model: DEFAULT_MODEL_NAME.to_string(),
             enable_gpu: false,
+            pool_size: DEFAULT_POOL_SIZE,
+            queue_size: None,
+            max_duration_secs: None,
+            decoding: DecodingConfig::default(),
         }
     }
 }
+
+fn parse_env<T: FromStr>(name: &str) -> Option<T>
+where
+    T::Err: Debug,
+{
+    std::env::var(name).ok().map(|value| {
+        T::from_str(value.as_str()).unwrap_or_else(|err| panic!("incorrect {} value: {:?}", name, err))
+    })
+}
@@ -0,0 +1,217 @@
+use crate::whisper::audio::{deinterleave, downmix, AudioProbe, WHISPER_SAMPLE_RATE};
+use crate::whisper::errors::{RecognizeError, RecognizeResult};
+
+use rubato::{FftFixedIn, Resampler};
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{Cursor, ErrorKind};
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+const RESAMPLE_CHUNK_SIZE: usize = 1024;
+const RESAMPLE_SUB_CHUNKS: usize = 2;
+
+/// Decodes audio file in-process and converts it to 16 kHz samples of every
+/// channel if `split_channels` is passed or of the single mixed one otherwise.
+pub(crate) fn decode_file_channels(file_path: &str, split_channels: bool) -> RecognizeResult<Vec<Vec<f32>>> {
+    let file = File::open(file_path)
+        .map_err(|err| RecognizeError::DecodeAudio(err.to_string()))?;
+    let extension = Path::new(file_path)
+        .extension()
+        .and_then(OsStr::to_str);
+
+    decode_source(Box::new(file), extension, split_channels)
+}
+
+/// Decodes in-memory audio data and converts it to 16 kHz mono samples.
+pub(crate) fn decode_bytes(audio_data: Vec<u8>) -> RecognizeResult<Vec<f32>> {
+    let mut channels = decode_source(Box::new(Cursor::new(audio_data)), None, false)?;
+    Ok(channels.remove(0))
+}
+
+/// Reads duration from container headers and checks that codec of the audio
+/// track is supported, nothing is decoded yet.
+pub(crate) fn probe_file(file_path: &str) -> RecognizeResult<AudioProbe> {
+    let file = File::open(file_path)
+        .map_err(|err| RecognizeError::DecodeAudio(err.to_string()))?;
+    let extension = Path::new(file_path)
+        .extension()
+        .and_then(OsStr::to_str);
+
+    let codec_params = open_track(Box::new(file), extension)?.codec_params;
+    let duration_secs = match (codec_params.n_frames, codec_params.sample_rate, codec_params.time_base) {
+        (Some(n_frames), Some(sample_rate), _) => Some(n_frames as f64 / sample_rate as f64),
+        (Some(n_frames), None, Some(time_base)) => {
+            let time = time_base.calc_time(n_frames);
+            Some(time.seconds as f64 + time.frac)
+        }
+        _ => None,
+    };
+
+    Ok(AudioProbe { duration_secs })
+}
+
+/// Demuxer and decoder of the first audio track.
+struct AudioTrack {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    codec_params: CodecParameters,
+    track_id: u32,
+}
+
+fn open_track(source: Box<dyn MediaSource>, extension: Option<&str>) -> RecognizeResult<AudioTrack> {
+    let mut hint = Hint::new();
+    if let Some(extension) = extension {
+        hint.with_extension(extension);
+    }
+
+    let stream = MediaSourceStream::new(source, Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|err| RecognizeError::UnsupportedAudio(err.to_string()))?;
+
+    let format = probed.format;
+    let track = format
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| RecognizeError::UnsupportedAudio("no audio track found".to_string()))?;
+
+    let track_id = track.id;
+    let decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|err| RecognizeError::UnsupportedAudio(err.to_string()))?;
+
+    Ok(AudioTrack {
+        codec_params: track.codec_params.clone(),
+        format,
+        decoder,
+        track_id,
+    })
+}
+
+fn decode_source(
+    source: Box<dyn MediaSource>,
+    extension: Option<&str>,
+    split_channels: bool,
+) -> RecognizeResult<Vec<Vec<f32>>> {
+    let AudioTrack {
+        mut format,
+        mut decoder,
+        codec_params,
+        track_id,
+    } = open_track(source, extension)?;
+
+    let mut sample_rate = codec_params.sample_rate;
+    let mut channels = vec![Vec::new()];
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(RecognizeError::DecodeAudio(err.to_string())),
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(SymphoniaError::DecodeError(err)) => {
+                log::warn!("Skipped corrupted audio packet: {}", err);
+                continue;
+            }
+            Err(err) => return Err(RecognizeError::DecodeAudio(err.to_string())),
+        };
+
+        let spec = *decoded.spec();
+        sample_rate.get_or_insert(spec.rate);
+
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+        match split_channels {
+            true => append_channels(&mut channels, deinterleave(buffer.samples(), spec.channels.count())),
+            false => channels[0].extend(downmix(buffer.samples(), spec.channels.count())),
+        }
+    }
+
+    let sample_rate = sample_rate
+        .ok_or_else(|| RecognizeError::DecodeAudio("audio track is empty".to_string()))?;
+    channels
+        .iter()
+        .map(|channel| resample(channel, sample_rate, WHISPER_SAMPLE_RATE))
+        .collect()
+}
+
+/// Appends decoded packet to channels, channels missing in packet get silence
+/// so all of them stay aligned in time.
+fn append_channels(channels: &mut Vec<Vec<f32>>, packet: Vec<Vec<f32>>) {
+    let frames = packet.first().map_or(0, Vec::len);
+    let decoded_len = channels[0].len();
+    channels.resize_with(channels.len().max(packet.len()), || vec![0.0; decoded_len]);
+
+    let mut packet = packet.into_iter();
+    for channel in channels.iter_mut() {
+        match packet.next() {
+            Some(samples) => channel.extend(samples),
+            None => channel.resize(channel.len() + frames, 0.0),
+        }
+    }
+}
+
+/// FFT based resampling of mono samples.
+pub(crate) fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> RecognizeResult<Vec<f32>> {
+    if from_rate == to_rate || samples.is_empty() {
+        return Ok(samples.to_vec());
+    }
+
+    let resample_error = |err: &dyn std::error::Error| RecognizeError::DecodeAudio(err.to_string());
+    let mut resampler = FftFixedIn::<f32>::new(
+        from_rate as usize,
+        to_rate as usize,
+        RESAMPLE_CHUNK_SIZE,
+        RESAMPLE_SUB_CHUNKS,
+        1,
+    )
+    .map_err(|err| resample_error(&err))?;
+
+    let delay = resampler.output_delay();
+    let expected_len = (samples.len() as u64 * to_rate as u64 / from_rate as u64) as usize;
+    let mut resampled = Vec::with_capacity(expected_len + delay);
+
+    let mut chunks = samples.chunks_exact(RESAMPLE_CHUNK_SIZE);
+    for chunk in chunks.by_ref() {
+        let output = resampler
+            .process(&[chunk], None)
+            .map_err(|err| resample_error(&err))?;
+        resampled.extend_from_slice(&output[0]);
+    }
+
+    let remainder = chunks.remainder();
+    if !remainder.is_empty() {
+        let output = resampler
+            .process_partial(Some(&[remainder]), None)
+            .map_err(|err| resample_error(&err))?;
+        resampled.extend_from_slice(&output[0]);
+    }
+
+    // Flush frames which are still delayed inside of resampler.
+    while resampled.len() < expected_len + delay {
+        let output = resampler
+            .process_partial::<&[f32]>(None, None)
+            .map_err(|err| resample_error(&err))?;
+        if output[0].is_empty() {
+            break;
+        }
+        resampled.extend_from_slice(&output[0]);
+    }
+
+    resampled.truncate(expected_len + delay);
+    Ok(resampled.split_off(delay.min(resampled.len())))
+}
@@ -0,0 +1,498 @@
+use crate::whisper::config::{DecodingConfig, WhisperClientConfig, WhisperModelConfig};
+use crate::whisper::errors::{RecognizeError, RecognizeResult};
+use crate::whisper::forms::{DecodingStrategy, LanguageProbability, RecognizeParameters, RecognizeResponse, RecognizedWord, SegmentConfidence};
+
+use std::cell::RefCell;
+use std::ffi::{c_int, c_void, CStr};
+use std::sync::{Mutex, PoisonError};
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperState};
+use whisper_rs_sys::{whisper_context, whisper_state};
+
+/// Receives intermediate events while engine recognizes audio.
+pub trait RecognizeObserver: Send + Sync {
+    /// Called with inference progress from 0 to 100 percent.
+    fn on_progress(&self, _percent: i32) {}
+
+    /// Called with every segment as soon as it is decoded.
+    fn on_segment(&self, _segment: &RecognizeResponse) {}
+
+    /// Polled while engine recognizes audio, recognition is aborted once
+    /// nobody waits for its results anymore.
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// Speech recognition backend which turns 16 kHz mono samples into text segments.
+pub trait SpeechEngine: Send + Sync {
+    fn recognize(&self, audio: &[f32], params: &RecognizeParameters) -> RecognizeResult<Vec<RecognizeResponse>>;
+
+    /// Recognizes audio reporting progress and segments to the observer. Engines
+    /// without intermediate results report them once recognition is done.
+    fn recognize_observed(
+        &self,
+        audio: &[f32],
+        params: &RecognizeParameters,
+        observer: &dyn RecognizeObserver,
+    ) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let recognized = self.recognize(audio, params)?;
+        recognized.iter().for_each(|segment| observer.on_segment(segment));
+        observer.on_progress(100);
+        Ok(recognized)
+    }
+
+    /// Detects spoken language returning probabilities of all languages in descending order.
+    fn detect_language(&self, audio: &[f32], threads: i32) -> RecognizeResult<Vec<LanguageProbability>>;
+
+    /// Maximum number of recognitions which may run at the same time.
+    fn capacity(&self) -> usize {
+        1
+    }
+}
+
+pub struct WhisperEngine {
+    context: &'static WhisperContext,
+    states: Mutex<Vec<WhisperState<'static>>>,
+    pool_size: usize,
+    decoding: DecodingConfig,
+}
+
+impl WhisperEngine {
+    pub fn new(cfg: &WhisperClientConfig, model: &WhisperModelConfig) -> Self {
+        let mut cxt_params = WhisperContextParameters::default();
+        cxt_params.use_gpu(cfg.is_gpu_enabled());
+
+        log::info!("Loading whisper model {} from {}", model.get_name(), model.get_model_path());
+        let whisper_ctx = WhisperContext::new_with_params(model.get_model_path(), cxt_params)
+            .expect("Failed while loading whisper model...");
+
+        // The model lives as long as the service does, so leaking the context
+        // lets pooled states borrow it without a self-referencing struct.
+        let context: &'static WhisperContext = Box::leak(Box::new(whisper_ctx));
+
+        let pool_size = cfg.get_pool_size();
+        let states = (0..pool_size)
+            .map(|_| context.create_state())
+            .collect::<Result<Vec<_>, _>>()
+            .expect("Failed while creating whisper states...");
+
+        WhisperEngine {
+            context,
+            states: Mutex::new(states),
+            pool_size,
+            decoding: cfg.get_decoding().clone(),
+        }
+    }
+
+    /// Takes a pooled state. Recognitions are bounded by the pool size with
+    /// permits of `ModelClient`, so the pool is never empty unless the bound
+    /// is broken, and no state is created beyond it.
+    fn acquire_state(&self) -> RecognizeResult<WhisperState<'static>> {
+        let pooled_state = self
+            .states
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop();
+
+        pooled_state.ok_or_else(|| {
+            let msg = format!("all {} states are in use", self.pool_size);
+            log::error!("Failed while acquiring whisper state: {}", msg);
+            RecognizeError::PoolExhausted(msg)
+        })
+    }
+
+    fn release_state(&self, state: WhisperState<'static>) {
+        self.states
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(state);
+    }
+
+    fn tokenize_prompt(&self, params: &RecognizeParameters) -> RecognizeResult<Vec<c_int>> {
+        match params.get_initial_prompt() {
+            None => Ok(Vec::default()),
+            Some(prompt) => {
+                // whisper.cpp keeps at most a half of text context for the prompt and fails
+                // to tokenize longer text, so the whole prompt is tokenized and only its
+                // last tokens are kept like OpenAI does. A token takes at least one byte.
+                let max_tokens = (self.context.n_text_ctx() / 2) as usize;
+                let mut tokens = self.context.tokenize(prompt, prompt.len() + 1)?;
+                tokens.drain(..tokens.len().saturating_sub(max_tokens));
+                Ok(tokens)
+            }
+        }
+    }
+
+    /// Maps request parameters onto whisper decoding parameters, missing
+    /// decoding parameters are taken from server defaults.
+    fn build_full_params<'a, 'b>(&self, params: &'a RecognizeParameters, prompt_tokens: &'b [c_int]) -> FullParams<'a, 'b> {
+        let decoding = &self.decoding;
+        let strategy = match params.get_strategy().unwrap_or(decoding.get_strategy()) {
+            DecodingStrategy::Greedy => SamplingStrategy::Greedy {
+                best_of: params.get_best_of().unwrap_or(decoding.get_best_of()),
+            },
+            DecodingStrategy::BeamSearch => SamplingStrategy::BeamSearch {
+                beam_size: params.get_beam_size().unwrap_or(decoding.get_beam_size()),
+                patience: params.get_patience().unwrap_or(decoding.get_patience()),
+            },
+        };
+
+        let mut full_params = FullParams::new(strategy);
+        full_params.set_language(params.get_lang());
+        full_params.set_n_threads(params.get_threads());
+        full_params.set_translate(params.is_translate_enable());
+        full_params.set_offset_ms(params.get_offset_ms());
+        full_params.set_duration_ms(params.get_duration_ms());
+        full_params.set_temperature(params.get_temperature());
+        full_params.set_temperature_inc(params.get_temperature_inc().unwrap_or(decoding.get_temperature_inc()));
+        full_params.set_entropy_thold(params.get_entropy_thold().unwrap_or(decoding.get_entropy_thold()));
+        full_params.set_logprob_thold(params.get_logprob_thold().unwrap_or(decoding.get_logprob_thold()));
+        full_params.set_tokens(prompt_tokens);
+        full_params.set_print_special(params.is_print_spec_enable());
+        full_params.set_print_progress(params.is_print_progress_enable());
+        full_params.set_print_realtime(params.is_print_realtime_enable());
+        full_params.set_print_timestamps(params.is_print_timestamp_enable());
+        full_params.set_token_timestamps(params.is_token_timestamps_enable());
+        full_params.set_max_len(params.get_max_segment_len());
+        full_params.set_split_on_word(params.is_split_on_word_enable());
+        full_params.set_tdrz_enable(params.is_speaker_turns_enable());
+        full_params
+    }
+
+    fn run_full(
+        &self,
+        state: &mut WhisperState,
+        audio: &[f32],
+        params: &RecognizeParameters,
+        observer: Option<&dyn RecognizeObserver>,
+    ) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let prompt_tokens = self.tokenize_prompt(params)?;
+        let mut full_params = self.build_full_params(params, &prompt_tokens);
+
+        // Whisper calls back from the same thread while `full` runs, so the
+        // callback data on this stack frame outlives every call. Speaker turns
+        // are exposed by raw state only, so they are collected by callback too.
+        let callback_data = CallbackData {
+            observer,
+            params,
+            speaker_turns: RefCell::default(),
+        };
+        if observer.is_some() || params.is_speaker_turns_enable() {
+            let user_data = &callback_data as *const CallbackData as *mut c_void;
+            unsafe {
+                full_params.set_progress_callback(Some(progress_trampoline));
+                full_params.set_progress_callback_user_data(user_data);
+                full_params.set_new_segment_callback(Some(new_segment_trampoline));
+                full_params.set_new_segment_callback_user_data(user_data);
+                full_params.set_abort_callback(Some(abort_trampoline));
+                full_params.set_abort_callback_user_data(user_data);
+            }
+        }
+
+        let full_res = state.full(full_params, audio);
+        if observer.is_some_and(|observer| observer.is_cancelled()) {
+            return Err(RecognizeError::Interrupted("recognition has been cancelled".to_string()));
+        }
+        full_res?;
+
+        let language = detected_language(params, state.full_lang_id_from_state()?);
+        let speaker_turns = callback_data.speaker_turns.take();
+        let num_segments = state.full_n_segments()?;
+        let collected_results = (0..num_segments)
+            .filter_map(|id| self.extract_segment(state, id, params).ok())
+            .map(|segment| RecognizeResponse {
+                language: language.to_owned(),
+                speaker_turn_next: params
+                    .is_speaker_turns_enable()
+                    .then(|| speaker_turns.get(segment.frame_id as usize).copied().unwrap_or_default()),
+                ..segment
+            })
+            .collect::<Vec<RecognizeResponse>>();
+
+        Ok(collected_results)
+    }
+
+    fn run_lang_detect(state: &mut WhisperState, audio: &[f32], threads: i32) -> RecognizeResult<Vec<LanguageProbability>> {
+        let threads = threads.max(1) as usize;
+        state.pcm_to_mel(audio, threads)?;
+        let probabilities = state.lang_detect(0, threads)?;
+
+        let mut languages = probabilities
+            .into_iter()
+            .enumerate()
+            .filter_map(|(lang_id, probability)| {
+                whisper_rs::get_lang_str(lang_id as i32).map(|language| LanguageProbability {
+                    language: language.to_string(),
+                    probability,
+                })
+            })
+            .collect::<Vec<LanguageProbability>>();
+        languages.sort_by(|a, b| b.probability.total_cmp(&a.probability));
+
+        Ok(languages)
+    }
+
+    fn extract_segment(&self, state: &WhisperState, segment_id: c_int, params: &RecognizeParameters) -> RecognizeResult<RecognizeResponse> {
+        let start_timestamp = state.full_get_segment_t0(segment_id)?;
+        let end_timestamp = state.full_get_segment_t1(segment_id)?;
+        let segment = state.full_get_segment_text(segment_id)?;
+        let tokens = match is_tokens_required(params) {
+            true => self.extract_tokens(state, segment_id)?,
+            false => Vec::new(),
+        };
+
+        Ok(build_segment(segment_id, start_timestamp, end_timestamp, segment, tokens, params))
+    }
+
+    fn extract_tokens(&self, state: &WhisperState, segment_id: c_int) -> RecognizeResult<Vec<SegmentToken>> {
+        let token_eot = self.context.token_eot();
+        let mut tokens = Vec::new();
+        for token_id in 0..state.full_n_tokens(segment_id)? {
+            let data = state.full_get_token_data(segment_id, token_id)?;
+            // Special and timestamp tokens follow the end of text token.
+            if data.id >= token_eot {
+                continue;
+            }
+
+            tokens.push(SegmentToken {
+                bytes: self.context.token_to_cstr(data.id)?.to_bytes().to_vec(),
+                t0: data.t0,
+                t1: data.t1,
+                probability: data.p,
+                logprob: data.plog,
+            });
+        }
+
+        Ok(tokens)
+    }
+}
+
+impl SpeechEngine for WhisperEngine {
+    fn recognize(&self, audio: &[f32], params: &RecognizeParameters) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let mut state = self.acquire_state()?;
+        let recognize_res = self.run_full(&mut state, audio, params, None);
+        self.release_state(state);
+        recognize_res
+    }
+
+    fn recognize_observed(
+        &self,
+        audio: &[f32],
+        params: &RecognizeParameters,
+        observer: &dyn RecognizeObserver,
+    ) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let mut state = self.acquire_state()?;
+        let recognize_res = self.run_full(&mut state, audio, params, Some(observer));
+        self.release_state(state);
+        recognize_res
+    }
+
+    fn detect_language(&self, audio: &[f32], threads: i32) -> RecognizeResult<Vec<LanguageProbability>> {
+        let mut state = self.acquire_state()?;
+        let detect_res = Self::run_lang_detect(&mut state, audio, threads);
+        self.release_state(state);
+        detect_res
+    }
+
+    fn capacity(&self) -> usize {
+        self.pool_size
+    }
+}
+
+/// Text token of recognized segment with timing in centiseconds.
+struct SegmentToken {
+    bytes: Vec<u8>,
+    t0: i64,
+    t1: i64,
+    probability: f32,
+    logprob: f32,
+}
+
+fn is_tokens_required(params: &RecognizeParameters) -> bool {
+    params.is_word_timestamps_enable() || params.is_confidence_enable()
+}
+
+fn build_segment(
+    segment_id: c_int,
+    frame_start: i64,
+    frame_end: i64,
+    text: String,
+    tokens: Vec<SegmentToken>,
+    params: &RecognizeParameters,
+) -> RecognizeResponse {
+    let confidence = params
+        .is_confidence_enable()
+        .then(|| build_confidence(text.as_str(), &tokens));
+    let words = params
+        .is_word_timestamps_enable()
+        .then(|| group_words(tokens));
+
+    RecognizeResponse {
+        frame_id: segment_id,
+        frame_start,
+        frame_end,
+        text,
+        words,
+        confidence,
+        ..Default::default()
+    }
+}
+
+/// Detected language is reported only if it was not passed by client.
+fn detected_language(params: &RecognizeParameters, lang_id: c_int) -> Option<String> {
+    match params.is_auto_lang() {
+        true => whisper_rs::get_lang_str(lang_id).map(str::to_string),
+        false => None,
+    }
+}
+
+/// whisper.cpp suppresses no speech token before logits are exposed,
+/// so its probability is not reported.
+fn build_confidence(text: &str, tokens: &[SegmentToken]) -> SegmentConfidence {
+    let tokens_count = tokens.len().max(1) as f32;
+    SegmentConfidence {
+        avg_logprob: tokens.iter().map(|token| token.logprob).sum::<f32>() / tokens_count,
+        min_token_prob: tokens
+            .iter()
+            .map(|token| token.probability)
+            .reduce(f32::min)
+            .unwrap_or_default(),
+        no_speech_prob: None,
+        compression_ratio: SegmentConfidence::compression_ratio(text),
+    }
+}
+
+/// Joins tokens into words, a token starting with space begins a new word.
+/// Bytes are joined before decoding since a token may hold a part of UTF-8 char.
+fn group_words(tokens: Vec<SegmentToken>) -> Vec<RecognizedWord> {
+    let mut words = Vec::new();
+    let mut word_tokens = Vec::<SegmentToken>::new();
+    for token in tokens {
+        if token.bytes.first() == Some(&b' ') && !word_tokens.is_empty() {
+            words.extend(build_word(std::mem::take(&mut word_tokens)));
+        }
+        word_tokens.push(token);
+    }
+    words.extend(build_word(word_tokens));
+
+    words
+}
+
+fn build_word(tokens: Vec<SegmentToken>) -> Option<RecognizedWord> {
+    let (first, last) = (tokens.first()?, tokens.last()?);
+    let bytes = tokens.iter().flat_map(|token| token.bytes.iter().copied()).collect::<Vec<u8>>();
+    let word = String::from_utf8_lossy(&bytes).trim().to_string();
+    if word.is_empty() {
+        return None;
+    }
+
+    let probability = tokens.iter().map(|token| token.probability).sum::<f32>() / tokens.len() as f32;
+    Some(RecognizedWord {
+        word,
+        start: first.t0,
+        end: last.t1,
+        probability,
+    })
+}
+
+/// Passed to whisper callbacks as user data.
+struct CallbackData<'a> {
+    observer: Option<&'a dyn RecognizeObserver>,
+    params: &'a RecognizeParameters,
+    /// Whether speaker changes after segment, indexed by segment id.
+    speaker_turns: RefCell<Vec<bool>>,
+}
+
+unsafe extern "C" fn progress_trampoline(
+    _: *mut whisper_context,
+    _: *mut whisper_state,
+    progress: c_int,
+    user_data: *mut c_void,
+) {
+    let callback_data = &*(user_data as *const CallbackData);
+    if let Some(observer) = callback_data.observer {
+        observer.on_progress(progress);
+    }
+}
+
+/// Asks whisper to stop encoding and decoding once observer is cancelled.
+unsafe extern "C" fn abort_trampoline(user_data: *mut c_void) -> bool {
+    let callback_data = &*(user_data as *const CallbackData);
+    callback_data
+        .observer
+        .is_some_and(|observer| observer.is_cancelled())
+}
+
+/// Whisper passes the count of segments decoded since the previous call,
+/// they are read from the raw state which is borrowed by `full` meanwhile.
+unsafe extern "C" fn new_segment_trampoline(
+    ctx: *mut whisper_context,
+    state: *mut whisper_state,
+    n_new: c_int,
+    user_data: *mut c_void,
+) {
+    let callback_data = &*(user_data as *const CallbackData);
+    let params = callback_data.params;
+    let n_segments = whisper_rs_sys::whisper_full_n_segments_from_state(state);
+    for segment_id in (n_segments - n_new).max(0)..n_segments {
+        // Segments may be split by `max_segment_len` before the call, so
+        // turns of every new segment are final here.
+        let speaker_turn_next = whisper_rs_sys::whisper_full_get_segment_speaker_turn_next_from_state(state, segment_id);
+        if params.is_speaker_turns_enable() {
+            let mut speaker_turns = callback_data.speaker_turns.borrow_mut();
+            if speaker_turns.len() <= segment_id as usize {
+                speaker_turns.resize(segment_id as usize + 1, false);
+            }
+            speaker_turns[segment_id as usize] = speaker_turn_next;
+        }
+
+        let Some(observer) = callback_data.observer else {
+            continue;
+        };
+
+        let text = whisper_rs_sys::whisper_full_get_segment_text_from_state(state, segment_id);
+        if text.is_null() {
+            continue;
+        }
+
+        let tokens = match is_tokens_required(params) {
+            true => read_raw_tokens(ctx, state, segment_id),
+            false => Vec::new(),
+        };
+
+        let mut segment = build_segment(
+            segment_id,
+            whisper_rs_sys::whisper_full_get_segment_t0_from_state(state, segment_id),
+            whisper_rs_sys::whisper_full_get_segment_t1_from_state(state, segment_id),
+            CStr::from_ptr(text).to_string_lossy().to_string(),
+            tokens,
+            params,
+        );
+        let lang_id = whisper_rs_sys::whisper_full_lang_id_from_state(state);
+        segment.language = detected_language(params, lang_id);
+        segment.speaker_turn_next = params.is_speaker_turns_enable().then_some(speaker_turn_next);
+        observer.on_segment(&segment);
+    }
+}
+
+unsafe fn read_raw_tokens(ctx: *mut whisper_context, state: *mut whisper_state, segment_id: c_int) -> Vec<SegmentToken> {
+    let token_eot = whisper_rs_sys::whisper_token_eot(ctx);
+    let mut tokens = Vec::new();
+    for token_id in 0..whisper_rs_sys::whisper_full_n_tokens_from_state(state, segment_id) {
+        let data = whisper_rs_sys::whisper_full_get_token_data_from_state(state, segment_id, token_id);
+        let text = whisper_rs_sys::whisper_token_to_str(ctx, data.id);
+        if data.id >= token_eot || text.is_null() {
+            continue;
+        }
+
+        tokens.push(SegmentToken {
+            bytes: CStr::from_ptr(text).to_bytes().to_vec(),
+            t0: data.t0,
+            t1: data.t1,
+            probability: data.p,
+            logprob: data.plog,
+        });
+    }
+
+    tokens
+}
@@ -0,0 +1,28 @@
+use thiserror::Error;
+use whisper_rs::WhisperError;
+
+pub type RecognizeResult<T> = Result<T, RecognizeError>;
+
+#[derive(Debug, Error)]
+pub enum RecognizeError {
+    #[error("Failed while running whisper: {0}")]
+    Whisper(#[from] WhisperError),
+    #[error("Unsupported audio: {0}")]
+    UnsupportedAudio(String),
+    #[error("Failed while decoding audio: {0}")]
+    DecodeAudio(String),
+    #[error("Failed while converting audio with ffmpeg: {0}")]
+    ConvertAudio(String),
+    #[error("ffmpeg is not available: {0}")]
+    ConverterMissing(String),
+    #[error("Audio is too long: {0}")]
+    TooLong(String),
+    #[error("Unknown model: {0}")]
+    UnknownModel(String),
+    #[error("Engine is busy: {0}")]
+    EngineBusy(String),
+    #[error("Whisper state pool is exhausted: {0}")]
+    PoolExhausted(String),
+    #[error("Recognition task has been interrupted: {0}")]
+    Interrupted(String),
+}
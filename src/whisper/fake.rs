@@ -0,0 +1,149 @@
+use crate::whisper::audio::WHISPER_SAMPLE_RATE;
+use crate::whisper::engine::{RecognizeObserver, SpeechEngine};
+use crate::whisper::errors::{RecognizeError, RecognizeResult};
+use crate::whisper::forms::{LanguageProbability, RecognizeParameters, RecognizeResponse, RecognizedWord, SegmentConfidence};
+
+use std::time::Duration;
+
+const FAKE_LANGUAGE: &str = "en";
+
+/// In-process engine which answers with canned phrases instead of running a model.
+/// Phrases are spread evenly over the audio duration so timestamps stay plausible,
+/// which allows to exercise routes and websocket sessions without a model file.
+/// Speaker changes after every phrase if speaker turns are requested.
+pub struct FakeEngine {
+    phrases: Vec<String>,
+    phrase_delay: Duration,
+    capacity: usize,
+}
+
+impl FakeEngine {
+    pub fn new(phrases: Vec<String>) -> Self {
+        FakeEngine {
+            phrases,
+            phrase_delay: Duration::ZERO,
+            capacity: 1,
+        }
+    }
+
+    /// Spends the delay on every phrase like a slow model does, observed
+    /// recognition reports phrases one by one meanwhile.
+    pub fn with_delay(mut self, phrase_delay: Duration) -> Self {
+        self.phrase_delay = phrase_delay;
+        self
+    }
+
+    /// Allows recognitions to run in parallel like a pool of whisper states does.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    fn build_segments(&self, audio: &[f32], params: &RecognizeParameters) -> Vec<RecognizeResponse> {
+        if audio.is_empty() || self.phrases.is_empty() {
+            return Vec::default();
+        }
+
+        // Whisper reports timestamps in centiseconds.
+        let duration = (audio.len() * 100 / WHISPER_SAMPLE_RATE as usize) as i64;
+        let phrases_count = self.phrases.len() as i64;
+        self.phrases
+            .iter()
+            .enumerate()
+            .map(|(id, phrase)| {
+                let id = id as i64;
+                let frame_start = duration * id / phrases_count;
+                let frame_end = duration * (id + 1) / phrases_count;
+                let words = params
+                    .is_word_timestamps_enable()
+                    .then(|| split_words(phrase, frame_start, frame_end));
+                let confidence = params.is_confidence_enable().then(|| SegmentConfidence {
+                    avg_logprob: 0.0,
+                    min_token_prob: 1.0,
+                    no_speech_prob: None,
+                    compression_ratio: SegmentConfidence::compression_ratio(phrase),
+                });
+
+                RecognizeResponse {
+                    frame_id: id as i32,
+                    frame_start,
+                    frame_end,
+                    text: phrase.to_owned(),
+                    words,
+                    confidence,
+                    language: params.is_auto_lang().then(|| FAKE_LANGUAGE.to_string()),
+                    speaker_turn_next: params.is_speaker_turns_enable().then_some(id + 1 < phrases_count),
+                    ..Default::default()
+                }
+            })
+            .collect::<Vec<RecognizeResponse>>()
+    }
+}
+
+impl Default for FakeEngine {
+    fn default() -> Self {
+        let phrases = vec!["Hello".to_string(), "world".to_string()];
+        FakeEngine::new(phrases)
+    }
+}
+
+impl SpeechEngine for FakeEngine {
+    fn recognize(&self, audio: &[f32], params: &RecognizeParameters) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let collected_results = self.build_segments(audio, params);
+        std::thread::sleep(self.phrase_delay * collected_results.len() as u32);
+        Ok(collected_results)
+    }
+
+    fn recognize_observed(
+        &self,
+        audio: &[f32],
+        params: &RecognizeParameters,
+        observer: &dyn RecognizeObserver,
+    ) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let recognized = self.build_segments(audio, params);
+        let segments_count = recognized.len().max(1);
+        for (id, segment) in recognized.iter().enumerate() {
+            std::thread::sleep(self.phrase_delay);
+            if observer.is_cancelled() {
+                return Err(RecognizeError::Interrupted("recognition has been cancelled".to_string()));
+            }
+
+            observer.on_segment(segment);
+            observer.on_progress(((id + 1) * 100 / segments_count) as i32);
+        }
+
+        observer.on_progress(100);
+        Ok(recognized)
+    }
+
+    fn detect_language(&self, _audio: &[f32], _threads: i32) -> RecognizeResult<Vec<LanguageProbability>> {
+        let detected = LanguageProbability {
+            language: FAKE_LANGUAGE.to_string(),
+            probability: 1.0,
+        };
+        Ok(vec![detected])
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// Spreads phrase words evenly over the phrase duration.
+fn split_words(phrase: &str, start: i64, end: i64) -> Vec<RecognizedWord> {
+    let words = phrase.split_whitespace().collect::<Vec<&str>>();
+    let words_count = words.len() as i64;
+    words
+        .into_iter()
+        .enumerate()
+        .map(|(id, word)| {
+            let id = id as i64;
+            RecognizedWord {
+                word: word.to_string(),
+                start: start + (end - start) * id / words_count,
+                end: start + (end - start) * (id + 1) / words_count,
+                probability: 1.0,
+            }
+        })
+        .collect()
+}
@@ -1,15 +1,126 @@
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::Write;
+use std::thread;
 use utoipa::{IntoParams, ToSchema};
 
-#[derive(serde::Deserialize, IntoParams, ToSchema)]
-pub(crate) struct RecognizeParameters {
+const MAX_INITIAL_PROMPT_LENGTH: usize = 1024;
+const MAX_BEST_OF: i32 = 10;
+const MAX_BEAM_SIZE: i32 = 16;
+const MAX_LINE_CHARS: usize = 200;
+const MAX_LINES: usize = 10;
+const MAX_DETECT_DURATION_SECS: u32 = 30;
+const MAX_VAD_THRESHOLD_DB: f32 = 60.0;
+const MAX_VAD_SILENCE_MS: i32 = 10_000;
+const MAX_VAD_PAD_MS: i32 = 2_000;
+const MIN_CHUNK_SECS: i32 = 30;
+const MAX_CHUNK_SECS: i32 = 3_600;
+const MAX_CHUNK_OVERLAP_MS: i32 = 10_000;
+const MAX_SPEAKERS_COUNT: i32 = 32;
+
+/// Language value which lets whisper detect spoken language itself.
+pub const AUTO_LANGUAGE: &str = "auto";
+
+#[derive(Clone, Copy, Default, serde::Deserialize, serde::Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DecodingStrategy {
+    #[default]
+    Greedy,
+    BeamSearch,
+}
+
+/// How channels of multichannel audio are recognized.
+#[derive(Clone, Copy, Default, PartialEq, serde::Deserialize, serde::Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelMode {
+    /// Channels are mixed into mono
+    #[default]
+    Mix,
+    /// Every channel is recognized apart and segments are labeled with channel
+    Split,
+}
+
+/// Representation of recognized segments returned to client.
+#[derive(Clone, Copy, Default, serde::Deserialize, serde::Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseFormat {
+    #[default]
+    Json,
+    Srt,
+    Vtt,
+    /// WebVTT with inline word timing tags, requires word timestamps
+    VttKaraoke,
+}
+
+#[derive(Clone, serde::Deserialize, serde::Serialize, IntoParams, ToSchema)]
+#[into_params(parameter_in = Query)]
+#[serde(default)]
+pub struct RecognizeParameters {
+    /// Spoken language code like `en`, `de` or `auto` to detect it
     language: Option<String>,
+    /// Number of threads used by decoder
     use_threads: i32,
-    resample_rate: u32,
+    /// Translate recognized text to english
     enable_translate: bool,
     enable_print_special: bool,
     enable_print_progress: bool,
     enable_print_realtime: bool,
     enable_print_timestamps: bool,
+    /// Start offset of audio to recognize in milliseconds
+    offset_ms: i32,
+    /// Duration of audio to recognize in milliseconds, whole audio if zero
+    duration_ms: i32,
+    /// Text passed to decoder as previous context to guide style or vocabulary,
+    /// only its last tokens fitting a half of model text context are used
+    initial_prompt: Option<String>,
+    /// Sampling temperature from 0.0 to 1.0
+    temperature: f32,
+    /// Decoding strategy: `greedy` or `beam_search`, server default if missing
+    strategy: Option<DecodingStrategy>,
+    /// Number of candidates for greedy decoding, server default if missing
+    best_of: Option<i32>,
+    /// Number of beams for beam search decoding, server default if missing
+    beam_size: Option<i32>,
+    /// Beam search patience factor or -1 to disable, server default if missing
+    patience: Option<f32>,
+    /// Temperature increase to decode again with if thresholds fail, zero disables fallback
+    temperature_inc: Option<f32>,
+    /// Decode again with higher temperature if token entropy exceeds it
+    entropy_thold: Option<f32>,
+    /// Decode again with higher temperature if average log probability falls below it
+    logprob_thold: Option<f32>,
+    /// Report start, end and probability of every word in `words` array
+    enable_word_timestamps: bool,
+    /// Maximum segment length in characters, unlimited if zero
+    max_segment_len: i32,
+    /// Split segments exceeding `max_segment_len` on word boundary instead of token
+    enable_split_on_word: bool,
+    /// Report token probabilities and compression ratio of every segment in `confidence`
+    enable_confidence: bool,
+    /// Recognize only speech regions found by voice activity detection
+    enable_vad: bool,
+    /// Frame energy above noise floor in dB which is considered as speech
+    vad_threshold_db: f32,
+    /// Shorter pauses in milliseconds do not split speech regions
+    vad_min_silence_ms: i32,
+    /// Padding in milliseconds added around every speech region
+    vad_speech_pad_ms: i32,
+    /// Report speech region of every segment in `speech_region`, requires `enable_vad`
+    enable_speech_regions: bool,
+    /// Split long audio at silences into chunks which are recognized concurrently
+    enable_parallel: bool,
+    /// Approximate chunk duration in seconds for parallel recognition
+    chunk_secs: i32,
+    /// Audio in milliseconds shared by neighbour chunks to keep words at boundaries
+    chunk_overlap_ms: i32,
+    /// Channels mode: `mix` into mono or `split` to recognize every channel apart
+    channels: ChannelMode,
+    /// Comma separated names of channels like `agent,customer` for `split` mode
+    channel_names: Option<String>,
+    /// Detect speaker turns with tinydiarize model and label segments with `speaker`
+    enable_speaker_turns: bool,
+    /// Number of speakers whose labels alternate on turns, every turn gets a new label if zero
+    speakers_count: i32,
 }
 
 #[allow(dead_code)]
@@ -20,8 +131,9 @@ impl RecognizeParameters {
             Some(lang) => Some(lang.as_str())
         }
     }
-    pub fn get_resample_rate(&self) -> u32 {
-        self.resample_rate
+    /// Language is detected by whisper if it is missing or `auto`.
+    pub fn is_auto_lang(&self) -> bool {
+        self.get_lang().is_none_or(|lang| lang == AUTO_LANGUAGE)
     }
     pub fn get_threads(&self) -> i32 {
         self.use_threads
@@ -41,6 +153,202 @@ impl RecognizeParameters {
     pub fn is_print_timestamp_enable(&self) -> bool {
         self.enable_print_timestamps
     }
+    pub fn get_offset_ms(&self) -> i32 {
+        self.offset_ms
+    }
+    pub fn get_duration_ms(&self) -> i32 {
+        self.duration_ms
+    }
+    pub fn get_initial_prompt(&self) -> Option<&str> {
+        self.initial_prompt.as_deref()
+    }
+    pub fn get_temperature(&self) -> f32 {
+        self.temperature
+    }
+    pub fn get_strategy(&self) -> Option<DecodingStrategy> {
+        self.strategy
+    }
+    pub fn get_best_of(&self) -> Option<i32> {
+        self.best_of
+    }
+    pub fn get_beam_size(&self) -> Option<i32> {
+        self.beam_size
+    }
+    pub fn get_patience(&self) -> Option<f32> {
+        self.patience
+    }
+    pub fn get_temperature_inc(&self) -> Option<f32> {
+        self.temperature_inc
+    }
+    pub fn get_entropy_thold(&self) -> Option<f32> {
+        self.entropy_thold
+    }
+    pub fn get_logprob_thold(&self) -> Option<f32> {
+        self.logprob_thold
+    }
+    pub fn is_word_timestamps_enable(&self) -> bool {
+        self.enable_word_timestamps
+    }
+    pub fn get_max_segment_len(&self) -> i32 {
+        self.max_segment_len
+    }
+    /// whisper.cpp splits segments by `max_len` only when token timestamps are computed.
+    pub fn is_token_timestamps_enable(&self) -> bool {
+        self.enable_word_timestamps || self.max_segment_len > 0
+    }
+    pub fn is_split_on_word_enable(&self) -> bool {
+        self.enable_split_on_word
+    }
+    pub fn is_confidence_enable(&self) -> bool {
+        self.enable_confidence
+    }
+    pub fn is_vad_enable(&self) -> bool {
+        self.enable_vad
+    }
+    pub fn get_vad_threshold_db(&self) -> f32 {
+        self.vad_threshold_db
+    }
+    pub fn get_vad_min_silence_ms(&self) -> i32 {
+        self.vad_min_silence_ms
+    }
+    pub fn get_vad_speech_pad_ms(&self) -> i32 {
+        self.vad_speech_pad_ms
+    }
+    pub fn is_speech_regions_enable(&self) -> bool {
+        self.enable_speech_regions
+    }
+    pub fn is_parallel_enable(&self) -> bool {
+        self.enable_parallel
+    }
+    pub fn get_chunk_secs(&self) -> i32 {
+        self.chunk_secs
+    }
+    pub fn get_chunk_overlap_ms(&self) -> i32 {
+        self.chunk_overlap_ms
+    }
+    pub fn get_channels(&self) -> ChannelMode {
+        self.channels
+    }
+    pub fn is_split_channels_enable(&self) -> bool {
+        self.channels == ChannelMode::Split
+    }
+    /// Name passed for channel of the index, if any.
+    pub fn get_channel_name(&self, channel: usize) -> Option<&str> {
+        self.channel_names
+            .as_deref()
+            .and_then(|names| names.split(',').nth(channel))
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+    }
+    pub fn is_speaker_turns_enable(&self) -> bool {
+        self.enable_speaker_turns
+    }
+    pub fn get_speakers_count(&self) -> i32 {
+        self.speakers_count
+    }
+    pub fn set_lang(&mut self, language: Option<String>) {
+        self.language = language;
+    }
+    pub fn set_translate_enable(&mut self, enable_translate: bool) {
+        self.enable_translate = enable_translate;
+    }
+    pub fn set_initial_prompt(&mut self, initial_prompt: Option<String>) {
+        self.initial_prompt = initial_prompt;
+    }
+    pub fn set_temperature(&mut self, temperature: f32) {
+        self.temperature = temperature;
+    }
+    pub fn set_word_timestamps_enable(&mut self, enable_word_timestamps: bool) {
+        self.enable_word_timestamps = enable_word_timestamps;
+    }
+    pub fn set_confidence_enable(&mut self, enable_confidence: bool) {
+        self.enable_confidence = enable_confidence;
+    }
+    pub fn set_offset_ms(&mut self, offset_ms: i32) {
+        self.offset_ms = offset_ms;
+    }
+    pub fn set_duration_ms(&mut self, duration_ms: i32) {
+        self.duration_ms = duration_ms;
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(lang) = self.get_lang().filter(|lang| *lang != AUTO_LANGUAGE) {
+            if whisper_rs::get_lang_id(lang).is_none() {
+                return Err(format!("unsupported language: {}", lang));
+            }
+        }
+
+        let max_threads = thread::available_parallelism()
+            .map(|threads| threads.get() as i32)
+            .unwrap_or(1);
+        if !(1..=max_threads).contains(&self.use_threads) {
+            return Err(format!("use_threads must be between 1 and {}", max_threads));
+        }
+
+        if self.offset_ms < 0 || self.duration_ms < 0 {
+            return Err("offset_ms and duration_ms must not be negative".to_string());
+        }
+
+        if !(0.0..=1.0).contains(&self.temperature) {
+            return Err("temperature must be between 0.0 and 1.0".to_string());
+        }
+
+        validate_decoding(
+            self.best_of,
+            self.beam_size,
+            self.patience,
+            self.temperature_inc,
+            self.entropy_thold,
+            self.logprob_thold,
+        )?;
+
+        if self.max_segment_len < 0 {
+            return Err("max_segment_len must not be negative".to_string());
+        }
+
+        if !(0.0..=MAX_VAD_THRESHOLD_DB).contains(&self.vad_threshold_db) {
+            return Err(format!("vad_threshold_db must be between 0.0 and {}", MAX_VAD_THRESHOLD_DB));
+        }
+
+        if !(0..=MAX_VAD_SILENCE_MS).contains(&self.vad_min_silence_ms) {
+            return Err(format!("vad_min_silence_ms must be between 0 and {}", MAX_VAD_SILENCE_MS));
+        }
+
+        if !(0..=MAX_VAD_PAD_MS).contains(&self.vad_speech_pad_ms) {
+            return Err(format!("vad_speech_pad_ms must be between 0 and {}", MAX_VAD_PAD_MS));
+        }
+
+        if self.enable_speech_regions && !self.enable_vad {
+            return Err("enable_speech_regions requires enable_vad".to_string());
+        }
+
+        if !(MIN_CHUNK_SECS..=MAX_CHUNK_SECS).contains(&self.chunk_secs) {
+            return Err(format!("chunk_secs must be between {} and {}", MIN_CHUNK_SECS, MAX_CHUNK_SECS));
+        }
+
+        if !(0..=MAX_CHUNK_OVERLAP_MS).contains(&self.chunk_overlap_ms) {
+            return Err(format!("chunk_overlap_ms must be between 0 and {}", MAX_CHUNK_OVERLAP_MS));
+        }
+
+        if self.channel_names.is_some() && !self.is_split_channels_enable() {
+            return Err("channel_names requires split channels mode".to_string());
+        }
+
+        if !(0..=MAX_SPEAKERS_COUNT).contains(&self.speakers_count) {
+            return Err(format!("speakers_count must be between 0 and {}", MAX_SPEAKERS_COUNT));
+        }
+
+        if self.enable_speaker_turns && self.is_split_channels_enable() {
+            return Err("enable_speaker_turns requires mix channels mode".to_string());
+        }
+
+        let prompt_length = self.initial_prompt.as_ref().map_or(0, |prompt| prompt.chars().count());
+        if prompt_length > MAX_INITIAL_PROMPT_LENGTH {
+            return Err(format!("initial_prompt must not exceed {} characters", MAX_INITIAL_PROMPT_LENGTH));
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for RecognizeParameters {
@@ -48,27 +356,356 @@ impl Default for RecognizeParameters {
         RecognizeParameters {
             language: Some("en".to_string()),
             use_threads: 1,
-            resample_rate: 29000,
             enable_translate: false,
             enable_print_special: false,
             enable_print_progress: false,
             enable_print_realtime: false,
             enable_print_timestamps: false,
+            offset_ms: 0,
+            duration_ms: 0,
+            initial_prompt: None,
+            temperature: 0.0,
+            strategy: None,
+            best_of: None,
+            beam_size: None,
+            patience: None,
+            temperature_inc: None,
+            entropy_thold: None,
+            logprob_thold: None,
+            enable_word_timestamps: false,
+            max_segment_len: 0,
+            enable_split_on_word: false,
+            enable_confidence: false,
+            enable_vad: false,
+            vad_threshold_db: 12.0,
+            vad_min_silence_ms: 500,
+            vad_speech_pad_ms: 200,
+            enable_speech_regions: false,
+            enable_parallel: false,
+            chunk_secs: 300,
+            chunk_overlap_ms: 2_000,
+            channels: ChannelMode::Mix,
+            channel_names: None,
+            enable_speaker_turns: false,
+            speakers_count: 2,
+        }
+    }
+}
+
+/// Checks decoding values passed by request or server config, missing ones are skipped.
+pub(crate) fn validate_decoding(
+    best_of: Option<i32>,
+    beam_size: Option<i32>,
+    patience: Option<f32>,
+    temperature_inc: Option<f32>,
+    entropy_thold: Option<f32>,
+    logprob_thold: Option<f32>,
+) -> Result<(), String> {
+    if best_of.is_some_and(|best_of| !(1..=MAX_BEST_OF).contains(&best_of)) {
+        return Err(format!("best_of must be between 1 and {}", MAX_BEST_OF));
+    }
+
+    if beam_size.is_some_and(|beam_size| !(1..=MAX_BEAM_SIZE).contains(&beam_size)) {
+        return Err(format!("beam_size must be between 1 and {}", MAX_BEAM_SIZE));
+    }
+
+    if patience.is_some_and(|patience| patience != -1.0 && patience <= 0.0) {
+        return Err("patience must be positive or -1 to disable".to_string());
+    }
+
+    if temperature_inc.is_some_and(|temperature_inc| !(0.0..=1.0).contains(&temperature_inc)) {
+        return Err("temperature_inc must be between 0.0 and 1.0".to_string());
+    }
+
+    if entropy_thold.is_some_and(|entropy_thold| entropy_thold <= 0.0) {
+        return Err("entropy_thold must be positive".to_string());
+    }
+
+    if logprob_thold.is_some_and(|logprob_thold| logprob_thold > 0.0) {
+        return Err("logprob_thold must not be positive".to_string());
+    }
+
+    Ok(())
+}
+
+#[derive(Clone, serde::Deserialize, IntoParams, ToSchema)]
+#[into_params(parameter_in = Query)]
+#[serde(default)]
+pub struct SubtitleParameters {
+    /// Split and merge recognized segments into subtitle cues
+    enable_reflow: bool,
+    /// Maximum characters per subtitle line
+    max_line_chars: usize,
+    /// Maximum lines per subtitle cue
+    max_lines: usize,
+    /// Minimum cue duration in milliseconds
+    min_duration_ms: i64,
+    /// Maximum cue duration in milliseconds
+    max_duration_ms: i64,
+    /// Reading speed in characters per second used to extend short cues
+    max_chars_per_second: f32,
+}
+
+impl SubtitleParameters {
+    pub fn is_reflow_enable(&self) -> bool {
+        self.enable_reflow
+    }
+    pub fn get_max_line_chars(&self) -> usize {
+        self.max_line_chars
+    }
+    pub fn get_max_lines(&self) -> usize {
+        self.max_lines
+    }
+    pub fn get_min_duration_ms(&self) -> i64 {
+        self.min_duration_ms
+    }
+    pub fn get_max_duration_ms(&self) -> i64 {
+        self.max_duration_ms
+    }
+    pub fn get_max_chars_per_second(&self) -> f32 {
+        self.max_chars_per_second
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_LINE_CHARS).contains(&self.max_line_chars) {
+            return Err(format!("max_line_chars must be between 1 and {}", MAX_LINE_CHARS));
+        }
+
+        if !(1..=MAX_LINES).contains(&self.max_lines) {
+            return Err(format!("max_lines must be between 1 and {}", MAX_LINES));
         }
+
+        if self.min_duration_ms < 0 || self.max_duration_ms <= self.min_duration_ms {
+            return Err("max_duration_ms must be greater than non-negative min_duration_ms".to_string());
+        }
+
+        if self.max_chars_per_second <= 0.0 {
+            return Err("max_chars_per_second must be positive".to_string());
+        }
+
+        Ok(())
     }
 }
 
-#[derive(Default, serde::Serialize, ToSchema)]
-pub(crate) struct RecognizeResponse {
+impl Default for SubtitleParameters {
+    fn default() -> Self {
+        SubtitleParameters {
+            enable_reflow: false,
+            max_line_chars: 42,
+            max_lines: 2,
+            min_duration_ms: 1000,
+            max_duration_ms: 7000,
+            max_chars_per_second: 17.0,
+        }
+    }
+}
+
+#[derive(Clone, serde::Deserialize, IntoParams, ToSchema)]
+#[into_params(parameter_in = Query)]
+#[serde(default)]
+pub struct DetectLanguageParameters {
+    /// Name of loaded model to detect with, server default if missing
+    model: Option<String>,
+    /// Number of the most probable languages to return
+    top_k: usize,
+    /// Seconds from the beginning of audio to detect language within
+    duration_secs: u32,
+    /// Threads count to compute spectrogram and run encoder
+    use_threads: i32,
+}
+
+impl DetectLanguageParameters {
+    pub fn get_model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+    pub fn get_top_k(&self) -> usize {
+        self.top_k
+    }
+    pub fn get_duration_secs(&self) -> u32 {
+        self.duration_secs
+    }
+    pub fn get_threads(&self) -> i32 {
+        self.use_threads
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let max_languages = whisper_rs::get_lang_max_id() as usize + 1;
+        if !(1..=max_languages).contains(&self.top_k) {
+            return Err(format!("top_k must be between 1 and {}", max_languages));
+        }
+
+        // Whisper encoder looks at 30 seconds window only.
+        if !(1..=MAX_DETECT_DURATION_SECS).contains(&self.duration_secs) {
+            return Err(format!("duration_secs must be between 1 and {}", MAX_DETECT_DURATION_SECS));
+        }
+
+        let max_threads = thread::available_parallelism()
+            .map(|threads| threads.get() as i32)
+            .unwrap_or(1);
+        if !(1..=max_threads).contains(&self.use_threads) {
+            return Err(format!("use_threads must be between 1 and {}", max_threads));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for DetectLanguageParameters {
+    fn default() -> Self {
+        DetectLanguageParameters {
+            model: None,
+            top_k: 5,
+            duration_secs: MAX_DETECT_DURATION_SECS,
+            use_threads: 1,
+        }
+    }
+}
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize, ToSchema)]
+pub struct LanguageProbability {
+    /// Language code like `en`, `de`
+    pub language: String,
+    pub probability: f32,
+}
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize, ToSchema)]
+pub struct DetectLanguageResponse {
+    /// The most probable language
+    pub language: String,
+    /// The most probable languages in descending order of probability
+    pub languages: Vec<LanguageProbability>,
+    pub model: String,
+}
+
+#[derive(Default, serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RecognizeQuery {
+    /// Concatenate chunked text to common
+    #[serde(default)]
+    concatenate: bool,
+    /// Name of loaded model to recognize with, server default if missing
+    model: Option<String>,
+    /// Response format: `json` segments, `srt`, `vtt` or `vtt_karaoke` subtitles
+    #[serde(default)]
+    format: ResponseFormat,
+    /// Stream json segments as soon as they are decoded: NDJSON lines or
+    /// server-sent events if `Accept: text/event-stream` is passed
+    #[serde(default)]
+    stream: bool,
+}
+
+impl RecognizeQuery {
+    pub fn is_concatenate_enable(&self) -> bool {
+        self.concatenate
+    }
+    pub fn get_model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+    pub fn get_format(&self) -> ResponseFormat {
+        self.format
+    }
+    pub fn is_stream_enable(&self) -> bool {
+        self.stream
+    }
+}
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize, ToSchema)]
+pub struct RecognizeResponse {
     pub frame_id: i32,
     pub frame_start: i64,
     pub frame_end: i64,
     pub text: String,
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub words: Option<Vec<RecognizedWord>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<SegmentConfidence>,
+    /// Spoken language detected by whisper if `language=auto` is passed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Speech region which segment is recognized within if `enable_speech_regions` is passed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speech_region: Option<SpeechRegion>,
+    /// Index of channel which segment is recognized from in `split` channels mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<usize>,
+    /// Name of the channel if it is passed in `channel_names`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_name: Option<String>,
+    /// Speaker of the segment counted from zero if `enable_speaker_turns` is passed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<i32>,
+    /// Speaker changes after the segment, reported if `enable_speaker_turns` is passed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker_turn_next: Option<bool>,
+}
+
+/// Region of audio detected as speech, timestamps are in centiseconds like segment ones.
+#[derive(Clone, Copy, Default, serde::Deserialize, serde::Serialize, ToSchema)]
+pub struct SpeechRegion {
+    pub start: i64,
+    pub end: i64,
+}
+
+/// Scores to find passages which need review, low token probabilities and
+/// high compression ratio (repeated text) point to unreliable segments.
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize, ToSchema)]
+pub struct SegmentConfidence {
+    /// Average log probability of text tokens
+    pub avg_logprob: f32,
+    /// Lowest probability of text token
+    pub min_token_prob: f32,
+    /// Probability that segment holds no speech, reported only by engines which expose it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_speech_prob: Option<f32>,
+    /// Ratio of text length to its zlib compressed length like OpenAI reports it
+    pub compression_ratio: f32,
+}
+
+impl SegmentConfidence {
+    pub fn compression_ratio(text: &str) -> f32 {
+        if text.is_empty() {
+            return 0.0;
+        }
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        let compressed = encoder
+            .write_all(text.as_bytes())
+            .and_then(|_| encoder.finish())
+            .map_or(0, |compressed| compressed.len());
+
+        match compressed {
+            0 => 0.0,
+            _ => text.len() as f32 / compressed as f32,
+        }
+    }
+}
+
+/// Word of recognized segment, timestamps are in centiseconds like segment ones.
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize, ToSchema)]
+pub struct RecognizedWord {
+    pub word: String,
+    pub start: i64,
+    pub end: i64,
+    pub probability: f32,
 }
 
 impl From<Vec<RecognizeResponse>> for RecognizeResponse {
     fn from(value: Vec<RecognizeResponse>) -> Self {
         let mut common_response = RecognizeResponse::default();
+        if let Some(first) = value.first() {
+            common_response.model = first.model.to_owned();
+            common_response.language = first.language.to_owned();
+        }
+
+        let common_words = value
+            .iter()
+            .filter_map(|rec| rec.words.clone())
+            .reduce(|mut common_words, words| {
+                common_words.extend(words);
+                common_words
+            });
+
         let common_text = value
             .into_iter()
             .map(|rec| rec.text.to_owned())
@@ -76,6 +713,7 @@ impl From<Vec<RecognizeResponse>> for RecognizeResponse {
             .join(" ");
 
         common_response.text = common_text;
+        common_response.words = common_words;
         common_response
     }
 }
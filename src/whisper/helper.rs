@@ -1,60 +1,253 @@
-use actix_multipart::Multipart;
-use actix_web::web;
-use futures_util::{StreamExt, TryStreamExt};
+use crate::errors::WebError;
+use crate::uploads::config::UploadsConfig;
+use crate::uploads::file::TempFile;
+use crate::uploads::media::{MediaType, MEDIA_HEADER_LEN};
+use crate::whisper::errors::RecognizeResult;
+use crate::whisper::forms::{RecognizeResponse, ResponseFormat};
+use crate::whisper::subtitles;
+
+use actix_multipart::{Field, Multipart};
+use actix_web::http::{header, StatusCode};
+use actix_web::{web, HttpRequest, HttpResponse};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::fmt::{Debug, Display};
-use std::fs::File;
-use std::io::Write;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+const MAX_TEXT_FIELD_SIZE: usize = 64 * 1024;
+
+pub(crate) const EVENT_STREAM_CONTENT_TYPE: &str = "text/event-stream";
+pub(crate) const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Framing of streamed responses, server-sent events are chosen by `Accept` header.
+#[derive(Clone, Copy)]
+pub(crate) enum StreamFormat {
+    Ndjson,
+    EventStream,
+}
+
+impl StreamFormat {
+    pub(crate) fn from_request(req: &HttpRequest) -> Self {
+        let accepts_events = req
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.contains(EVENT_STREAM_CONTENT_TYPE));
+
+        match accepts_events {
+            true => StreamFormat::EventStream,
+            false => StreamFormat::Ndjson,
+        }
+    }
+
+    pub(crate) fn content_type(&self) -> &'static str {
+        match self {
+            StreamFormat::Ndjson => NDJSON_CONTENT_TYPE,
+            StreamFormat::EventStream => EVENT_STREAM_CONTENT_TYPE,
+        }
+    }
+
+    /// Frames serialized value as a line or as an event with given name.
+    pub(crate) fn frame<T: Serialize>(&self, event: &str, value: &T) -> Result<web::Bytes, WebError> {
+        let data = serde_json::to_string(value)?;
+        let frame = match self {
+            StreamFormat::Ndjson => format!("{}\n", data),
+            StreamFormat::EventStream => format!("event: {}\ndata: {}\n\n", event, data),
+        };
 
+        Ok(web::Bytes::from(frame))
+    }
+}
+
+pub(crate) struct MultiformData {
+    /// Uploaded file which is removed once the form is dropped.
+    pub file: TempFile,
+    pub fields: HashMap<String, String>,
+}
+
+/// Stores the uploaded file under unique name within upload dir and collects
+/// the rest of text fields of multipart form.
 pub(crate) async fn extract_multiform_data(
     mut payload: Multipart,
-) -> Result<String, anyhow::Error> {
+    uploads: &UploadsConfig,
+) -> Result<MultiformData, WebError> {
+    let mut file = None;
+    let mut fields = HashMap::new();
     while let Some(mut field) = payload
         .try_next()
         .await
-        .map_err(|err| extract_error(err, "Failed while processing stream"))?
+        .map_err(|err| WebError::InvalidMultipart(extract_error(err, "processing stream")))?
     {
         let content_type = field.content_disposition();
-        let filename = content_type.get_filename().unwrap();
-        let filepath = format!("./upload/{}", filename);
-        let filepath_cln = filepath.clone();
+        let field_name = content_type.get_name().unwrap_or_default().to_string();
+        let filename = content_type.get_filename().map(str::to_string);
+        match filename {
+            None => {
+                // Repeated fields like `timestamp_granularities[]` are joined by comma.
+                let value = read_text_field(&mut field).await?;
+                fields
+                    .entry(field_name)
+                    .and_modify(|values: &mut String| {
+                        values.push(',');
+                        values.push_str(value.as_str());
+                    })
+                    .or_insert(value);
+            }
+            Some(_) if file.is_some() => {
+                let msg = "Failed while extracting multiform: only one file expected";
+                return Err(WebError::InvalidMultipart(msg.to_string()));
+            }
+            Some(filename) => {
+                let uploaded = TempFile::unique(uploads.get_upload_dir(), Some(filename.as_str()));
+                write_file_field(&mut field, uploaded.path(), uploads).await?;
+                file = Some(uploaded);
+            }
+        }
+    }
+
+    match file {
+        Some(file) => Ok(MultiformData { file, fields }),
+        None => {
+            let msg = "Failed while extracting multiform: file field expected".to_string();
+            Err(WebError::InvalidMultipart(msg))
+        }
+    }
+}
 
-        let create_file_result = web::block(|| File::create(filepath)).await.unwrap();
-        if create_file_result.is_err() {
-            let err = create_file_result.err().unwrap();
-            return Err(extract_error(err, "creating tmp file"));
+/// Deserializes parameters from query string where multipart text fields override query ones.
+pub(crate) fn merge_parameters<T: DeserializeOwned>(
+    query: &str,
+    fields: &HashMap<String, String>,
+) -> Result<T, serde_urlencoded::de::Error> {
+    let mut pairs = serde_urlencoded::from_str::<Vec<(String, String)>>(query)?;
+    pairs.retain(|(key, _)| !fields.contains_key(key));
+    pairs.extend(fields.iter().map(|(key, value)| (key.to_owned(), value.to_owned())));
+
+    let merged_query = serde_urlencoded::to_string(pairs)
+        .map_err(|err| serde::de::Error::custom(err.to_string()))?;
+    serde_urlencoded::from_str::<T>(merged_query.as_str())
+}
+
+/// Builds response with recognized segments rendered in requested format.
+pub(crate) fn build_response(
+    format: ResponseFormat,
+    concatenate: bool,
+    segments: Vec<RecognizeResponse>,
+) -> HttpResponse {
+    let mut response = HttpResponse::build(StatusCode::OK);
+    match format {
+        ResponseFormat::Srt => response
+            .content_type(subtitles::SRT_CONTENT_TYPE)
+            .body(subtitles::to_srt(&segments)),
+        ResponseFormat::Vtt => response
+            .content_type(subtitles::VTT_CONTENT_TYPE)
+            .body(subtitles::to_vtt(&segments)),
+        ResponseFormat::VttKaraoke => response
+            .content_type(subtitles::VTT_CONTENT_TYPE)
+            .body(subtitles::to_vtt_karaoke(&segments)),
+        ResponseFormat::Json if concatenate => response.json(RecognizeResponse::from(segments)),
+        ResponseFormat::Json => response.json(segments),
+    }
+}
+
+/// Builds response which flushes every segment as soon as it is received.
+/// Recognition error ends the stream with `error` frame holding `ErrorResponse`.
+pub(crate) fn build_stream_response(
+    format: StreamFormat,
+    receiver: mpsc::UnboundedReceiver<RecognizeResult<RecognizeResponse>>,
+) -> HttpResponse {
+    let frames = stream::unfold(receiver, move |mut receiver| async move {
+        let frame = match receiver.recv().await? {
+            Ok(segment) => format.frame("segment", &segment),
+            Err(err) => format.frame("error", &WebError::from(err).to_response()),
+        };
+
+        Some((frame, receiver))
+    });
+
+    HttpResponse::build(StatusCode::OK)
+        .content_type(format.content_type())
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(frames)
+}
+
+/// Writes file field while checking its size and container, so rejected
+/// uploads are not read to the end.
+async fn write_file_field(field: &mut Field, filepath: &str, uploads: &UploadsConfig) -> Result<(), WebError> {
+    let mut file = tokio::fs::File::create(filepath)
+        .await
+        .map_err(|err| WebError::InternalError(extract_error(err, "creating tmp file")))?;
+
+    let max_bytes = uploads.get_max_bytes();
+    let mut written = 0_u64;
+    let mut header = Vec::with_capacity(MEDIA_HEADER_LEN);
+    while let Some(read_chunk_result) = field.next().await {
+        let data = read_chunk_result
+            .map_err(|err| WebError::InvalidMultipart(extract_error(err, "extracting chunk")))?;
+
+        written += data.len() as u64;
+        if max_bytes > 0 && written > max_bytes {
+            let msg = format!("Failed while extracting file: exceeds {} bytes", max_bytes);
+            return Err(WebError::PayloadTooLarge(msg));
         }
 
-        let mut file = create_file_result.unwrap();
-        while let Some(read_chunk_result) = field.next().await {
-            if read_chunk_result.is_err() {
-                let err = read_chunk_result.err().unwrap();
-                return Err(extract_error(err, "extracting chunk"));
+        if header.len() < MEDIA_HEADER_LEN {
+            let header_rest = (MEDIA_HEADER_LEN - header.len()).min(data.len());
+            header.extend_from_slice(&data[..header_rest]);
+            if header.len() == MEDIA_HEADER_LEN {
+                check_media_type(&header, uploads)?;
             }
+        }
 
-            let data = read_chunk_result.unwrap();
-            let file_res = web::block(move || file.write_all(&data).map(|_| file))
-                .await
-                .unwrap();
+        file.write_all(&data)
+            .await
+            .map_err(|err| WebError::InternalError(extract_error(err, "writing chunk")))?;
+    }
 
-            if file_res.is_err() {
-                let err = file_res.err().unwrap();
-                let msg = format!("Failed while extracting chunk: {}", err);
-                log::error!("{}", msg);
-                return Err(anyhow::Error::msg(msg));
-            }
+    // Files shorter than signature are checked once they are read.
+    if header.len() < MEDIA_HEADER_LEN {
+        check_media_type(&header, uploads)?;
+    }
 
-            file = file_res.unwrap()
+    file.flush()
+        .await
+        .map_err(|err| WebError::InternalError(extract_error(err, "writing chunk")))
+}
+
+fn check_media_type(header: &[u8], uploads: &UploadsConfig) -> Result<(), WebError> {
+    match MediaType::sniff(header) {
+        Some(media_type) if uploads.is_type_allowed(media_type) => Ok(()),
+        Some(media_type) => {
+            let msg = format!("{} files are not allowed", media_type.as_str());
+            Err(WebError::UnsupportedMedia(msg))
         }
+        // ffprobe knows more containers than sniffing does, so it decides on them.
+        None if uploads.is_unknown_allowed() => Ok(()),
+        None => Err(WebError::UnsupportedMedia("unknown audio container".to_string())),
+    }
+}
 
-        return Ok(filepath_cln);
+async fn read_text_field(field: &mut Field) -> Result<String, WebError> {
+    let mut value = Vec::new();
+    while let Some(read_chunk_result) = field.next().await {
+        let data = read_chunk_result
+            .map_err(|err| WebError::InvalidMultipart(extract_error(err, "extracting field")))?;
+        if value.len() + data.len() > MAX_TEXT_FIELD_SIZE {
+            let msg = format!("Failed while extracting field: exceeds {} bytes", MAX_TEXT_FIELD_SIZE);
+            return Err(WebError::PayloadTooLarge(msg));
+        }
+        value.extend_from_slice(&data);
     }
 
-    let msg = "Failed while extracting multiform".to_string();
-    Err(anyhow::Error::msg(msg))
+    String::from_utf8(value)
+        .map_err(|err| WebError::InvalidMultipart(extract_error(err, "decoding field")))
 }
 
-fn extract_error<T: Debug + Display>(err: T, msg: &str) -> anyhow::Error {
+fn extract_error<T: Debug + Display>(err: T, msg: &str) -> String {
     let msg = format!("Failed while {}: {}", msg, err);
     log::error!("{}", msg);
-    return anyhow::Error::msg(msg);
+    msg
 }
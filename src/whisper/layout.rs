@@ -0,0 +1,199 @@
+use crate::whisper::forms::{RecognizeResponse, RecognizedWord, SubtitleParameters};
+
+/// Pause between words in centiseconds which always starts a new cue.
+const MAX_PAUSE_IN_CUE: i64 = 150;
+
+struct TimedWord {
+    start: i64,
+    end: i64,
+    text: String,
+    /// Word reported by whisper, missing if timing is interpolated.
+    recognized: Option<RecognizedWord>,
+    channel: Option<usize>,
+    channel_name: Option<String>,
+    speaker: Option<i32>,
+}
+
+struct Cue {
+    start: i64,
+    end: i64,
+    lines: Vec<String>,
+    words: Vec<Option<RecognizedWord>>,
+    channel: Option<usize>,
+    channel_name: Option<String>,
+    speaker: Option<i32>,
+}
+
+impl Cue {
+    fn new(word: TimedWord) -> Self {
+        Cue {
+            start: word.start,
+            end: word.end,
+            lines: vec![word.text],
+            words: vec![word.recognized],
+            channel: word.channel,
+            channel_name: word.channel_name,
+            speaker: word.speaker,
+        }
+    }
+
+    fn chars_count(&self) -> usize {
+        self.lines.iter().map(|line| line.chars().count()).sum::<usize>() + self.lines.len() - 1
+    }
+
+    fn fits_last_line(&self, word: &TimedWord, params: &SubtitleParameters) -> bool {
+        let last_line_chars = self.lines.last().map_or(0, |line| line.chars().count());
+        last_line_chars + 1 + word.text.chars().count() <= params.get_max_line_chars()
+    }
+
+    fn accepts(&self, word: &TimedWord, params: &SubtitleParameters) -> bool {
+        // Words of different channels or speakers never share a cue.
+        if word.channel != self.channel || word.speaker != self.speaker {
+            return false;
+        }
+
+        if word.start - self.end > MAX_PAUSE_IN_CUE {
+            return false;
+        }
+
+        if word.end - self.start > params.get_max_duration_ms() / 10 {
+            return false;
+        }
+
+        // Prefer to start a new cue with a new sentence once cue lasts long enough.
+        let ends_sentence = self
+            .lines
+            .last()
+            .is_some_and(|line| line.ends_with(['.', '!', '?']));
+        if ends_sentence && self.end - self.start >= params.get_min_duration_ms() / 10 {
+            return false;
+        }
+
+        self.fits_last_line(word, params) || self.lines.len() < params.get_max_lines()
+    }
+
+    fn push(&mut self, word: TimedWord, params: &SubtitleParameters) {
+        self.end = word.end;
+        let fits_last_line = self.fits_last_line(&word, params);
+        self.words.push(word.recognized);
+        match self.lines.last_mut() {
+            Some(line) if fits_last_line => {
+                line.push(' ');
+                line.push_str(word.text.as_str());
+            }
+            _ => self.lines.push(word.text),
+        }
+    }
+}
+
+/// Splits and merges recognized segments into subtitle cues which respect
+/// line length, lines count, cue duration and reading speed constraints.
+/// Word timings reported by whisper are kept, otherwise they are interpolated
+/// within segment by characters count.
+pub fn reflow(segments: Vec<RecognizeResponse>, params: &SubtitleParameters) -> Vec<RecognizeResponse> {
+    let model = segments
+        .first()
+        .map(|segment| segment.model.to_owned())
+        .unwrap_or_default();
+    let language = segments.first().and_then(|segment| segment.language.to_owned());
+
+    let mut cues = Vec::<Cue>::new();
+    for word in split_words(&segments) {
+        match cues.last_mut() {
+            Some(cue) if cue.accepts(&word, params) => cue.push(word, params),
+            _ => cues.push(Cue::new(word)),
+        }
+    }
+
+    extend_durations(&mut cues, params);
+
+    let next_speakers = cues
+        .iter()
+        .skip(1)
+        .map(|cue| cue.speaker)
+        .chain(std::iter::once(None))
+        .collect::<Vec<Option<i32>>>();
+
+    cues.into_iter()
+        .zip(next_speakers)
+        .enumerate()
+        .map(|(index, (cue, next_speaker))| RecognizeResponse {
+            frame_id: index as i32,
+            frame_start: cue.start,
+            frame_end: cue.end,
+            text: cue.lines.join("\n"),
+            model: model.to_owned(),
+            words: cue.words.into_iter().collect(),
+            // Cues mix words of several segments, so segment confidence does not apply.
+            confidence: None,
+            language: language.to_owned(),
+            speech_region: None,
+            channel: cue.channel,
+            channel_name: cue.channel_name,
+            speaker_turn_next: cue.speaker.map(|speaker| next_speaker.is_some_and(|next| next != speaker)),
+            speaker: cue.speaker,
+        })
+        .collect()
+}
+
+fn split_words(segments: &[RecognizeResponse]) -> Vec<TimedWord> {
+    let mut timed_words = Vec::new();
+    for segment in segments {
+        if let Some(words) = segment.words.as_ref() {
+            timed_words.extend(words.iter().map(|word| TimedWord {
+                start: word.start,
+                end: word.end,
+                text: word.word.to_owned(),
+                recognized: Some(word.clone()),
+                channel: segment.channel,
+                channel_name: segment.channel_name.to_owned(),
+                speaker: segment.speaker,
+            }));
+            continue;
+        }
+
+        let words = segment.text.split_whitespace().collect::<Vec<&str>>();
+        let total_chars = words.iter().map(|word| word.chars().count() + 1).sum::<usize>() as i64;
+        let duration = (segment.frame_end - segment.frame_start).max(0);
+
+        let mut passed_chars = 0;
+        for word in words {
+            let word_chars = word.chars().count() as i64 + 1;
+            timed_words.push(TimedWord {
+                start: segment.frame_start + duration * passed_chars / total_chars,
+                end: segment.frame_start + duration * (passed_chars + word_chars) / total_chars,
+                text: word.to_string(),
+                recognized: None,
+                channel: segment.channel,
+                channel_name: segment.channel_name.to_owned(),
+                speaker: segment.speaker,
+            });
+            passed_chars += word_chars;
+        }
+    }
+
+    timed_words
+}
+
+/// Extends cues shorter than minimal duration or reading time up to the next cue.
+fn extend_durations(cues: &mut [Cue], params: &SubtitleParameters) {
+    let next_starts = cues
+        .iter()
+        .skip(1)
+        .map(|cue| Some(cue.start))
+        .chain(std::iter::once(None))
+        .collect::<Vec<Option<i64>>>();
+
+    for (cue, next_start) in cues.iter_mut().zip(next_starts) {
+        let reading_time = (cue.chars_count() as f32 / params.get_max_chars_per_second() * 100.0).ceil() as i64;
+        let required = reading_time
+            .max(params.get_min_duration_ms() / 10)
+            .min(params.get_max_duration_ms() / 10);
+
+        let mut end = cue.end.max(cue.start + required);
+        if let Some(next_start) = next_start {
+            end = end.min(next_start.max(cue.end));
+        }
+        cue.end = end;
+    }
+}
@@ -1,16 +1,31 @@
 use actix_web::{Scope, web};
 
+pub mod audio;
+pub(crate) mod chunking;
 pub mod client;
 pub mod client_async;
 pub mod config;
+#[cfg(feature = "enable-native-decoding")]
+pub(crate) mod decoder;
+pub mod engine;
+pub mod errors;
+pub mod fake;
 pub mod forms;
+pub mod layout;
 pub(crate) mod resampler;
 pub mod routes;
+pub(crate) mod speakers;
+pub mod subtitles;
+pub(crate) mod vad;
 pub mod helper;
 
 pub fn build_scope() -> Scope {
     web::scope("/recognize")
         .service(routes::upload_form)
         .service(routes::recognize_file)
+}
 
+pub fn build_detect_scope() -> Scope {
+    web::scope("/detect-language")
+        .service(routes::detect_language)
 }
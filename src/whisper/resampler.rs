@@ -1,24 +1,125 @@
+use crate::uploads::file::TempFile;
+use crate::whisper::audio::{AudioProbe, WHISPER_SAMPLE_RATE};
+use crate::whisper::errors::{RecognizeError, RecognizeResult};
+
+use serde::Deserialize;
+use std::io::ErrorKind;
+use std::process::Output;
+use std::str::FromStr;
 use tokio::process::Command;
 
+/// Converts audio file of any format supported by ffmpeg to 16 kHz wav file,
+/// channels are mixed into mono unless `split_channels` is passed. Converted
+/// file is removed once returned handle is dropped. Input is probed first unless
+/// `probed` is passed, so failure of conversion is a failure of ffmpeg rather than of the input.
 pub(crate) async fn resample_audio(
     file_path: &str,
-    resample_rate: u32,
-) -> Result<String, std::io::Error> {
-    let uuid_value = uuid::Uuid::new_v4();
-    let output_file = format!("/tmp/{}.wav", uuid_value);
-    let exec_result = Command::new("ffmpeg")
+    split_channels: bool,
+    probed: Option<&AudioProbe>,
+) -> RecognizeResult<TempFile> {
+    if probed.is_none() {
+        probe_audio(file_path).await?;
+    }
+
+    let output_file = TempFile::temp("wav");
+    let mut command = Command::new("ffmpeg");
+    // ffmpeg must not write the file after request is cancelled and the file is removed.
+    command
+        .kill_on_drop(true)
+        .arg("-nostdin")
+        .arg("-loglevel")
+        .arg("error")
         .arg("-i")
-        .arg(file_path)
-        .arg("-ac")
-        .arg("1")
+        .arg(file_path);
+    if !split_channels {
+        command.arg("-ac").arg("1");
+    }
+
+    let exec_result = command
         .arg("-ar")
-        .arg(resample_rate.to_string().as_str())
-        .arg(output_file.as_str())
+        .arg(WHISPER_SAMPLE_RATE.to_string().as_str())
+        .arg("-c:a")
+        .arg("pcm_s16le")
+        .arg(output_file.path())
         .output()
-        .await;
+        .await
+        .map_err(|err| spawn_error("ffmpeg", err))?;
 
-    match exec_result {
-        Ok(_) => Ok(output_file),
-        Err(err) => Err(err),
+    if !exec_result.status.success() {
+        return Err(RecognizeError::ConvertAudio(exit_error(&exec_result)));
     }
+
+    Ok(output_file)
+}
+
+/// Checks with ffprobe that file holds an audio stream ffmpeg is able to decode
+/// and reads its duration. ffprobe exits with failure when input could not be demuxed.
+pub(crate) async fn probe_audio(file_path: &str) -> RecognizeResult<AudioProbe> {
+    let exec_result = Command::new("ffprobe")
+        .kill_on_drop(true)
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-select_streams")
+        .arg("a:0")
+        .arg("-show_entries")
+        .arg("stream=codec_name:format=duration")
+        .arg("-of")
+        .arg("json")
+        .arg(file_path)
+        .output()
+        .await
+        .map_err(|err| spawn_error("ffprobe", err))?;
+
+    if !exec_result.status.success() {
+        return Err(RecognizeError::UnsupportedAudio(exit_error(&exec_result)));
+    }
+
+    let probed = serde_json::from_slice::<ProbeOutput>(&exec_result.stdout)
+        .map_err(|err| RecognizeError::ConvertAudio(format!("unexpected ffprobe output: {}", err)))?;
+
+    let stream = probed
+        .streams
+        .first()
+        .ok_or_else(|| RecognizeError::UnsupportedAudio("no audio stream found".to_string()))?;
+    // Streams of codecs unknown to ffmpeg are reported without name or as `unknown`.
+    if matches!(stream.codec_name.as_deref(), None | Some("unknown")) {
+        return Err(RecognizeError::UnsupportedAudio("unknown audio codec".to_string()));
+    }
+
+    let duration_secs = probed
+        .format
+        .and_then(|format| format.duration)
+        .and_then(|duration| f64::from_str(duration.as_str()).ok());
+
+    Ok(AudioProbe { duration_secs })
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    duration: Option<String>,
+}
+
+fn spawn_error(program: &str, err: std::io::Error) -> RecognizeError {
+    let msg = format!("failed to run {}: {}", program, err);
+    match err.kind() {
+        ErrorKind::NotFound => RecognizeError::ConverterMissing(msg),
+        _ => RecognizeError::ConvertAudio(msg),
+    }
+}
+
+fn exit_error(exec_result: &Output) -> String {
+    let stderr = String::from_utf8_lossy(&exec_result.stderr);
+    format!("{}: {}", exec_result.status, stderr.trim())
 }
@@ -1,11 +1,14 @@
 use crate::errors::{ErrorResponse, SuccessfulResponse, WebError};
-use crate::{ContextData, RecognizeData};
-use crate::whisper::forms::{RecognizeParameters, RecognizeResponse};
-use crate::whisper::helper;
+use crate::{ContextData, UploadsData};
+use crate::whisper::forms::{
+    DetectLanguageParameters, DetectLanguageResponse, RecognizeParameters, RecognizeQuery, ResponseFormat,
+    SubtitleParameters,
+};
+use crate::whisper::layout;
+use crate::whisper::helper::{self, StreamFormat};
 use actix_multipart::Multipart;
-use actix_web::{get, HttpResponse, post, web};
+use actix_web::{get, HttpRequest, HttpResponse, post};
 use actix_web::http::StatusCode;
-use actix_web::web::Query;
 
 #[utoipa::path(
     get,
@@ -44,46 +47,106 @@ pub async fn upload_form() -> HttpResponse {
     post,
     path = "/recognize/file",
     tag = "Recognize",
-    params(
-        (
-            "concatenate", Query,
-            description = "Concatenate chunked text to common",
-            example = "true"
-        )
-    ),
+    params(RecognizeQuery, RecognizeParameters, SubtitleParameters),
     request_body(
         content_type = "multipart/formdata",
         content = Multipart,
-        example = "To check open url /recognize/file into browser.",
+        example = "To check open url /recognize/file into browser. \
+            Recognize parameters may be passed as form fields as well.",
     ),
     responses(
         (
             status = 200,
             description = "Successful",
-            body = [RecognizeResponse],
-            example = json!([
-                {
-                    "frame_id": 0,
-                    "frame_start": 0,
-                    "frame_end": 3,
-                    "text": "Hello",
-                },
-                {
-                    "frame_id": 2,
-                    "frame_start": 0,
-                    "frame_end": 3,
-                    "text": "world",
-                }
-            ])
+            content(
+                ("application/json" = [RecognizeResponse], example = json!([
+                    {
+                        "frame_id": 0,
+                        "frame_start": 0,
+                        "frame_end": 3,
+                        "text": "Hello",
+                        "model": "default",
+                    },
+                    {
+                        "frame_id": 2,
+                        "frame_start": 0,
+                        "frame_end": 3,
+                        "text": "world",
+                        "model": "default",
+                    }
+                ])),
+                ("application/x-subrip" = String, example = json!(
+                    "1\n00:00:00,000 --> 00:00:00,030\nHello\n\n"
+                )),
+                ("text/vtt" = String, example = json!(
+                    "WEBVTT\n\n00:00:00.000 --> 00:00:00.030\nHello\n\n"
+                )),
+                ("application/x-ndjson" = String, example = json!(
+                    "{\"frame_id\":0,\"frame_start\":0,\"frame_end\":3,\"text\":\"Hello\",\"model\":\"default\"}\n"
+                )),
+                ("text/event-stream" = String, example = json!(
+                    "event: segment\ndata: {\"frame_id\":0,\"frame_start\":0,\"frame_end\":3,\"text\":\"Hello\",\"model\":\"default\"}\n\n"
+                )),
+            )
         ),
         (
             status = 400,
-            description = "Failed while recognizing audio file",
+            description = "Failed while extracting multipart form",
             body = ErrorResponse,
             example = json!(ErrorResponse {
                 code: 400,
-                error: "Bad Request".to_string(),
-                message: "Failed while recognizing audio file".to_string(),
+                error: "InvalidMultipart".to_string(),
+                message: "Invalid multipart form: file field expected".to_string(),
+            })
+        ),
+        (
+            status = 413,
+            description = "Uploaded file, text field or audio duration exceeds limits",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 413,
+                error: "PayloadTooLarge".to_string(),
+                message: "Payload too large: Failed while extracting file: exceeds 1073741824 bytes".to_string(),
+            })
+        ),
+        (
+            status = 415,
+            description = "Audio container is not allowed or codec is not supported",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 415,
+                error: "UnsupportedMedia".to_string(),
+                message: "Unsupported media: unknown audio container".to_string(),
+            })
+        ),
+        (
+            status = 422,
+            description = "Invalid recognize parameters or unknown model",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 422,
+                error: "InvalidParameters".to_string(),
+                message: "Invalid parameters: unsupported language: xx".to_string(),
+            })
+        ),
+        (
+            status = 500,
+            description = "Failed while decoding or recognizing audio file",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 500,
+                error: "DecodeFailed".to_string(),
+                message: "Failed while decoding audio: end of stream".to_string(),
+            })
+        ),
+        (
+            status = 503,
+            description = "All engines are busy, retry after `Retry-After` seconds",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 503,
+                error: "EngineBusy".to_string(),
+                message: "Engine is busy: 8 requests are waiting".to_string(),
             })
         ),
     )
@@ -91,27 +154,160 @@ pub async fn upload_form() -> HttpResponse {
 #[post("/file")]
 pub async fn recognize_file(
     cxt: ContextData,
-    concatenate: Query<bool>,
+    uploads: UploadsData,
+    req: HttpRequest,
     payload: Multipart,
-) -> RecognizeData<serde_json::Value> {
-    let params = RecognizeParameters::default();
-    match helper::extract_multiform_data(payload).await {
-        Err(err) => Err(WebError::ResponseError(err.to_string())),
-        Ok(file_path) => {
-            let client = cxt.get_ref().get_client().await;
-            match client.recognize_file(file_path.as_str(), &params).await {
-                Err(err) => Err(WebError::ResponseError(err.to_string())),
-                Ok(data) => {
-                    if concatenate.0 {
-                        let test = RecognizeResponse::from(data);
-                        Ok(web::Json(serde_json::to_value(test).unwrap()))
-                    } else {
-                        Ok(web::Json(serde_json::to_value(data).unwrap()))
-                    }
-                },
-            }
-        },
+) -> Result<HttpResponse, WebError> {
+    let form = helper::extract_multiform_data(payload, uploads.get_ref()).await?;
+
+    let query = helper::merge_parameters::<RecognizeQuery>(req.query_string(), &form.fields)
+        .map_err(|err| WebError::InvalidParameters(err.to_string()))?;
+    let mut params = helper::merge_parameters::<RecognizeParameters>(req.query_string(), &form.fields)
+        .map_err(|err| WebError::InvalidParameters(err.to_string()))?;
+    params.validate().map_err(WebError::InvalidParameters)?;
+    if matches!(query.get_format(), ResponseFormat::VttKaraoke) {
+        params.set_word_timestamps_enable(true);
+    }
+    let subtitles = helper::merge_parameters::<SubtitleParameters>(req.query_string(), &form.fields)
+        .map_err(|err| WebError::InvalidParameters(err.to_string()))?;
+    subtitles.validate().map_err(WebError::InvalidParameters)?;
+
+    let client = cxt.get_ref().get_model(query.get_model())?;
+    if query.is_stream_enable() {
+        let streamable = matches!(query.get_format(), ResponseFormat::Json)
+            && !query.is_concatenate_enable()
+            && !subtitles.is_reflow_enable()
+            && !params.is_split_channels_enable()
+            && !params.is_parallel_enable();
+        if !streamable {
+            let msg = "stream supports json format without concatenate, reflow, split channels and parallel only";
+            return Err(WebError::InvalidParameters(msg.to_string()));
+        }
+
+        let receiver = client.stream_file(form.file.path(), &params).await?;
+        return Ok(helper::build_stream_response(StreamFormat::from_request(&req), receiver));
+    }
+
+    let mut segments = client.recognize_file(form.file.path(), &params).await?;
+    if subtitles.is_reflow_enable() {
+        segments = layout::reflow(segments, &subtitles);
     }
+    let response = helper::build_response(query.get_format(), query.is_concatenate_enable(), segments);
+    Ok(response)
 }
 
+#[utoipa::path(
+    post,
+    path = "/detect-language",
+    tag = "Recognize",
+    params(DetectLanguageParameters),
+    request_body(
+        content_type = "multipart/formdata",
+        content = Multipart,
+        example = "Detect parameters may be passed as form fields as well.",
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Successful",
+            body = DetectLanguageResponse,
+            example = json!({
+                "language": "en",
+                "languages": [
+                    {"language": "en", "probability": 0.93},
+                    {"language": "de", "probability": 0.04},
+                ],
+                "model": "default",
+            })
+        ),
+        (
+            status = 400,
+            description = "Failed while extracting multipart form",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 400,
+                error: "InvalidMultipart".to_string(),
+                message: "Invalid multipart form: file field expected".to_string(),
+            })
+        ),
+        (
+            status = 413,
+            description = "Uploaded file or audio duration exceeds limits",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 413,
+                error: "PayloadTooLarge".to_string(),
+                message: "Payload too large: Failed while extracting file: exceeds 1073741824 bytes".to_string(),
+            })
+        ),
+        (
+            status = 415,
+            description = "Audio container is not allowed or codec is not supported",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 415,
+                error: "UnsupportedMedia".to_string(),
+                message: "Unsupported media: unknown audio container".to_string(),
+            })
+        ),
+        (
+            status = 422,
+            description = "Invalid detect parameters or unknown model",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 422,
+                error: "InvalidParameters".to_string(),
+                message: "Invalid parameters: duration_secs must be between 1 and 30".to_string(),
+            })
+        ),
+        (
+            status = 500,
+            description = "Failed while decoding audio file or detecting language",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 500,
+                error: "DecodeFailed".to_string(),
+                message: "Failed while decoding audio: end of stream".to_string(),
+            })
+        ),
+        (
+            status = 503,
+            description = "All engines are busy, retry after `Retry-After` seconds",
+            body = ErrorResponse,
+            example = json!(ErrorResponse {
+                code: 503,
+                error: "EngineBusy".to_string(),
+                message: "Engine is busy: 8 requests are waiting".to_string(),
+            })
+        ),
+    )
+)]
+#[post("")]
+pub async fn detect_language(
+    cxt: ContextData,
+    uploads: UploadsData,
+    req: HttpRequest,
+    payload: Multipart,
+) -> Result<HttpResponse, WebError> {
+    let form = helper::extract_multiform_data(payload, uploads.get_ref()).await?;
+
+    let params = helper::merge_parameters::<DetectLanguageParameters>(req.query_string(), &form.fields)
+        .map_err(|err| WebError::InvalidParameters(err.to_string()))?;
+    params.validate().map_err(WebError::InvalidParameters)?;
 
+    let client = cxt.get_ref().get_model(params.get_model())?;
+    let mut languages = client
+        .detect_language(form.file.path(), params.get_duration_secs(), params.get_threads())
+        .await?;
+    languages.truncate(params.get_top_k());
+
+    let response = DetectLanguageResponse {
+        language: languages
+            .first()
+            .map(|detected| detected.language.to_owned())
+            .unwrap_or_default(),
+        languages,
+        model: client.get_name().to_string(),
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
@@ -0,0 +1,64 @@
+use crate::whisper::engine::RecognizeObserver;
+use crate::whisper::forms::{RecognizeParameters, RecognizeResponse};
+
+use std::sync::{Mutex, PoisonError};
+
+/// Counts speaker turns of segments which come in time order. Tinydiarize
+/// marks turns only, so speakers are told apart by alternating labels.
+pub(crate) struct SpeakerTurns {
+    turn: i32,
+    speakers_count: i32,
+}
+
+impl SpeakerTurns {
+    pub fn new(params: &RecognizeParameters) -> Self {
+        SpeakerTurns {
+            turn: 0,
+            speakers_count: params.get_speakers_count(),
+        }
+    }
+
+    /// Labels segment with speaker of the current turn and starts the next
+    /// turn if speaker changes after the segment.
+    pub fn label(&mut self, segment: &mut RecognizeResponse) {
+        segment.speaker = Some(match self.speakers_count {
+            0 => self.turn,
+            count => self.turn % count,
+        });
+
+        if segment.speaker_turn_next == Some(true) {
+            self.turn += 1;
+        }
+    }
+}
+
+/// Labels segments of the whole recognized audio with speakers.
+pub(crate) fn label_speakers(segments: &mut [RecognizeResponse], params: &RecognizeParameters) {
+    let mut turns = SpeakerTurns::new(params);
+    segments.iter_mut().for_each(|segment| turns.label(segment));
+}
+
+/// Labels segments with speakers before they are passed to the observer.
+pub(crate) struct SpeakersObserver<'a> {
+    pub observer: &'a dyn RecognizeObserver,
+    pub turns: Mutex<SpeakerTurns>,
+}
+
+impl RecognizeObserver for SpeakersObserver<'_> {
+    fn on_progress(&self, percent: i32) {
+        self.observer.on_progress(percent);
+    }
+
+    fn on_segment(&self, segment: &RecognizeResponse) {
+        let mut segment = segment.clone();
+        self.turns
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .label(&mut segment);
+        self.observer.on_segment(&segment);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.observer.is_cancelled()
+    }
+}
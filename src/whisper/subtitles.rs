@@ -0,0 +1,158 @@
+use crate::whisper::forms::{RecognizeResponse, RecognizedWord};
+
+use std::fmt::Write;
+
+pub const SRT_CONTENT_TYPE: &str = "application/x-subrip; charset=utf-8";
+pub const VTT_CONTENT_TYPE: &str = "text/vtt; charset=utf-8";
+
+/// Renders recognized segments as SubRip subtitles, text of labeled
+/// segments is prefixed with speaker.
+pub fn to_srt(segments: &[RecognizeResponse]) -> String {
+    let mut subtitles = String::new();
+    cues(segments).enumerate().for_each(|(index, (segment, text))| {
+        let text = match speaker_label(segment) {
+            Some(speaker) => format!("{}: {}", speaker, text),
+            None => text.to_string(),
+        };
+
+        let _ = write!(
+            subtitles,
+            "{}\n{} --> {}\n{}\n\n",
+            index + 1,
+            format_timestamp(segment.frame_start, ','),
+            format_timestamp(segment.frame_end, ','),
+            text,
+        );
+    });
+
+    subtitles
+}
+
+/// Renders recognized segments as WebVTT subtitles, labeled segments are
+/// wrapped into voice tags.
+pub fn to_vtt(segments: &[RecognizeResponse]) -> String {
+    let mut subtitles = String::from("WEBVTT\n\n");
+    cues(segments).for_each(|(segment, text)| {
+        // Escaping also keeps the timings separator out of cue payload.
+        let _ = write!(
+            subtitles,
+            "{} --> {}\n{}{}\n\n",
+            format_timestamp(segment.frame_start, '.'),
+            format_timestamp(segment.frame_end, '.'),
+            voice_tag(segment),
+            escape_cue_text(text),
+        );
+    });
+
+    subtitles
+}
+
+/// Renders recognized segments as WebVTT subtitles with inline timestamp tags
+/// before every word, so players highlight words as they are spoken. Segments
+/// without word timestamps are rendered as plain cues.
+pub fn to_vtt_karaoke(segments: &[RecognizeResponse]) -> String {
+    let mut subtitles = String::from("WEBVTT\n\n");
+    for segment in segments {
+        let (start, end) = (segment.frame_start, segment.frame_end);
+        let text = match segment.words.as_deref() {
+            Some(words) if !words.is_empty() => karaoke_text(start, end, segment.text.as_str(), words),
+            _ => escape_cue_text(segment.text.trim()),
+        };
+
+        if text.is_empty() {
+            continue;
+        }
+
+        let _ = write!(
+            subtitles,
+            "{} --> {}\n{}{}\n\n",
+            format_timestamp(start, '.'),
+            format_timestamp(end, '.'),
+            voice_tag(segment),
+            text,
+        );
+    }
+
+    subtitles
+}
+
+/// Timestamp tags must lay strictly inside cue, so the first word goes without one.
+/// Words are broken into lines like cue text is, e.g. after reflow.
+fn karaoke_text(start: i64, end: i64, text: &str, words: &[RecognizedWord]) -> String {
+    let words_per_line = text
+        .lines()
+        .map(|line| line.split_whitespace().count())
+        .filter(|count| *count > 0)
+        .collect::<Vec<usize>>();
+    let line_breaks = match words_per_line.iter().sum::<usize>() == words.len() {
+        true => words_per_line,
+        false => vec![words.len()],
+    };
+
+    let mut lines = Vec::new();
+    let mut remaining = words;
+    for count in line_breaks {
+        let (line_words, rest) = remaining.split_at(count);
+        remaining = rest;
+
+        let mut line = String::new();
+        for word in line_words {
+            let word_start = word.start.clamp(start, end);
+            if word_start > start && word_start < end {
+                let _ = write!(line, "<{}>", format_timestamp(word_start, '.'));
+            }
+
+            let _ = write!(line, "<c>{}</c> ", escape_cue_text(word.word.as_str()));
+        }
+        lines.push(line.trim_end().to_string());
+    }
+
+    lines.join("\n")
+}
+
+/// Escapes characters which start tags or entities in cue payload.
+fn escape_cue_text(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Skips segments without text, whisper emits them for silence.
+fn cues(segments: &[RecognizeResponse]) -> impl Iterator<Item = (&RecognizeResponse, &str)> {
+    segments
+        .iter()
+        .map(|segment| (segment, segment.text.trim()))
+        .filter(|(_, text)| !text.is_empty())
+}
+
+/// Speaker of segment is its channel name or index in `split` channels mode
+/// or speaker detected by turns.
+fn speaker_label(segment: &RecognizeResponse) -> Option<String> {
+    match (segment.channel_name.as_ref(), segment.channel, segment.speaker) {
+        (Some(name), _, _) => Some(name.to_owned()),
+        (None, Some(channel), _) => Some(format!("Channel {}", channel)),
+        (None, None, Some(speaker)) => Some(format!("Speaker {}", speaker)),
+        (None, None, None) => None,
+    }
+}
+
+/// Voice tag opening WebVTT cue payload of labeled segment.
+fn voice_tag(segment: &RecognizeResponse) -> String {
+    speaker_label(segment)
+        .map(|speaker| format!("<v {}>", escape_cue_text(speaker.as_str())))
+        .unwrap_or_default()
+}
+
+/// Formats whisper timestamp in centiseconds as `HH:MM:SS,mmm`.
+fn format_timestamp(centiseconds: i64, millis_separator: char) -> String {
+    let millis = centiseconds.max(0) * 10;
+    let hours = millis / 3_600_000;
+    let minutes = millis / 60_000 % 60;
+    let seconds = millis / 1_000 % 60;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        hours,
+        minutes,
+        seconds,
+        millis_separator,
+        millis % 1_000
+    )
+}
@@ -0,0 +1,118 @@
+use crate::whisper::audio::WHISPER_SAMPLE_RATE;
+use crate::whisper::forms::RecognizeParameters;
+
+use std::ops::Range;
+
+/// Analysis frame of 30 ms.
+pub(crate) const FRAME_SIZE: usize = WHISPER_SAMPLE_RATE as usize * 30 / 1000;
+/// Quiet frames which are taken as noise floor, in percents.
+const NOISE_FLOOR_PERCENTILE: usize = 10;
+/// Frames quieter than it are never speech, whatever the noise floor is.
+const MIN_SPEECH_DB: f32 = -55.0;
+/// Clips without pauses would take speech as noise floor, so the floor is capped.
+const MAX_NOISE_FLOOR_DB: f32 = -45.0;
+/// Broadband noise crosses zero at about a half of samples, voiced speech much less often.
+const MAX_SPEECH_ZCR: f32 = 0.45;
+/// Speech regions shorter than it are taken as clicks and dropped.
+const MIN_SPEECH_MS: usize = 250;
+/// Whisper decodes audio by 30 seconds windows, longer regions are split.
+const MAX_REGION_MS: usize = 30_000;
+
+/// Energy and zero crossing rate of analysis frame.
+pub(crate) struct Frame {
+    pub energy_db: f32,
+    pub zcr: f32,
+}
+
+/// Splits audio into analysis frames, the last partial frame is kept.
+pub(crate) fn analyze_frames(audio: &[f32]) -> Vec<Frame> {
+    audio
+        .chunks(FRAME_SIZE)
+        .map(|frame| {
+            let power = frame.iter().map(|sample| sample * sample).sum::<f32>() / frame.len() as f32;
+            let crossings = frame
+                .windows(2)
+                .filter(|pair| (pair[0] >= 0.0) != (pair[1] >= 0.0))
+                .count();
+
+            Frame {
+                energy_db: 10.0 * (power + 1e-10).log10(),
+                zcr: crossings as f32 / frame.len().max(2) as f32,
+            }
+        })
+        .collect()
+}
+
+/// Detects speech regions of 16 kHz mono audio and returns their sample ranges.
+/// Frame is speech if its energy exceeds the adaptive noise floor by threshold
+/// and it does not look like broadband noise. Short pauses are bridged, short
+/// bursts are dropped and every region is padded and kept within 30 seconds.
+pub(crate) fn detect_speech(audio: &[f32], params: &RecognizeParameters) -> Vec<Range<usize>> {
+    let frames = analyze_frames(audio);
+    if frames.is_empty() {
+        return Vec::new();
+    }
+
+    let threshold_db = (noise_floor_db(&frames) + params.get_vad_threshold_db()).max(MIN_SPEECH_DB);
+    let is_speech = frames
+        .iter()
+        .map(|frame| frame.energy_db > threshold_db && frame.zcr < MAX_SPEECH_ZCR)
+        .collect::<Vec<bool>>();
+
+    let min_silence = ms_to_frames(params.get_vad_min_silence_ms() as usize);
+    let min_speech = ms_to_frames(MIN_SPEECH_MS);
+    let pad = ms_to_frames(params.get_vad_speech_pad_ms() as usize);
+
+    let mut regions = Vec::<Range<usize>>::new();
+    for (frame_id, _) in is_speech.iter().enumerate().filter(|(_, speech)| **speech) {
+        match regions.last_mut() {
+            Some(region) if frame_id - region.end < min_silence => region.end = frame_id + 1,
+            _ => regions.push(frame_id..frame_id + 1),
+        }
+    }
+
+    let mut padded = Vec::<Range<usize>>::new();
+    for region in regions.into_iter().filter(|region| region.len() >= min_speech) {
+        let region = region.start.saturating_sub(pad)..(region.end + pad).min(frames.len());
+        match padded.last_mut() {
+            Some(last) if region.start <= last.end => last.end = region.end,
+            _ => padded.push(region),
+        }
+    }
+
+    padded
+        .into_iter()
+        .flat_map(|region| split_long_region(region, &frames))
+        .map(|region| region.start * FRAME_SIZE..(region.end * FRAME_SIZE).min(audio.len()))
+        .collect()
+}
+
+/// Splits region at the quietest frame of its second half until every part fits whisper window.
+fn split_long_region(region: Range<usize>, frames: &[Frame]) -> Vec<Range<usize>> {
+    let max_frames = ms_to_frames(MAX_REGION_MS);
+    let mut parts = Vec::new();
+    let mut remaining = region;
+    while remaining.len() > max_frames {
+        let search = remaining.start + max_frames / 2..remaining.start + max_frames;
+        let split_at = search
+            .clone()
+            .min_by(|a, b| frames[*a].energy_db.total_cmp(&frames[*b].energy_db))
+            .unwrap_or(search.end);
+
+        parts.push(remaining.start..split_at);
+        remaining = split_at..remaining.end;
+    }
+    parts.push(remaining);
+
+    parts
+}
+
+fn noise_floor_db(frames: &[Frame]) -> f32 {
+    let mut energies = frames.iter().map(|frame| frame.energy_db).collect::<Vec<f32>>();
+    energies.sort_by(f32::total_cmp);
+    energies[(energies.len() - 1) * NOISE_FLOOR_PERCENTILE / 100].min(MAX_NOISE_FLOOR_DB)
+}
+
+fn ms_to_frames(millis: usize) -> usize {
+    millis * WHISPER_SAMPLE_RATE as usize / 1000 / FRAME_SIZE
+}
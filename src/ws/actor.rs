@@ -1,8 +1,8 @@
-use crate::whisper::forms::RecognizeParameters;
-use crate::whisper::client::WhisperClient;
-use crate::whisper::config::WhisperClientConfig;
+use crate::errors::WebError;
+use crate::whisper::forms::{RecognizeParameters, RecognizeResponse};
+use crate::whisper::client_async::ModelClient;
 
-use actix::{Actor, ActorContext, AsyncContext, StreamHandler};
+use actix::{Actor, ActorContext, ActorFutureExt, AsyncContext, StreamHandler, WrapFuture};
 use actix_web_actors::ws;
 use std::time::{Duration, Instant};
 
@@ -11,14 +11,18 @@ const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct WebsocketActor {
     hb: Instant,
-    client: WhisperClient,
+    client: ModelClient,
+    params: RecognizeParameters,
+    concatenate: bool,
 }
 
 impl WebsocketActor {
-    pub fn new(wh_cfg: &WhisperClientConfig) -> Self {
+    pub fn new(client: ModelClient, params: RecognizeParameters, concatenate: bool) -> Self {
         Self {
             hb: Instant::now(),
-            client: WhisperClient::new(wh_cfg),
+            client,
+            params,
+            concatenate,
         }
     }
 
@@ -61,17 +65,32 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebsocketActor {
             // Text will echo any text received back to the client (for now)
             Ok(ws::Message::Text(text)) => ctx.text(text),
             Ok(ws::Message::Binary(data)) => {
-                let data_vec = data.to_vec();
-                let params = RecognizeParameters::default();
-                match self.client.recognize_chunk(data_vec.as_slice(), &params) {
-                    Err(err) => {
-                        ctx.text(err.to_string())
-                    }
-                    Ok(resp) => {
-                        let values = serde_json::to_value(resp).unwrap();
-                        ctx.text(values.to_string())
-                    }
-                }
+                let client = self.client.clone();
+                let params = self.params.clone();
+                let recognize_fut = async move {
+                    client.recognize_chunk(data.to_vec(), &params).await
+                };
+
+                let recognize_fut = recognize_fut
+                    .into_actor(self)
+                    .map(|recognize_res, act, ctx| match recognize_res {
+                        Err(err) => {
+                            let values = serde_json::to_value(WebError::from(err).to_response()).unwrap();
+                            ctx.text(values.to_string())
+                        }
+                        Ok(resp) if act.concatenate => {
+                            let values = serde_json::to_value(RecognizeResponse::from(resp)).unwrap();
+                            ctx.text(values.to_string())
+                        }
+                        Ok(resp) => {
+                            let values = serde_json::to_value(resp).unwrap();
+                            ctx.text(values.to_string())
+                        }
+                    });
+
+                // Next frames wait until the chunk is recognized, so replies
+                // come in order of chunks whatever the state pool size is.
+                ctx.wait(recognize_fut);
             }
             Ok(ws::Message::Close(reason)) => {
                 ctx.close(reason);
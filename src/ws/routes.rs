@@ -1,5 +1,7 @@
+use crate::ContextData;
+use crate::errors::WebError;
+use crate::whisper::forms::{RecognizeParameters, RecognizeQuery};
 use crate::ws::actor::WebsocketActor;
-use crate::whisper::config::WhisperClientConfig;
 
 use actix_web::{Error, HttpRequest, HttpResponse, Responder, web};
 use actix_web::http::StatusCode;
@@ -11,7 +13,27 @@ pub async fn stream(_: HttpRequest) -> impl Responder {
         .body(include_str!("../../static/stream.html"))
 }
 
-pub async fn websocket(req: HttpRequest, stream: web::Payload) -> Result<HttpResponse, Error> {
-    let wh_cfg = WhisperClientConfig::default();
-    ws::start(WebsocketActor::new(&wh_cfg), &req, stream)
+pub async fn websocket(
+    cxt: ContextData,
+    query: web::Query<RecognizeQuery>,
+    req: HttpRequest,
+    stream: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let client = cxt
+        .get_ref()
+        .get_model(query.get_model())
+        .map_err(WebError::from)?
+        .clone();
+
+    let params = web::Query::<RecognizeParameters>::from_query(req.query_string())
+        .map_err(|err| WebError::InvalidParameters(err.to_string()))?
+        .into_inner();
+    params.validate().map_err(WebError::InvalidParameters)?;
+    if params.is_split_channels_enable() {
+        let msg = "websocket chunks are recognized as mono, split channels are not supported";
+        return Err(WebError::InvalidParameters(msg.to_string()).into());
+    }
+
+    let actor = WebsocketActor::new(client, params, query.is_concatenate_enable());
+    ws::start(actor, &req, stream)
 }
@@ -0,0 +1,88 @@
+mod common;
+
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::engine::SpeechEngine;
+use audio_to_text::whisper::errors::{RecognizeError, RecognizeResult};
+use audio_to_text::whisper::forms::{LanguageProbability, RecognizeParameters, RecognizeResponse};
+
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::io::Cursor;
+
+const AMPLITUDE: f32 = 0.5;
+
+/// Answers with samples count and peak of audio passed to the engine.
+struct LevelEngine;
+
+impl SpeechEngine for LevelEngine {
+    fn recognize(&self, audio: &[f32], _params: &RecognizeParameters) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let peak = audio.iter().fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+        let text = format!("{} {}", audio.len(), peak);
+        Ok(vec![RecognizeResponse { text, ..Default::default() }])
+    }
+
+    fn detect_language(&self, _audio: &[f32], _threads: i32) -> RecognizeResult<Vec<LanguageProbability>> {
+        Ok(Vec::new())
+    }
+}
+
+fn wav_bytes(sample_rate: u32, channels: u16, bits_per_sample: u16, sample_format: SampleFormat, secs: f32) -> Vec<u8> {
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample,
+        sample_format,
+    };
+
+    let mut cursor = Cursor::new(Vec::new());
+    let mut writer = WavWriter::new(&mut cursor, spec).unwrap();
+    let max_value = ((1_i64 << (bits_per_sample - 1)) - 1) as f32;
+    for sample in 0..(secs * sample_rate as f32) as usize {
+        let value = (sample as f32 * 440.0 * std::f32::consts::TAU / sample_rate as f32).sin() * AMPLITUDE;
+        for _ in 0..channels {
+            match sample_format {
+                SampleFormat::Float => writer.write_sample(value).unwrap(),
+                SampleFormat::Int => writer.write_sample((value * max_value) as i32).unwrap(),
+            }
+        }
+    }
+
+    writer.finalize().unwrap();
+    cursor.into_inner()
+}
+
+#[actix_web::test]
+async fn any_wav_is_normalized_to_whisper_mono() {
+    let client = WhisperAsyncClient::with_engine(LevelEngine);
+    let model = client.get_model(None).unwrap();
+    let params = RecognizeParameters::default();
+
+    let formats = [
+        (16_000, 1, 16, SampleFormat::Int),
+        (8_000, 1, 8, SampleFormat::Int),
+        (44_100, 2, 24, SampleFormat::Int),
+        (22_050, 3, 32, SampleFormat::Int),
+        (48_000, 6, 32, SampleFormat::Float),
+    ];
+    for (sample_rate, channels, bits_per_sample, sample_format) in formats {
+        let audio_data = wav_bytes(sample_rate, channels, bits_per_sample, sample_format, 2.5);
+        let segments = model.recognize_chunk(audio_data, &params).await.unwrap();
+
+        let values = segments[0].text.split(' ').collect::<Vec<&str>>();
+        let samples_count = values[0].parse::<usize>().unwrap();
+        let peak = values[1].parse::<f32>().unwrap();
+        let expected_count = (2.5 * common::SAMPLE_RATE as f32) as usize;
+        assert!(samples_count.abs_diff(expected_count) < 100, "{} Hz: {} samples", sample_rate, samples_count);
+        assert!((peak - AMPLITUDE).abs() < 0.05, "{} Hz {} bits: peak {}", sample_rate, bits_per_sample, peak);
+    }
+}
+
+#[actix_web::test]
+async fn broken_wav_is_unsupported() {
+    let client = WhisperAsyncClient::with_engine(LevelEngine);
+    let model = client.get_model(None).unwrap();
+
+    let recognized = model
+        .recognize_chunk(b"RIFF\x10\0\0\0WAVEbroken".to_vec(), &RecognizeParameters::default())
+        .await;
+    assert!(matches!(recognized, Err(RecognizeError::UnsupportedAudio(_))));
+}
@@ -0,0 +1,170 @@
+mod common;
+
+use audio_to_text::whisper;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::engine::SpeechEngine;
+use audio_to_text::whisper::errors::RecognizeResult;
+use audio_to_text::whisper::forms::{LanguageProbability, RecognizeParameters, RecognizeResponse};
+use audio_to_text::whisper::subtitles;
+
+use actix_web::test::{call_service, init_service};
+use actix_web::web;
+
+/// Recognizes every second of passed audio which is not silent as a segment.
+struct SecondsEngine;
+
+impl SpeechEngine for SecondsEngine {
+    fn recognize(&self, audio: &[f32], _params: &RecognizeParameters) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let segments = audio
+            .chunks(common::SAMPLE_RATE as usize)
+            .enumerate()
+            .filter(|(_, second)| second.iter().any(|sample| *sample != 0.0))
+            .enumerate()
+            .map(|(frame_id, (second, _))| RecognizeResponse {
+                frame_id: frame_id as i32,
+                frame_start: second as i64 * 100,
+                frame_end: (second as i64 + 1) * 100,
+                text: format!("second {}", second),
+                ..Default::default()
+            })
+            .collect();
+        Ok(segments)
+    }
+
+    fn detect_language(&self, _audio: &[f32], _threads: i32) -> RecognizeResult<Vec<LanguageProbability>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Audio of one second parts, a part is a tone if it is set.
+fn seconds(parts: &[bool]) -> Vec<f32> {
+    parts
+        .iter()
+        .flat_map(|speech| {
+            let amplitude = if *speech { 0.25 } else { 0.0 };
+            (0..common::SAMPLE_RATE).map(move |sample| (sample as f32 * 0.05).sin() * amplitude)
+        })
+        .collect()
+}
+
+fn parse_parameters(query: &str) -> RecognizeParameters {
+    web::Query::<RecognizeParameters>::from_query(query).unwrap().into_inner()
+}
+
+async fn recognize_dialog(query: &str) -> Vec<RecognizeResponse> {
+    let client = WhisperAsyncClient::with_engine(SecondsEngine);
+    let params = parse_parameters(query);
+    params.validate().unwrap();
+
+    let channels = vec![seconds(&[true, false, true]), seconds(&[false, true, false])];
+    client.get_model(None).unwrap().recognize_channels(channels, &params, None).await.unwrap()
+}
+
+#[actix_web::test]
+async fn channels_are_recognized_and_interleaved_by_time() {
+    let dialog = recognize_dialog("channels=split").await;
+
+    let turns = dialog
+        .iter()
+        .map(|segment| (segment.frame_id, segment.frame_start, segment.channel, segment.text.as_str()))
+        .collect::<Vec<(i32, i64, Option<usize>, &str)>>();
+    let expected = [
+        (0, 0, Some(0), "second 0"),
+        (1, 100, Some(1), "second 1"),
+        (2, 200, Some(0), "second 2"),
+    ];
+    assert_eq!(turns, expected);
+    assert!(dialog.iter().all(|segment| segment.channel_name.is_none()));
+}
+
+#[actix_web::test]
+async fn channels_are_labeled_with_passed_names() {
+    let dialog = recognize_dialog("channels=split&channel_names=agent,%20customer").await;
+    let names = dialog
+        .iter()
+        .map(|segment| segment.channel_name.as_deref())
+        .collect::<Vec<Option<&str>>>();
+    assert_eq!(names, [Some("agent"), Some("customer"), Some("agent")]);
+
+    // Channels without name are labeled by index only.
+    let dialog = recognize_dialog("channels=split&channel_names=agent").await;
+    assert_eq!(dialog[1].channel, Some(1));
+    assert!(dialog[1].channel_name.is_none());
+}
+
+#[actix_web::test]
+async fn mixed_channel_is_not_labeled() {
+    let client = WhisperAsyncClient::with_engine(SecondsEngine);
+    let params = RecognizeParameters::default();
+    let channels = vec![seconds(&[true, true])];
+    let segments = client.get_model(None).unwrap().recognize_channels(channels, &params, None).await.unwrap();
+
+    assert_eq!(segments.len(), 2);
+    assert!(segments.iter().all(|segment| segment.channel.is_none()));
+}
+
+#[actix_web::test]
+async fn subtitles_are_labeled_with_channels() {
+    let dialog = recognize_dialog("channels=split&channel_names=agent").await;
+
+    let srt = subtitles::to_srt(&dialog);
+    let expected = "1\n00:00:00,000 --> 00:00:01,000\nagent: second 0\n\n\
+        2\n00:00:01,000 --> 00:00:02,000\nChannel 1: second 1\n\n\
+        3\n00:00:02,000 --> 00:00:03,000\nagent: second 2\n\n";
+    assert_eq!(srt, expected);
+
+    let vtt = subtitles::to_vtt(&dialog);
+    assert!(vtt.contains("00:00:00.000 --> 00:00:01.000\n<v agent>second 0\n"), "{}", vtt);
+    assert!(vtt.contains("00:00:01.000 --> 00:00:02.000\n<v Channel 1>second 1\n"), "{}", vtt);
+}
+
+#[actix_web::test]
+async fn channel_names_require_split_mode() {
+    let client = WhisperAsyncClient::with_engine(SecondsEngine);
+    let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let req = common::upload_request("/recognize/file?channel_names=agent", &[], &common::wav_bytes(1)).to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), 422);
+
+    let error = common::read_json(resp).await;
+    assert!(error["message"].as_str().unwrap().contains("channel_names requires split channels mode"));
+}
+
+#[actix_web::test]
+async fn websocket_rejects_split_channels() {
+    let client = WhisperAsyncClient::with_engine(SecondsEngine);
+    let (url, server) = common::spawn_ws_server(client);
+
+    let split_url = format!("{}?channels=split", url);
+    assert!(tokio_tungstenite::connect_async(split_url.as_str()).await.is_err());
+    assert!(tokio_tungstenite::connect_async(url.as_str()).await.is_ok());
+
+    server.stop(false).await;
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn stereo_upload_is_recognized_by_channels() {
+    let client = WhisperAsyncClient::with_engine(SecondsEngine);
+    let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let uri = "/recognize/file?channels=split&channel_names=agent,customer";
+    let req = common::upload_request(uri, &[], &common::wav_channels_bytes(2, 2)).to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    let dialog = common::read_json(resp).await;
+    let labels = dialog
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|segment| (segment["frame_start"].as_i64().unwrap(), segment["channel_name"].as_str().unwrap()))
+        .collect::<Vec<(i64, &str)>>();
+    assert_eq!(labels, [(0, "agent"), (0, "customer"), (100, "agent"), (100, "customer")]);
+
+    let req = common::upload_request("/recognize/file", &[], &common::wav_channels_bytes(2, 2)).to_request();
+    let segments = common::read_json(call_service(&app, req).await).await;
+    assert_eq!(segments.as_array().unwrap().len(), 2);
+    assert!(segments[0].get("channel").is_none());
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use audio_to_text::whisper;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::engine::SpeechEngine;
+use audio_to_text::whisper::errors::RecognizeResult;
+use audio_to_text::whisper::forms::{LanguageProbability, RecognizeParameters, RecognizeResponse};
+
+use actix_web::test::{call_service, init_service};
+use actix_web::web;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const SEGMENT_CENTIS: i64 = 500;
+
+/// Recognizes a segment per 5 seconds of passed audio and records how
+/// long windows are and how many of them are recognized at once.
+#[derive(Clone, Default)]
+struct WindowEngine {
+    windows: Arc<Mutex<Vec<usize>>>,
+    active: Arc<AtomicUsize>,
+    peak: Arc<AtomicUsize>,
+}
+
+impl SpeechEngine for WindowEngine {
+    fn recognize(&self, audio: &[f32], _params: &RecognizeParameters) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(active, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(200));
+        self.active.fetch_sub(1, Ordering::SeqCst);
+        self.windows.lock().unwrap().push(audio.len());
+
+        let duration = (audio.len() * 100 / common::SAMPLE_RATE as usize) as i64;
+        let segments = (0..duration)
+            .step_by(SEGMENT_CENTIS as usize)
+            .enumerate()
+            .map(|(frame_id, start)| RecognizeResponse {
+                frame_id: frame_id as i32,
+                frame_start: start,
+                frame_end: (start + SEGMENT_CENTIS).min(duration),
+                text: format!("part {}", frame_id),
+                ..Default::default()
+            })
+            .collect();
+        Ok(segments)
+    }
+
+    fn detect_language(&self, _audio: &[f32], _threads: i32) -> RecognizeResult<Vec<LanguageProbability>> {
+        Ok(Vec::new())
+    }
+
+    fn capacity(&self) -> usize {
+        4
+    }
+}
+
+/// Tone of the given seconds with short pauses at the given seconds.
+fn tone_with_pauses(secs: usize, pauses: &[f32]) -> Vec<f32> {
+    let rate = common::SAMPLE_RATE as usize;
+    let mut audio = (0..secs * rate)
+        .map(|sample| (sample as f32 * 0.05).sin() * 0.25)
+        .collect::<Vec<f32>>();
+    for pause in pauses {
+        let start = (pause * rate as f32) as usize;
+        audio[start..start + rate / 2].fill(0.0);
+    }
+
+    audio
+}
+
+async fn recognize(engine: &WindowEngine, audio: Vec<f32>, query: &str) -> Vec<RecognizeResponse> {
+    let client = WhisperAsyncClient::with_engine(engine.clone());
+    let params = web::Query::<RecognizeParameters>::from_query(query).unwrap().into_inner();
+    params.validate().unwrap();
+    client.get_model(None).unwrap().recognize_audio(audio, &params).await.unwrap()
+}
+
+fn assert_stitched(engine: &WindowEngine, segments: &[RecognizeResponse], start: i64, end: i64) {
+    let ids = segments.iter().map(|segment| segment.frame_id).collect::<Vec<i32>>();
+    assert_eq!(ids, (0..segments.len() as i32).collect::<Vec<i32>>());
+    assert_eq!(segments.first().unwrap().frame_start, start);
+    assert_eq!(segments.last().unwrap().frame_end, end);
+
+    // Segments of overlapping windows are taken once by their middle, so only
+    // segments crossing chunk boundaries may overlap by less than a segment.
+    let boundaries = engine.windows.lock().unwrap().len() as i64 - 1;
+    let covered = segments.iter().map(|segment| segment.frame_end - segment.frame_start).sum::<i64>();
+    assert!((end - start..=end - start + boundaries * SEGMENT_CENTIS).contains(&covered), "{} of {}", covered, end - start);
+    for pair in segments.windows(2) {
+        let (previous, next) = (&pair[0], &pair[1]);
+        assert!(previous.frame_start < next.frame_start);
+        assert!(previous.frame_end - SEGMENT_CENTIS < next.frame_start, "{} {}", previous.frame_end, next.frame_start);
+    }
+}
+
+#[actix_web::test]
+async fn long_audio_is_recognized_by_concurrent_chunks() {
+    let engine = WindowEngine::default();
+    let segments = recognize(&engine, tone_with_pauses(130, &[]), "enable_parallel=true&chunk_secs=30").await;
+
+    let windows = engine.windows.lock().unwrap().len();
+    assert!(windows >= 4, "{} windows", windows);
+    assert!(engine.peak.load(Ordering::SeqCst) > 1);
+    assert_stitched(&engine, &segments, 0, 13_000);
+}
+
+#[actix_web::test]
+async fn chunks_are_split_at_pauses() {
+    let engine = WindowEngine::default();
+    let audio = tone_with_pauses(70, &[26.0]);
+    recognize(&engine, audio, "enable_parallel=true&chunk_secs=30&chunk_overlap_ms=1000").await;
+
+    // The first window ends within the pause extended by overlap.
+    let rate = common::SAMPLE_RATE as usize;
+    let windows = engine.windows.lock().unwrap();
+    let first_window = 26 * rate + rate..26 * rate + rate / 2 + rate;
+    assert!(windows.iter().any(|window| first_window.contains(window)), "{:?}", windows);
+}
+
+#[actix_web::test]
+async fn requested_part_is_recognized_on_original_timeline() {
+    let engine = WindowEngine::default();
+    let query = "enable_parallel=true&chunk_secs=30&offset_ms=10000&duration_ms=60000";
+    let segments = recognize(&engine, tone_with_pauses(130, &[]), query).await;
+    assert_stitched(&engine, &segments, 1_000, 7_000);
+}
+
+#[actix_web::test]
+async fn short_audio_is_recognized_as_single_chunk() {
+    let engine = WindowEngine::default();
+    let segments = recognize(&engine, tone_with_pauses(20, &[]), "enable_parallel=true&chunk_secs=30").await;
+    assert_eq!(*engine.windows.lock().unwrap(), [20 * common::SAMPLE_RATE as usize]);
+    assert_stitched(&engine, &segments, 0, 2_000);
+}
+
+#[actix_web::test]
+async fn invalid_chunk_parameters_are_rejected() {
+    let client = WhisperAsyncClient::with_engine(WindowEngine::default());
+    let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let cases = [
+        ("enable_parallel=true&chunk_secs=10", "chunk_secs must be between 30 and 3600"),
+        ("enable_parallel=true&chunk_overlap_ms=20000", "chunk_overlap_ms must be between 0 and 10000"),
+    ];
+    for (query, message) in cases {
+        let uri = format!("/recognize/file?{}", query);
+        let req = common::upload_request(&uri, &[], &common::wav_bytes(1)).to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 422, "{}", query);
+
+        let error = common::read_json(resp).await;
+        assert!(error["message"].as_str().unwrap().contains(message), "{}", error);
+    }
+}
@@ -0,0 +1,138 @@
+// Every test binary uses its own subset of helpers.
+#![allow(dead_code)]
+
+use audio_to_text::jobs::forms::Job;
+use audio_to_text::jobs::worker::JobQueue;
+use audio_to_text::uploads::config::UploadsConfig;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::ws;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServerHandle, ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::{test, web, App, HttpServer};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const SAMPLE_RATE: u32 = 16_000;
+const BOUNDARY: &str = "audio-to-text-boundary";
+
+/// Mono 16 kHz wav with a tone over the whole duration.
+pub fn wav_bytes(secs: usize) -> Vec<u8> {
+    wav_channels_bytes(1, secs)
+}
+
+/// 16 kHz wav whose channels carry tones of different pitch.
+pub fn wav_channels_bytes(channels: u16, secs: usize) -> Vec<u8> {
+    wav_rate_bytes(SAMPLE_RATE, channels, secs)
+}
+
+/// 16-bit wav of the sample rate whose channels carry tones of different pitch.
+pub fn wav_rate_bytes(sample_rate: u32, channels: u16, secs: usize) -> Vec<u8> {
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+
+    let mut cursor = Cursor::new(Vec::new());
+    let mut writer = WavWriter::new(&mut cursor, spec).unwrap();
+    for sample in 0..secs * sample_rate as usize {
+        for channel in 0..channels {
+            let phase = sample as f32 * 0.05 * (channel + 1) as f32 * SAMPLE_RATE as f32 / sample_rate as f32;
+            writer.write_sample((phase.sin() * 8_000.0) as i16).unwrap();
+        }
+    }
+
+    writer.finalize().unwrap();
+    cursor.into_inner()
+}
+
+/// Builds multipart body with text fields and `file` field, returns its content type too.
+pub fn multipart(fields: &[(&str, &str)], file: &[u8]) -> (String, Vec<u8>) {
+    let mut body = Vec::new();
+    for (name, value) in fields {
+        let part = format!("--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n");
+        body.extend(part.as_bytes());
+    }
+
+    let file_part = format!(
+        "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"audio.wav\"\r\n\
+        Content-Type: audio/wav\r\n\r\n"
+    );
+    body.extend(file_part.as_bytes());
+    body.extend(file);
+    body.extend(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+
+    (format!("multipart/form-data; boundary={BOUNDARY}"), body)
+}
+
+pub fn upload_request(uri: &str, fields: &[(&str, &str)], file: &[u8]) -> test::TestRequest {
+    let (content_type, body) = multipart(fields, file);
+    test::TestRequest::post()
+        .uri(uri)
+        .insert_header(("content-type", content_type))
+        .set_payload(body)
+}
+
+/// App with the client and default upload limits like the server builds it.
+pub fn build_app(
+    client: WhisperAsyncClient,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    App::new()
+        .app_data(web::Data::new(Box::new(client)))
+        .app_data(web::Data::new(UploadsConfig::default()))
+}
+
+/// Reads response body as json value.
+pub async fn read_json(resp: ServiceResponse<impl MessageBody>) -> serde_json::Value {
+    let body = test::read_body(resp).await;
+    serde_json::from_slice(&body).unwrap()
+}
+
+/// Runs websocket route on a free local port, returns its url and server handle.
+pub fn spawn_ws_server(client: WhisperAsyncClient) -> (String, ServerHandle) {
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::new(Box::new(client.clone())))
+            .service(web::resource("/ws").route(web::get().to(ws::routes::websocket)))
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+
+    let url = format!("ws://{}/ws", server.addrs()[0]);
+    let server = server.run();
+    let handle = server.handle();
+    actix_web::rt::spawn(server);
+    (url, handle)
+}
+
+/// Polls the queue until the job is done or failed.
+pub async fn wait_job(queue: &JobQueue, job_id: &str) -> Job {
+    for _ in 0..200 {
+        let job = queue.get(job_id).await.unwrap();
+        if job.state.is_finished() {
+            return job;
+        }
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    panic!("job {} is not finished in time", job_id);
+}
+
+/// Unique path within system temp dir which is not touched by other tests.
+pub fn temp_path(prefix: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{}-{}", prefix, uuid::Uuid::new_v4()))
+}
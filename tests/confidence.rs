@@ -0,0 +1,83 @@
+mod common;
+
+use audio_to_text::openai::forms::TranscriptionSegment;
+use audio_to_text::whisper::forms::{RecognizeResponse, SegmentConfidence};
+
+#[test]
+fn compression_ratio_grows_for_repeated_text() {
+    assert_eq!(SegmentConfidence::compression_ratio(""), 0.0);
+
+    let plain = SegmentConfidence::compression_ratio("The quick brown fox jumps over the lazy dog.");
+    let repeated = SegmentConfidence::compression_ratio(&"Thank you. ".repeat(20));
+    assert!(plain < 1.5, "{}", plain);
+    // OpenAI treats ratio above 2.4 as hallucinated repetition.
+    assert!(repeated > 2.4, "{}", repeated);
+}
+
+#[test]
+fn missing_no_speech_probability_is_omitted() {
+    let confidence = SegmentConfidence {
+        avg_logprob: -0.25,
+        min_token_prob: 0.5,
+        no_speech_prob: None,
+        compression_ratio: 1.0,
+    };
+
+    let value = serde_json::to_value(&confidence).unwrap();
+    assert_eq!(value["avg_logprob"], -0.25);
+    assert_eq!(value["min_token_prob"], 0.5);
+    assert!(value.get("no_speech_prob").is_none());
+
+    let segment = RecognizeResponse {
+        frame_start: 100,
+        frame_end: 250,
+        text: "Hello".to_string(),
+        confidence: Some(confidence),
+        ..Default::default()
+    };
+    let value = serde_json::to_value(TranscriptionSegment::new(&segment, 0.2)).unwrap();
+    assert_eq!(value["start"], 1.0);
+    assert_eq!(value["end"], 2.5);
+    assert_eq!(value["avg_logprob"], -0.25);
+    assert_eq!(value["compression_ratio"], 1.0);
+    assert!(value.get("no_speech_prob").is_none());
+}
+
+#[test]
+fn known_no_speech_probability_is_reported() {
+    let confidence = SegmentConfidence {
+        no_speech_prob: Some(0.75),
+        ..Default::default()
+    };
+    let segment = RecognizeResponse {
+        confidence: Some(confidence.clone()),
+        ..Default::default()
+    };
+
+    assert_eq!(serde_json::to_value(&confidence).unwrap()["no_speech_prob"], 0.75);
+    assert_eq!(serde_json::to_value(TranscriptionSegment::new(&segment, 0.0)).unwrap()["no_speech_prob"], 0.75);
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn confidence_is_reported_when_requested() {
+    use audio_to_text::whisper;
+    use audio_to_text::whisper::client_async::WhisperAsyncClient;
+    use audio_to_text::whisper::fake::FakeEngine;
+    use actix_web::test::{call_service, init_service};
+
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let req = common::upload_request("/recognize/file", &[], &common::wav_bytes(2)).to_request();
+    let segments = common::read_json(call_service(&app, req).await).await;
+    assert!(segments[0].get("confidence").is_none());
+
+    let req = common::upload_request("/recognize/file?enable_confidence=true", &[], &common::wav_bytes(2)).to_request();
+    let segments = common::read_json(call_service(&app, req).await).await;
+    let confidence = &segments[0]["confidence"];
+    assert!(confidence["avg_logprob"].is_number());
+    assert!(confidence["min_token_prob"].is_number());
+    assert!(confidence["compression_ratio"].as_f64().unwrap() > 0.0);
+    assert!(confidence.get("no_speech_prob").is_none());
+}
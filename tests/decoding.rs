@@ -0,0 +1,60 @@
+#![cfg(feature = "enable-native-decoding")]
+
+mod common;
+
+use audio_to_text::whisper;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::fake::FakeEngine;
+
+use actix_web::test;
+
+/// Webm container is allowed for upload but not known to native decoder.
+const WEBM_HEADER: [u8; 12] = [0x1A, 0x45, 0xDF, 0xA3, 0x9F, 0x42, 0x86, 0x81, 0x01, 0x42, 0xF7, 0x81];
+
+#[actix_web::test]
+async fn wav_file_is_decoded_in_process() {
+    let file_path = std::env::temp_dir().join(format!("decoding-{}.wav", std::process::id()));
+    std::fs::write(&file_path, common::wav_rate_bytes(44_100, 2, 3)).unwrap();
+
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let model = client.get_model(None).unwrap();
+    let audio = model.decode_file(file_path.to_str().unwrap()).await;
+    std::fs::remove_file(&file_path).unwrap();
+
+    let audio = audio.unwrap();
+    assert!(audio.len().abs_diff(3 * common::SAMPLE_RATE as usize) < 100);
+}
+
+#[actix_web::test]
+async fn recognize_route_decodes_resampled_stereo() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let app = test::init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let req = common::upload_request("/recognize/file", &[], &common::wav_rate_bytes(44_100, 2, 3)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    let segments = common::read_json(resp).await;
+    assert_eq!(segments[1]["frame_end"], 300);
+}
+
+#[actix_web::test]
+async fn unknown_codec_without_ffmpeg_is_unsupported() {
+    // Only this test spawns processes, so no other test sees changed PATH.
+    let empty_dir = std::env::temp_dir().join(format!("decoding-path-{}", std::process::id()));
+    std::fs::create_dir_all(&empty_dir).unwrap();
+    std::env::set_var("PATH", &empty_dir);
+
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let app = test::init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let mut file = WEBM_HEADER.to_vec();
+    file.extend([0_u8; 64]);
+    let req = common::upload_request("/recognize/file", &[], &file).to_request();
+    let resp = test::call_service(&app, req).await;
+    std::fs::remove_dir(&empty_dir).unwrap();
+    assert_eq!(resp.status(), 415);
+
+    let error = common::read_json(resp).await;
+    assert_eq!(error["error"], "UnsupportedMedia");
+}
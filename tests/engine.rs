@@ -0,0 +1,104 @@
+mod common;
+
+use audio_to_text::whisper;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::errors::RecognizeError;
+use audio_to_text::whisper::fake::FakeEngine;
+use audio_to_text::whisper::forms::RecognizeParameters;
+
+use actix_web::test;
+use futures_util::{SinkExt, StreamExt};
+use tokio_tungstenite::tungstenite::Message;
+
+fn texts(segments: &serde_json::Value) -> Vec<&str> {
+    segments
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|segment| segment["text"].as_str().unwrap())
+        .collect()
+}
+
+#[actix_web::test]
+async fn client_recognizes_audio_with_registered_engines() {
+    let mut client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    client.register_engine("tiny", FakeEngine::new(vec!["tiny phrase".to_string()]));
+    assert_eq!(client.get_default_model(), "default");
+
+    let audio = vec![0.0; 2 * common::SAMPLE_RATE as usize];
+    let params = RecognizeParameters::default();
+    let segments = client
+        .get_model(None)
+        .unwrap()
+        .recognize_audio(audio.clone(), &params)
+        .await
+        .unwrap();
+
+    let phrases = segments.iter().map(|segment| segment.text.as_str()).collect::<Vec<&str>>();
+    assert_eq!(phrases, ["Hello", "world"]);
+    assert_eq!((segments[1].frame_start, segments[1].frame_end), (100, 200));
+    assert!(segments.iter().all(|segment| segment.model == "default"));
+
+    let tiny = client.get_model(Some("tiny")).unwrap();
+    let segments = tiny.recognize_audio(audio, &params).await.unwrap();
+    assert_eq!(segments.len(), 1);
+    assert_eq!(segments[0].model, "tiny");
+
+    let unknown = client.get_model(Some("large")).err();
+    assert!(matches!(unknown, Some(RecognizeError::UnknownModel(name)) if name == "large"));
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn recognize_route_returns_engine_segments() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let app = test::init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let req = common::upload_request("/recognize/file", &[], &common::wav_bytes(2)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    let segments = common::read_json(resp).await;
+    assert_eq!(texts(&segments), ["Hello", "world"]);
+    assert_eq!(segments[1]["frame_end"], 200);
+    assert_eq!(segments[1]["model"], "default");
+}
+
+#[actix_web::test]
+async fn recognize_route_rejects_unknown_model() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let app = test::init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let req = common::upload_request("/recognize/file?model=large", &[], &common::wav_bytes(1)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 422);
+
+    let error = common::read_json(resp).await;
+    assert_eq!(error["error"], "UnknownModel");
+}
+
+#[actix_web::test]
+async fn websocket_session_recognizes_chunks() {
+    let mut client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    client.register_engine("tiny", FakeEngine::new(vec!["tiny".to_string(), "phrase".to_string()]));
+    let (url, server) = common::spawn_ws_server(client);
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(url.as_str()).await.unwrap();
+    socket.send(Message::Binary(common::wav_bytes(1))).await.unwrap();
+    let reply = socket.next().await.unwrap().unwrap().into_text().unwrap();
+    let segments = serde_json::from_str::<serde_json::Value>(&reply).unwrap();
+    assert_eq!(texts(&segments), ["Hello", "world"]);
+
+    let concatenated_url = format!("{}?model=tiny&concatenate=true", url);
+    let (mut socket, _) = tokio_tungstenite::connect_async(concatenated_url.as_str()).await.unwrap();
+    socket.send(Message::Binary(common::wav_bytes(1))).await.unwrap();
+    let reply = socket.next().await.unwrap().unwrap().into_text().unwrap();
+    let segment = serde_json::from_str::<serde_json::Value>(&reply).unwrap();
+    assert_eq!(segment["text"], "tiny phrase");
+    assert_eq!(segment["model"], "tiny");
+
+    let unknown_url = format!("{}?model=large", url);
+    assert!(tokio_tungstenite::connect_async(unknown_url.as_str()).await.is_err());
+
+    server.stop(false).await;
+}
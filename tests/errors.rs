@@ -0,0 +1,163 @@
+mod common;
+
+use audio_to_text::whisper;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::fake::FakeEngine;
+
+use actix_web::test;
+use std::ffi::OsString;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use tokio::sync::{Mutex, MutexGuard};
+
+/// Tests looking up tools in PATH wait for each other, since stubs replace it.
+static ENV_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Webm container is allowed for upload but decoded by ffmpeg in every build.
+const WEBM_HEADER: [u8; 12] = [0x1A, 0x45, 0xDF, 0xA3, 0x9F, 0x42, 0x86, 0x81, 0x01, 0x42, 0xF7, 0x81];
+const PROBED_OPUS: &str = r#"{"streams":[{"codec_name":"opus"}],"format":{"duration":"1.0"}}"#;
+const PROBED_UNKNOWN: &str = r#"{"streams":[{"codec_name":"unknown"}],"format":{}}"#;
+
+fn write_script(dir: &Path, name: &str, body: &str) {
+    let path = dir.join(name);
+    std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+/// Keeps stubbed tools in PATH until dropped, then restores PATH and removes stubs.
+struct StubTools {
+    dir: PathBuf,
+    path: Option<OsString>,
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl Drop for StubTools {
+    fn drop(&mut self) {
+        match self.path.take() {
+            Some(path) => std::env::set_var("PATH", path),
+            None => std::env::remove_var("PATH"),
+        }
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Makes directory with ffprobe and optional ffmpeg stubs the only one in PATH.
+async fn stub_tools(case: &str, ffprobe: &str, ffmpeg: Option<&str>) -> StubTools {
+    let guard = ENV_LOCK.lock().await;
+    let dir = std::env::temp_dir().join(format!("errors-{}-{}", std::process::id(), case));
+    std::fs::create_dir_all(&dir).unwrap();
+    write_script(&dir, "ffprobe", ffprobe);
+    if let Some(ffmpeg) = ffmpeg {
+        write_script(&dir, "ffmpeg", ffmpeg);
+    }
+
+    let path = std::env::var_os("PATH");
+    std::env::set_var("PATH", &dir);
+    StubTools {
+        dir,
+        path,
+        _guard: guard,
+    }
+}
+
+#[actix_web::test]
+async fn ffmpeg_failures_are_told_apart_from_unsupported_input() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let app = test::init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let mut file = WEBM_HEADER.to_vec();
+    file.extend([0_u8; 64]);
+
+    // Without ffmpeg the server is broken, unless native decoder is there to reject audio itself.
+    let missing_status = match cfg!(feature = "enable-native-decoding") {
+        true => 415,
+        false => 500,
+    };
+    let probe_ok = format!("printf '{}'", PROBED_OPUS);
+    let probe_unknown = format!("printf '{}'", PROBED_UNKNOWN);
+    let cases = [
+        ("rejected", "echo 'Invalid data found' >&2; exit 1", Some("exit 0"), 415),
+        ("unknown", probe_unknown.as_str(), Some("exit 0"), 415),
+        ("failed", probe_ok.as_str(), Some("echo 'Conversion failed' >&2; exit 1"), 500),
+        ("missing", probe_ok.as_str(), None, missing_status),
+    ];
+    // Cases run one by one since they share PATH.
+    for (case, ffprobe, ffmpeg, status) in cases {
+        let tools = stub_tools(case, ffprobe, ffmpeg).await;
+        let req = common::upload_request("/recognize/file", &[], &file).to_request();
+        let resp = test::call_service(&app, req).await;
+        drop(tools);
+        assert_eq!(resp.status(), status, "{}", case);
+
+        let error = common::read_json(resp).await;
+        let error_name = match status {
+            415 => "UnsupportedMedia",
+            _ => "DecodeFailed",
+        };
+        assert_eq!(error["error"], error_name, "{}", case);
+    }
+}
+
+#[actix_web::test]
+async fn checked_audio_is_probed_once() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default()).with_max_duration(Some(60));
+    let app = test::init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let mut file = WEBM_HEADER.to_vec();
+    file.extend([0_u8; 64]);
+
+    // ffprobe counts its runs, ffmpeg writes prepared wav to output path passed last.
+    let work_dir = common::temp_path("errors-probed-once");
+    std::fs::create_dir_all(&work_dir).unwrap();
+    let runs_path = work_dir.join("runs");
+    let wav_path = work_dir.join("converted.wav");
+    std::fs::write(&wav_path, common::wav_bytes(1)).unwrap();
+    let ffprobe = format!("echo run >> '{}'; printf '{}'", runs_path.display(), PROBED_OPUS);
+    let ffmpeg = format!("for output; do :; done; /bin/cp '{}' \"$output\"", wav_path.display());
+
+    let tools = stub_tools("probed-once", ffprobe.as_str(), Some(ffmpeg.as_str())).await;
+    let req = common::upload_request("/recognize/file", &[], &file).to_request();
+    let resp = test::call_service(&app, req).await;
+    drop(tools);
+    assert_eq!(resp.status(), 200);
+
+    let runs = std::fs::read_to_string(&runs_path).unwrap();
+    assert_eq!(runs.lines().count(), 1);
+
+    std::fs::remove_dir_all(&work_dir).unwrap();
+}
+
+#[actix_web::test]
+async fn form_without_file_is_bad_request() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let app = test::init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let body = "--bound\r\nContent-Disposition: form-data; name=\"language\"\r\n\r\nen\r\n--bound--\r\n";
+    let req = test::TestRequest::post()
+        .uri("/recognize/file")
+        .insert_header(("content-type", "multipart/form-data; boundary=bound"))
+        .set_payload(body)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+
+    let error = common::read_json(resp).await;
+    assert_eq!(error["error"], "InvalidMultipart");
+    assert_eq!(error["code"], 400);
+}
+
+#[actix_web::test]
+async fn unknown_container_is_left_to_probe() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let app = test::init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let tools = stub_tools("unknown-container", "echo 'Invalid data found' >&2; exit 1", Some("exit 0")).await;
+    let req = common::upload_request("/recognize/file", &[], b"definitely not audio").to_request();
+    let resp = test::call_service(&app, req).await;
+    drop(tools);
+    assert_eq!(resp.status(), 415);
+
+    let error = common::read_json(resp).await;
+    assert_eq!(error["error"], "UnsupportedMedia");
+    assert!(error["message"].as_str().unwrap().contains("Invalid data found"), "{}", error);
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use audio_to_text::jobs;
+use audio_to_text::jobs::config::JobsConfig;
+use audio_to_text::jobs::forms::{Job, JobState};
+use audio_to_text::jobs::store::MemoryJobStore;
+use audio_to_text::jobs::worker::JobQueue;
+use audio_to_text::uploads::file::TempFile;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::fake::FakeEngine;
+use audio_to_text::whisper::forms::RecognizeParameters;
+
+use actix_web::test::{call_service, init_service, TestRequest};
+use actix_web::web;
+
+#[actix_web::test]
+async fn missing_job_is_not_found() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let queue = JobQueue::new(MemoryJobStore::default(), client.clone(), &JobsConfig::default());
+    let app = init_service(
+        common::build_app(client)
+            .app_data(web::Data::new(queue))
+            .service(jobs::build_scope()),
+    )
+    .await;
+
+    for uri in ["/jobs/missing", "/jobs/missing/result"] {
+        let resp = call_service(&app, TestRequest::get().uri(uri).to_request()).await;
+        assert_eq!(resp.status(), 404, "{}", uri);
+
+        let error = common::read_json(resp).await;
+        assert_eq!(error["error"], "JobNotFound");
+    }
+}
+
+#[actix_web::test]
+async fn failed_job_reports_error() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let queue = JobQueue::new(MemoryJobStore::default(), client.clone(), &JobsConfig::default());
+    let app = init_service(
+        common::build_app(client)
+            .app_data(web::Data::new(queue.clone()))
+            .service(jobs::build_scope()),
+    )
+    .await;
+
+    let upload_dir = common::temp_path("jobs-failed");
+    std::fs::create_dir_all(&upload_dir).unwrap();
+    let upload = TempFile::unique(upload_dir.to_str().unwrap(), Some("audio.wav"));
+    std::fs::write(upload.path(), b"RIFF\x10\0\0\0WAVEbroken").unwrap();
+
+    let mut job = Job::new("default", upload.path(), RecognizeParameters::default());
+    queue.submit(&mut job, upload).await.unwrap();
+    let job = common::wait_job(&queue, job.id.as_str()).await;
+    std::fs::remove_dir_all(&upload_dir).unwrap();
+    assert_eq!(job.state, JobState::Failed);
+    assert!(job.error.is_some());
+
+    let uri = format!("/jobs/{}", job.id);
+    let status = common::read_json(call_service(&app, TestRequest::get().uri(uri.as_str()).to_request()).await).await;
+    assert_eq!(status["state"], "failed");
+    assert_eq!(status["error"].as_str(), job.error.as_deref());
+
+    let uri = format!("/jobs/{}/result", job.id);
+    let resp = call_service(&app, TestRequest::get().uri(uri.as_str()).to_request()).await;
+    assert_eq!(resp.status(), 409);
+    assert_eq!(common::read_json(resp).await["error"], "JobFailed");
+}
+
+#[actix_web::test]
+async fn invalid_job_parameters_are_rejected() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let queue = JobQueue::new(MemoryJobStore::default(), client.clone(), &JobsConfig::default());
+    let app = init_service(
+        common::build_app(client)
+            .app_data(web::Data::new(queue))
+            .service(jobs::build_scope()),
+    )
+    .await;
+
+    for uri in ["/jobs?temperature=3", "/jobs?model=large", "/jobs?callback_url=ftp://host/path"] {
+        let req = common::upload_request(uri, &[], &common::wav_bytes(1)).to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 422, "{}", uri);
+    }
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn submitted_job_is_polled_until_result() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default().with_delay(std::time::Duration::from_millis(200)));
+    let queue = JobQueue::new(MemoryJobStore::default(), client.clone(), &JobsConfig::default());
+    let app = init_service(
+        common::build_app(client)
+            .app_data(web::Data::new(queue.clone()))
+            .service(jobs::build_scope()),
+    )
+    .await;
+
+    let req = common::upload_request("/jobs", &[("language", "en")], &common::wav_bytes(2)).to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), 202);
+
+    let location = resp.headers().get("location").unwrap().to_str().unwrap().to_string();
+    let status = common::read_json(resp).await;
+    let job_id = status["id"].as_str().unwrap().to_string();
+    assert_eq!(location, format!("/jobs/{}", job_id));
+    assert_eq!(status["state"], "queued");
+    assert_eq!(status["model"], "default");
+
+    let result_uri = format!("/jobs/{}/result", job_id);
+    let resp = call_service(&app, TestRequest::get().uri(result_uri.as_str()).to_request()).await;
+    assert_eq!(resp.status(), 409);
+    assert_eq!(common::read_json(resp).await["error"], "JobNotReady");
+
+    let job = common::wait_job(&queue, job_id.as_str()).await;
+    assert_eq!(job.state, JobState::Done);
+    std::fs::remove_file(&job.file_path).unwrap();
+
+    let resp = call_service(&app, TestRequest::get().uri(location.as_str()).to_request()).await;
+    assert_eq!(common::read_json(resp).await["state"], "done");
+
+    let resp = call_service(&app, TestRequest::get().uri(result_uri.as_str()).to_request()).await;
+    assert_eq!(resp.status(), 200);
+    let segments = common::read_json(resp).await;
+    assert_eq!(segments.as_array().unwrap().len(), 2);
+    assert_eq!(segments[0]["text"], "Hello");
+
+    let uri = format!("{}?format=srt", result_uri);
+    let resp = call_service(&app, TestRequest::get().uri(uri.as_str()).to_request()).await;
+    let body = actix_web::test::read_body(resp).await;
+    assert!(body.starts_with(b"1\n00:00:00,000 --> 00:00:01,000\nHello\n\n"));
+
+    let uri = format!("{}?concatenate=true", result_uri);
+    let resp = call_service(&app, TestRequest::get().uri(uri.as_str()).to_request()).await;
+    assert_eq!(common::read_json(resp).await["text"], "Hello world");
+}
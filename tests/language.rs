@@ -0,0 +1,102 @@
+mod common;
+
+use audio_to_text::whisper;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::engine::SpeechEngine;
+use audio_to_text::whisper::errors::RecognizeResult;
+use audio_to_text::whisper::forms::{LanguageProbability, RecognizeParameters, RecognizeResponse};
+
+use actix_web::test::{call_service, init_service};
+
+/// Detects three languages, the most probable first.
+struct LanguagesEngine;
+
+impl SpeechEngine for LanguagesEngine {
+    fn recognize(&self, _audio: &[f32], _params: &RecognizeParameters) -> RecognizeResult<Vec<RecognizeResponse>> {
+        Ok(Vec::new())
+    }
+
+    fn detect_language(&self, _audio: &[f32], _threads: i32) -> RecognizeResult<Vec<LanguageProbability>> {
+        let languages = [("de", 0.6), ("en", 0.3), ("fr", 0.1)];
+        let detected = languages
+            .into_iter()
+            .map(|(language, probability)| LanguageProbability {
+                language: language.to_string(),
+                probability,
+            })
+            .collect();
+        Ok(detected)
+    }
+}
+
+#[actix_web::test]
+async fn invalid_detect_parameters_are_rejected() {
+    let client = WhisperAsyncClient::with_engine(LanguagesEngine);
+    let app = init_service(common::build_app(client).service(whisper::build_detect_scope())).await;
+
+    let cases = [
+        ("/detect-language?top_k=0", "top_k must be between 1 and"),
+        ("/detect-language?duration_secs=60", "duration_secs must be between 1 and 30"),
+        ("/detect-language?use_threads=0", "use_threads must be between 1 and"),
+    ];
+    for (uri, message) in cases {
+        let req = common::upload_request(uri, &[], &common::wav_bytes(1)).to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 422, "{}", uri);
+
+        let error = common::read_json(resp).await;
+        assert!(error["message"].as_str().unwrap().contains(message), "{}", error);
+    }
+
+    let req = common::upload_request("/detect-language?model=large", &[], &common::wav_bytes(1)).to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), 422);
+    assert_eq!(common::read_json(resp).await["error"], "UnknownModel");
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn most_probable_languages_are_detected() {
+    let mut client = WhisperAsyncClient::with_engine(LanguagesEngine);
+    client.register_engine("tiny", audio_to_text::whisper::fake::FakeEngine::default());
+    let app = init_service(common::build_app(client).service(whisper::build_detect_scope())).await;
+
+    let req = common::upload_request("/detect-language?top_k=2", &[], &common::wav_bytes(2)).to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    let detected = common::read_json(resp).await;
+    let expected = serde_json::json!({
+        "language": "de",
+        "languages": [
+            {"language": "de", "probability": 0.6},
+            {"language": "en", "probability": 0.3},
+        ],
+        "model": "default",
+    });
+    assert_eq!(detected, expected);
+
+    let req = common::upload_request("/detect-language", &[("model", "tiny")], &common::wav_bytes(2)).to_request();
+    let detected = common::read_json(call_service(&app, req).await).await;
+    assert_eq!(detected["language"], "en");
+    assert_eq!(detected["model"], "tiny");
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn detected_language_is_reported_for_auto_language() {
+    let client = WhisperAsyncClient::with_engine(audio_to_text::whisper::fake::FakeEngine::default());
+    let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let req = common::upload_request("/recognize/file?language=auto", &[], &common::wav_bytes(2)).to_request();
+    let segments = common::read_json(call_service(&app, req).await).await;
+    assert_eq!(segments[0]["language"], "en");
+
+    let req = common::upload_request("/recognize/file?language=auto&concatenate=true", &[], &common::wav_bytes(2)).to_request();
+    let segment = common::read_json(call_service(&app, req).await).await;
+    assert_eq!(segment["language"], "en");
+
+    let req = common::upload_request("/recognize/file?language=de", &[], &common::wav_bytes(2)).to_request();
+    let segments = common::read_json(call_service(&app, req).await).await;
+    assert!(segments[0].get("language").is_none());
+}
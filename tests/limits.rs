@@ -0,0 +1,164 @@
+mod common;
+
+use audio_to_text::jobs;
+use audio_to_text::jobs::config::JobsConfig;
+use audio_to_text::jobs::store::MemoryJobStore;
+use audio_to_text::jobs::worker::JobQueue;
+use audio_to_text::openai;
+use audio_to_text::uploads::config::UploadsConfig;
+use audio_to_text::uploads::media::MediaType;
+use audio_to_text::whisper;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::fake::FakeEngine;
+
+use actix_web::test::{call_service, init_service};
+use actix_web::{web, App};
+use std::path::Path;
+use std::sync::Mutex;
+
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+fn uploads_config(vars: &[(&str, &str)]) -> UploadsConfig {
+    let _guard = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let names = ["UPLOAD_DIR", "UPLOAD_MAX_BYTES", "UPLOAD_ALLOWED_TYPES"];
+    names.iter().for_each(|name| std::env::remove_var(name));
+    vars.iter().for_each(|(name, value)| std::env::set_var(name, value));
+    UploadsConfig::from_env()
+}
+
+fn mp3_bytes() -> Vec<u8> {
+    let mut mp3 = b"ID3\x04\x00\x00\x00\x00\x00\x00".to_vec();
+    mp3.extend([0; 100]);
+    mp3
+}
+
+fn is_empty_dir(dir: &Path) -> bool {
+    std::fs::read_dir(dir).unwrap().next().is_none()
+}
+
+#[test]
+fn containers_are_sniffed_by_signature() {
+    let cases: [(&[u8], Option<MediaType>); 11] = [
+        (b"RIFF\x10\0\0\0WAVEfmt ", Some(MediaType::Wav)),
+        (b"RF64\x10\0\0\0WAVEds64", Some(MediaType::Wav)),
+        (b"FORM\x10\0\0\0AIFFCOMM", Some(MediaType::Aiff)),
+        (b"ID3\x04\0\0\0\0\0\0\0\0", Some(MediaType::Mp3)),
+        (b"\xFF\xFB\x90\x64\0\0\0\0\0\0\0\0", Some(MediaType::Mp3)),
+        (b"\xFF\xF1\x50\x80\0\0\0\0\0\0\0\0", Some(MediaType::Aac)),
+        (b"OggS\0\x02\0\0\0\0\0\0", Some(MediaType::Ogg)),
+        (b"fLaC\0\0\0\x22\0\0\0\0", Some(MediaType::Flac)),
+        (b"\0\0\0\x20ftypM4A ", Some(MediaType::Mp4)),
+        (b"\x1A\x45\xDF\xA3\0\0\0\0\0\0\0\0", Some(MediaType::Webm)),
+        (b"garbage-garbage", None),
+    ];
+    for (header, media_type) in cases {
+        assert_eq!(MediaType::sniff(header), media_type, "{:?}", header);
+    }
+}
+
+#[test]
+fn limits_are_read_from_env() {
+    let default = UploadsConfig::default();
+    assert_eq!(default.get_max_bytes(), 1024 * 1024 * 1024);
+    assert!(MediaType::ALL.iter().all(|media_type| default.is_type_allowed(*media_type)));
+    assert!(default.is_unknown_allowed());
+    assert!(uploads_config(&[]).is_unknown_allowed());
+
+    let cfg = uploads_config(&[("UPLOAD_MAX_BYTES", "1000"), ("UPLOAD_ALLOWED_TYPES", "wav, ogg")]);
+    assert_eq!(cfg.get_max_bytes(), 1000);
+    assert!(cfg.is_type_allowed(MediaType::Wav));
+    assert!(cfg.is_type_allowed(MediaType::Ogg));
+    assert!(!cfg.is_type_allowed(MediaType::Mp3));
+    assert!(!cfg.is_unknown_allowed());
+
+    let unknown_type = std::panic::catch_unwind(|| uploads_config(&[("UPLOAD_ALLOWED_TYPES", "wav,exe")]));
+    assert!(unknown_type.is_err());
+}
+
+#[actix_web::test]
+async fn uploads_exceeding_limits_are_rejected() {
+    let upload_dir = common::temp_path("limits");
+    std::fs::create_dir_all(&upload_dir).unwrap();
+    let vars = [
+        ("UPLOAD_DIR", upload_dir.to_str().unwrap()),
+        ("UPLOAD_MAX_BYTES", "10000"),
+        ("UPLOAD_ALLOWED_TYPES", "wav,ogg"),
+    ];
+    let app = App::new()
+        .app_data(web::Data::new(Box::new(WhisperAsyncClient::with_engine(FakeEngine::default()))))
+        .app_data(web::Data::new(uploads_config(&vars)))
+        .service(whisper::build_scope())
+        .service(whisper::build_detect_scope())
+        .service(openai::build_scope());
+    let app = init_service(app).await;
+
+    let large_field = "a".repeat(65 * 1024);
+    let cases = [
+        ("/recognize/file", vec![], common::wav_bytes(1), 413, "exceeds 10000 bytes"),
+        ("/detect-language", vec![], common::wav_bytes(1), 413, "exceeds 10000 bytes"),
+        ("/recognize/file", vec![], mp3_bytes(), 415, "mp3 files are not allowed"),
+        // Unknown containers are rejected once allowed types are listed.
+        ("/recognize/file", vec![], b"garbage-garbage-garbage".to_vec(), 415, "unknown audio container"),
+        ("/recognize/file", vec![("initial_prompt", large_field.as_str())], Vec::new(), 413, "exceeds 65536 bytes"),
+    ];
+    for (uri, fields, file, status, message) in cases {
+        let req = common::upload_request(uri, &fields, &file).to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), status, "{} {}", uri, message);
+
+        let error = common::read_json(resp).await;
+        assert!(error["message"].as_str().unwrap().contains(message), "{}", error);
+    }
+
+    let req = common::upload_request("/v1/audio/transcriptions", &[], &mp3_bytes()).to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), 415);
+    let error = common::read_json(resp).await;
+    assert_eq!(error["error"]["code"], "UnsupportedMedia");
+
+    // Rejected uploads are not left on disk.
+    assert!(is_empty_dir(upload_dir.as_path()));
+    std::fs::remove_dir_all(&upload_dir).unwrap();
+}
+
+#[actix_web::test]
+async fn long_audio_is_rejected_before_job_is_queued() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default()).with_max_duration(Some(2));
+    let queue = JobQueue::new(MemoryJobStore::default(), client.clone(), &JobsConfig::default());
+    let app = common::build_app(client)
+        .app_data(web::Data::new(queue.clone()))
+        .service(jobs::build_scope());
+    let app = init_service(app).await;
+
+    let req = common::upload_request("/jobs", &[], &common::wav_bytes(3)).to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), 413);
+    let error = common::read_json(resp).await;
+    assert_eq!(error["error"], "PayloadTooLarge");
+    assert!(error["message"].as_str().unwrap().contains("3 seconds exceed 2 seconds"), "{}", error);
+
+    let req = common::upload_request("/jobs", &[], &common::wav_bytes(1)).to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), 202);
+
+    // Audio is kept with the job, so it is removed by the test.
+    let status = common::read_json(resp).await;
+    let job = common::wait_job(&queue, status["id"].as_str().unwrap()).await;
+    std::fs::remove_file(&job.file_path).unwrap();
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn long_audio_is_rejected_before_inference() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default()).with_max_duration(Some(2));
+    let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let req = common::upload_request("/recognize/file", &[], &common::wav_bytes(3)).to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), 413);
+    let error = common::read_json(resp).await;
+    assert!(error["message"].as_str().unwrap().contains("3 seconds exceed 2 seconds"), "{}", error);
+
+    let req = common::upload_request("/recognize/file", &[], &common::wav_bytes(2)).to_request();
+    assert_eq!(call_service(&app, req).await.status(), 200);
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use audio_to_text::whisper;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::config::WhisperClientConfig;
+use audio_to_text::whisper::fake::FakeEngine;
+
+use actix_web::test::{call_service, init_service};
+
+const MODEL_VARS: [&str; 4] = [
+    "WHISPER_MODELS",
+    "WHISPER_MODEL_PATH",
+    "WHISPER_DEFAULT_MODEL",
+    "WHISPER_ENABLE_GPU",
+];
+
+/// Client with the default model and `tiny` one answering with different phrases.
+fn models_client() -> WhisperAsyncClient {
+    let mut client = WhisperAsyncClient::with_engine(FakeEngine::new(vec!["default phrase".to_string()]));
+    client.register_engine("tiny", FakeEngine::new(vec!["tiny phrase".to_string()]));
+    client
+}
+
+fn model_names(cfg: &WhisperClientConfig) -> Vec<(&str, &str)> {
+    cfg.get_models()
+        .iter()
+        .map(|model| (model.get_name(), model.get_model_path()))
+        .collect()
+}
+
+#[test]
+fn models_are_read_from_env() {
+    // Env is process wide, so every case of this binary runs within the single test.
+    std::env::set_var("WHISPER_ENABLE_GPU", "false");
+    std::env::set_var("WHISPER_MODELS", " tiny=./models/tiny.bin, base = ./models/base.bin ,");
+    let cfg = WhisperClientConfig::from_env();
+    assert_eq!(model_names(&cfg), [("tiny", "./models/tiny.bin"), ("base", "./models/base.bin")]);
+    assert_eq!(cfg.get_default_model(), "tiny");
+
+    std::env::set_var("WHISPER_DEFAULT_MODEL", "base");
+    assert_eq!(WhisperClientConfig::from_env().get_default_model(), "base");
+
+    std::env::set_var("WHISPER_DEFAULT_MODEL", "large");
+    assert!(std::panic::catch_unwind(WhisperClientConfig::from_env).is_err());
+
+    std::env::remove_var("WHISPER_DEFAULT_MODEL");
+    std::env::set_var("WHISPER_MODELS", "tiny");
+    assert!(std::panic::catch_unwind(WhisperClientConfig::from_env).is_err());
+
+    // The single model path is registered as the default model.
+    std::env::remove_var("WHISPER_MODELS");
+    std::env::set_var("WHISPER_MODEL_PATH", "./models/ggml-base.en.bin");
+    let cfg = WhisperClientConfig::from_env();
+    assert_eq!(model_names(&cfg), [("default", "./models/ggml-base.en.bin")]);
+    assert_eq!(cfg.get_default_model(), "default");
+
+    for name in MODEL_VARS {
+        std::env::remove_var(name);
+    }
+}
+
+#[actix_web::test]
+async fn unknown_model_is_rejected_by_routes() {
+    let app = common::build_app(models_client())
+        .service(whisper::build_scope())
+        .service(whisper::build_detect_scope());
+    let app = init_service(app).await;
+
+    let cases = [
+        ("/recognize/file?model=large", vec![]),
+        ("/recognize/file", vec![("model", "large")]),
+        ("/detect-language?model=large", vec![]),
+    ];
+    for (uri, fields) in cases {
+        let req = common::upload_request(uri, &fields, &common::wav_bytes(1)).to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 422, "{} {:?}", uri, fields);
+
+        let error = common::read_json(resp).await;
+        assert_eq!(error["error"], "UnknownModel");
+        assert_eq!(error["message"], "Unknown model: large");
+    }
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn model_is_selected_per_request() {
+    let app = common::build_app(models_client())
+        .service(whisper::build_scope())
+        .service(audio_to_text::openai::build_scope());
+    let app = init_service(app).await;
+
+    let cases = [
+        ("/recognize/file", vec![], "default", "default phrase"),
+        ("/recognize/file?model=tiny", vec![], "tiny", "tiny phrase"),
+        ("/recognize/file", vec![("model", "tiny")], "tiny", "tiny phrase"),
+        // Form fields override query ones.
+        ("/recognize/file?model=large", vec![("model", "default")], "default", "default phrase"),
+    ];
+    for (uri, fields, model, text) in cases {
+        let req = common::upload_request(uri, &fields, &common::wav_bytes(1)).to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 200, "{} {:?}", uri, fields);
+
+        let segments = common::read_json(resp).await;
+        assert_eq!(segments[0]["model"], model);
+        assert_eq!(segments[0]["text"], text);
+    }
+
+    // OpenAI clients pass their own model names, unknown ones are served by the default model.
+    let cases = [("tiny", "tiny phrase"), ("whisper-1", "default phrase")];
+    for (model, text) in cases {
+        let req = common::upload_request("/v1/audio/transcriptions", &[("model", model)], &common::wav_bytes(1)).to_request();
+        let transcription = common::read_json(call_service(&app, req).await).await;
+        assert_eq!(transcription["text"], text);
+    }
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use audio_to_text::openai;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::fake::FakeEngine;
+
+use actix_web::test::{call_service, init_service};
+
+#[actix_web::test]
+async fn errors_are_wrapped_into_openai_envelope() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let app = init_service(common::build_app(client).service(openai::build_scope())).await;
+
+    let cases = [
+        (vec![("timestamp_granularities[]", "char"), ("response_format", "verbose_json")], "unsupported timestamp granularity: char"),
+        (vec![("timestamp_granularities[]", "word")], "timestamp_granularities requires verbose_json response format"),
+        (vec![("temperature", "2")], "temperature must be between 0.0 and 1.0"),
+    ];
+    for (fields, message) in cases {
+        let req = common::upload_request("/v1/audio/transcriptions", &fields, &common::wav_bytes(1)).to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+
+        let error = common::read_json(resp).await;
+        assert_eq!(error["error"]["type"], "invalid_request_error");
+        assert_eq!(error["error"]["code"], "InvalidParameters");
+        assert_eq!(error["error"]["param"], serde_json::Value::Null);
+        assert_eq!(error["error"]["message"], format!("Invalid parameters: {}", message));
+    }
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn transcription_is_rendered_in_requested_format() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::new(vec![" Hello".to_string(), " world".to_string()]));
+    let app = init_service(common::build_app(client).service(openai::build_scope())).await;
+
+    // Model names of OpenAI are served by the default model.
+    let fields = [("model", "whisper-1")];
+    let req = common::upload_request("/v1/audio/transcriptions", &fields, &common::wav_bytes(2)).to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(common::read_json(resp).await, serde_json::json!({"text": "Hello world"}));
+
+    let fields = [("response_format", "text")];
+    let req = common::upload_request("/v1/audio/transcriptions", &fields, &common::wav_bytes(2)).to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert!(resp.headers().get("content-type").unwrap().to_str().unwrap().starts_with("text/plain"));
+    assert_eq!(actix_web::test::read_body(resp).await, "Hello world");
+
+    let fields = [("response_format", "srt")];
+    let req = common::upload_request("/v1/audio/transcriptions", &fields, &common::wav_bytes(2)).to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body = actix_web::test::read_body(resp).await;
+    assert!(body.starts_with(b"1\n00:00:00,000 --> 00:00:01,000\nHello\n\n"));
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn verbose_translation_reports_segments_and_words() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::new(vec![" Hello there".to_string(), " world".to_string()]));
+    let app = init_service(common::build_app(client).service(openai::build_scope())).await;
+
+    let fields = [
+        ("response_format", "verbose_json"),
+        ("timestamp_granularities[]", "segment"),
+        ("timestamp_granularities[]", "word"),
+    ];
+    let req = common::upload_request("/v1/audio/translations", &fields, &common::wav_bytes(2)).to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    let transcription = common::read_json(resp).await;
+    assert_eq!(transcription["task"], "translate");
+    assert_eq!(transcription["language"], "en");
+    assert_eq!(transcription["duration"], 2.0);
+    assert_eq!(transcription["text"], "Hello there world");
+    assert_eq!(transcription["segments"].as_array().unwrap().len(), 2);
+    assert_eq!(transcription["segments"][1]["start"], 1.0);
+    assert_eq!(transcription["segments"][1]["end"], 2.0);
+
+    let words = transcription["words"].as_array().unwrap();
+    let words = words.iter().map(|word| word["word"].as_str().unwrap()).collect::<Vec<&str>>();
+    assert_eq!(words, ["Hello", "there", "world"]);
+
+    // Only words are reported if segments are not requested.
+    let fields = [("response_format", "verbose_json"), ("timestamp_granularities[]", "word")];
+    let req = common::upload_request("/v1/audio/transcriptions", &fields, &common::wav_bytes(2)).to_request();
+    let transcription = common::read_json(call_service(&app, req).await).await;
+    assert_eq!(transcription["task"], "transcribe");
+    assert!(transcription.get("segments").is_none());
+    assert_eq!(transcription["words"].as_array().unwrap().len(), 3);
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn verbose_duration_is_length_of_audio() {
+    // Silence is not recognized, so no segment tells where the audio ends.
+    let client = WhisperAsyncClient::with_engine(FakeEngine::new(Vec::new()));
+    let app = init_service(common::build_app(client).service(openai::build_scope())).await;
+
+    let fields = [("response_format", "verbose_json")];
+    let req = common::upload_request("/v1/audio/transcriptions", &fields, &common::wav_bytes(3)).to_request();
+    let transcription = common::read_json(call_service(&app, req).await).await;
+    assert_eq!(transcription["duration"], 3.0);
+    assert_eq!(transcription["text"], "");
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use audio_to_text::whisper;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::engine::SpeechEngine;
+use audio_to_text::whisper::errors::RecognizeResult;
+use audio_to_text::whisper::forms::{LanguageProbability, RecognizeParameters, RecognizeResponse};
+
+use actix_web::test;
+
+/// Answers with parameters it is called with to check what reaches the engine.
+struct ParamsEngine;
+
+impl SpeechEngine for ParamsEngine {
+    fn recognize(&self, _audio: &[f32], params: &RecognizeParameters) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let text = format!(
+            "{} {} {}",
+            params.get_lang().unwrap_or_default(),
+            params.get_temperature(),
+            params.get_initial_prompt().unwrap_or_default(),
+        );
+        Ok(vec![RecognizeResponse { text, ..Default::default() }])
+    }
+
+    fn detect_language(&self, _audio: &[f32], _threads: i32) -> RecognizeResult<Vec<LanguageProbability>> {
+        Ok(Vec::new())
+    }
+}
+
+#[actix_web::test]
+async fn invalid_parameters_are_rejected() {
+    let client = WhisperAsyncClient::with_engine(ParamsEngine);
+    let app = test::init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let cases = [
+        ("/recognize/file?temperature=3", vec![], "temperature must be between 0.0 and 1.0"),
+        ("/recognize/file?language=xx", vec![], "unsupported language: xx"),
+        ("/recognize/file", vec![("beam_size", "100")], "beam_size must be between 1 and 16"),
+        ("/recognize/file?use_threads=0", vec![], "use_threads must be between"),
+        ("/recognize/file?temperature=hot", vec![], "invalid float literal"),
+    ];
+    for (uri, fields, message) in cases {
+        let req = common::upload_request(uri, &fields, &common::wav_bytes(1)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422, "{}", uri);
+
+        let error = common::read_json(resp).await;
+        assert_eq!(error["error"], "InvalidParameters");
+        assert!(error["message"].as_str().unwrap().contains(message), "{}", error);
+    }
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn parameters_reach_engine_and_form_fields_override_query() {
+    let client = WhisperAsyncClient::with_engine(ParamsEngine);
+    let app = test::init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let uri = "/recognize/file?language=de&temperature=3&initial_prompt=query";
+    let fields = [("temperature", "0.5"), ("initial_prompt", "Glossary: whisper")];
+    let req = common::upload_request(uri, &fields, &common::wav_bytes(1)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    let segments = common::read_json(resp).await;
+    assert_eq!(segments[0]["text"], "de 0.5 Glossary: whisper");
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn missing_parameters_fall_back_to_defaults() {
+    let client = WhisperAsyncClient::with_engine(ParamsEngine);
+    let app = test::init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let req = common::upload_request("/recognize/file", &[], &common::wav_bytes(1)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    let segments = common::read_json(resp).await;
+    assert_eq!(segments[0]["text"], "en 0 ");
+}
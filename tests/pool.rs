@@ -0,0 +1,133 @@
+mod common;
+
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::engine::SpeechEngine;
+use audio_to_text::whisper::errors::{RecognizeError, RecognizeResult};
+use audio_to_text::whisper::fake::FakeEngine;
+use audio_to_text::whisper::forms::{LanguageProbability, RecognizeParameters, RecognizeResponse};
+
+use futures_util::future;
+use futures_util::{SinkExt, StreamExt};
+use std::time::{Duration, Instant};
+use tokio_tungstenite::tungstenite::Message;
+
+const DELAY: Duration = Duration::from_millis(300);
+
+/// Recognizes chunks longer than a second slowly, so replies to short
+/// chunks sent later would be ready first.
+struct LengthEngine;
+
+impl SpeechEngine for LengthEngine {
+    fn recognize(&self, audio: &[f32], _params: &RecognizeParameters) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let secs = audio.len() / common::SAMPLE_RATE as usize;
+        if secs > 1 {
+            std::thread::sleep(DELAY);
+        }
+
+        let segment = RecognizeResponse {
+            text: format!("{} seconds", secs),
+            ..Default::default()
+        };
+        Ok(vec![segment])
+    }
+
+    fn detect_language(&self, _audio: &[f32], _threads: i32) -> RecognizeResult<Vec<LanguageProbability>> {
+        Ok(Vec::new())
+    }
+
+    fn capacity(&self) -> usize {
+        2
+    }
+}
+
+fn slow_engine(capacity: usize) -> FakeEngine {
+    FakeEngine::new(vec!["Hello".to_string()])
+        .with_delay(DELAY)
+        .with_capacity(capacity)
+}
+
+async fn recognize_concurrently(client: &WhisperAsyncClient, count: usize) -> Duration {
+    let model = client.get_model(None).unwrap();
+    let params = RecognizeParameters::default();
+    let started = Instant::now();
+    let tasks = (0..count).map(|_| model.recognize_audio(vec![0.0; common::SAMPLE_RATE as usize], &params));
+    let recognized = future::join_all(tasks).await;
+    assert!(recognized.iter().all(Result::is_ok));
+    started.elapsed()
+}
+
+#[actix_web::test]
+async fn recognitions_are_bounded_by_pool_size() {
+    let pooled = WhisperAsyncClient::with_engine(slow_engine(2));
+    let elapsed = recognize_concurrently(&pooled, 2).await;
+    assert!(elapsed < DELAY * 2 - DELAY / 4, "{:?}", elapsed);
+
+    // The third recognition waits for a free state.
+    let elapsed = recognize_concurrently(&pooled, 3).await;
+    assert!(elapsed >= DELAY * 2, "{:?}", elapsed);
+
+    let single = WhisperAsyncClient::with_engine(slow_engine(1));
+    let elapsed = recognize_concurrently(&single, 2).await;
+    assert!(elapsed >= DELAY * 2, "{:?}", elapsed);
+}
+
+#[actix_web::test]
+async fn requests_beyond_queue_are_rejected_as_busy() {
+    let client = WhisperAsyncClient::with_engine(slow_engine(1)).with_queue_size(Some(1));
+    let model = client.get_model(None).unwrap();
+    let params = RecognizeParameters::default();
+
+    let chunk = common::wav_bytes(1);
+    let tasks = (0..3).map(|_| model.recognize_chunk(chunk.clone(), &params));
+    let recognized = future::join_all(tasks).await;
+
+    let busy = recognized
+        .iter()
+        .filter(|result| matches!(result, Err(RecognizeError::EngineBusy(_))))
+        .count();
+    assert_eq!(busy, 1);
+    assert_eq!(recognized.iter().filter(|result| result.is_ok()).count(), 2);
+
+    // Queue is freed once requests are done.
+    assert!(model.recognize_chunk(chunk, &params).await.is_ok());
+}
+
+#[actix_web::test]
+async fn websocket_replies_come_in_order_of_chunks() {
+    let client = WhisperAsyncClient::with_engine(LengthEngine);
+    let (url, server) = common::spawn_ws_server(client);
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(url.as_str()).await.unwrap();
+    socket.send(Message::Binary(common::wav_bytes(2))).await.unwrap();
+    socket.send(Message::Binary(common::wav_bytes(1))).await.unwrap();
+
+    let mut replies = Vec::new();
+    for _ in 0..2 {
+        let reply = socket.next().await.unwrap().unwrap().into_text().unwrap();
+        let segments = serde_json::from_str::<serde_json::Value>(&reply).unwrap();
+        replies.push(segments[0]["text"].as_str().unwrap().to_string());
+    }
+    assert_eq!(replies, ["2 seconds", "1 seconds"]);
+
+    server.stop(false).await;
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn busy_route_asks_to_retry() {
+    use actix_web::test::{call_service, init_service};
+    use audio_to_text::whisper;
+
+    let client = WhisperAsyncClient::with_engine(slow_engine(1)).with_queue_size(Some(0));
+    let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let requests = (0..2).map(|_| {
+        let req = common::upload_request("/recognize/file", &[], &common::wav_bytes(1)).to_request();
+        call_service(&app, req)
+    });
+    let responses = future::join_all(requests).await;
+
+    let busy = responses.iter().find(|resp| resp.status() == 503).unwrap();
+    assert_eq!(busy.headers().get("retry-after").unwrap(), "5");
+    assert!(responses.iter().any(|resp| resp.status() == 200));
+}
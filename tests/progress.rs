@@ -0,0 +1,138 @@
+mod common;
+
+use audio_to_text::jobs;
+use audio_to_text::jobs::config::JobsConfig;
+use audio_to_text::jobs::forms::{Job, JobState};
+use audio_to_text::jobs::progress::{JobProgress, ProgressRegistry, ProgressStage};
+use audio_to_text::jobs::store::MemoryJobStore;
+use audio_to_text::jobs::worker::JobQueue;
+use audio_to_text::uploads::file::TempFile;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::engine::RecognizeObserver;
+use audio_to_text::whisper::fake::FakeEngine;
+use audio_to_text::whisper::forms::RecognizeParameters;
+
+use actix_web::test::{call_service, init_service, read_body, TestRequest};
+use actix_web::web;
+
+/// Parses data of `progress` events from server-sent events body.
+fn progress_events(body: &[u8]) -> Vec<serde_json::Value> {
+    String::from_utf8(body.to_vec())
+        .unwrap()
+        .split_terminator("\n\n")
+        .map(|event| {
+            let data = event.strip_prefix("event: progress\ndata: ").unwrap();
+            serde_json::from_str(data).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn inference_progress_reaches_subscribers_until_finished() {
+    let registry = ProgressRegistry::default();
+    let mut job = Job::new("default", "audio.wav", RecognizeParameters::default());
+    registry.register(&job);
+
+    let receiver = registry.subscribe(job.id.as_str()).unwrap();
+    assert_eq!(receiver.borrow().stage, ProgressStage::Upload);
+    assert_eq!(receiver.borrow().percent, 100);
+
+    job.set_state(JobState::Transcribing);
+    registry.publish(JobProgress::new(&job, ProgressStage::Inference, 0));
+    let observer = registry.observer(&job).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    observer.on_progress(25);
+    {
+        let progress = receiver.borrow();
+        assert_eq!(progress.state, JobState::Transcribing);
+        assert_eq!(progress.percent, 25);
+        // A quarter is done, so three times of elapsed time is left.
+        assert!(progress.eta_secs.unwrap() >= 0.06);
+    }
+
+    // Channel is closed once recognition drops its observer.
+    drop(observer);
+    job.complete(Vec::new());
+    registry.finish(&job);
+    assert_eq!(receiver.borrow().state, JobState::Done);
+    assert_eq!(receiver.borrow().percent, 100);
+    assert!(receiver.has_changed().is_err());
+    assert!(registry.subscribe(job.id.as_str()).is_none());
+}
+
+#[actix_web::test]
+async fn finished_job_sends_its_state_at_once() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let queue = JobQueue::new(MemoryJobStore::default(), client.clone(), &JobsConfig::default());
+    let app = init_service(
+        common::build_app(client)
+            .app_data(web::Data::new(queue.clone()))
+            .service(jobs::build_scope()),
+    )
+    .await;
+
+    let upload_dir = common::temp_path("progress-upload");
+    std::fs::create_dir_all(&upload_dir).unwrap();
+    let upload = TempFile::unique(upload_dir.to_str().unwrap(), Some("audio.wav"));
+    std::fs::write(upload.path(), b"RIFF\x10\0\0\0WAVEbroken").unwrap();
+    let mut job = Job::new("default", upload.path(), RecognizeParameters::default());
+    queue.submit(&mut job, upload).await.unwrap();
+    common::wait_job(&queue, job.id.as_str()).await;
+    std::fs::remove_dir_all(&upload_dir).unwrap();
+
+    let uri = format!("/jobs/{}/events", job.id);
+    let resp = call_service(&app, TestRequest::get().uri(uri.as_str()).to_request()).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers().get("content-type").unwrap(), "text/event-stream");
+
+    let events = progress_events(&read_body(resp).await);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["id"], job.id);
+    assert_eq!(events[0]["state"], "failed");
+
+    let resp = call_service(&app, TestRequest::get().uri("/jobs/missing/events").to_request()).await;
+    assert_eq!(resp.status(), 404);
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn job_progress_is_streamed_until_done() {
+    let engine = FakeEngine::new(vec!["one".to_string(), "two".to_string(), "three".to_string()])
+        .with_delay(std::time::Duration::from_millis(100));
+    let client = WhisperAsyncClient::with_engine(engine);
+    let queue = JobQueue::new(MemoryJobStore::default(), client.clone(), &JobsConfig::default());
+    let app = init_service(
+        common::build_app(client)
+            .app_data(web::Data::new(queue.clone()))
+            .service(jobs::build_scope()),
+    )
+    .await;
+
+    let req = common::upload_request("/jobs", &[], &common::wav_bytes(2)).to_request();
+    let status = common::read_json(call_service(&app, req).await).await;
+    let job_id = status["id"].as_str().unwrap();
+
+    let uri = format!("/jobs/{}/events", job_id);
+    let resp = call_service(&app, TestRequest::get().uri(uri.as_str()).to_request()).await;
+    let events = progress_events(&read_body(resp).await);
+    assert!(events.len() > 1, "{:?}", events);
+    assert!(events.iter().all(|event| event["id"] == job_id));
+    assert!(events
+        .iter()
+        .any(|event| event["state"] == "transcribing" && event["stage"] == "inference"));
+
+    let percents = events
+        .iter()
+        .filter(|event| event["stage"] == "inference")
+        .map(|event| event["percent"].as_i64().unwrap())
+        .collect::<Vec<i64>>();
+    assert!(percents.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", percents);
+
+    let last = events.last().unwrap();
+    assert_eq!(last["state"], "done");
+    assert_eq!(last["percent"], 100);
+
+    // Audio is kept with the job, so it is removed by the test.
+    let job = common::wait_job(&queue, job_id).await;
+    std::fs::remove_file(&job.file_path).unwrap();
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use audio_to_text::whisper;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::fake::FakeEngine;
+use audio_to_text::whisper::forms::{RecognizeResponse, SubtitleParameters};
+use audio_to_text::whisper::layout;
+
+use actix_web::test::{call_service, init_service};
+
+const LONG_TEXT: &str = "This is a rather long whisper segment that definitely should be split \
+    into several subtitle cues because it does not fit into two lines at all";
+
+fn segment(frame_start: i64, frame_end: i64, text: &str) -> RecognizeResponse {
+    RecognizeResponse {
+        frame_start,
+        frame_end,
+        text: text.to_string(),
+        model: "default".to_string(),
+        ..Default::default()
+    }
+}
+
+fn words(segments: &[RecognizeResponse]) -> Vec<String> {
+    segments
+        .iter()
+        .flat_map(|segment| segment.text.split_whitespace().map(str::to_string).collect::<Vec<String>>())
+        .collect()
+}
+
+#[test]
+fn long_segment_is_split_into_cues_within_limits() {
+    let params = serde_urlencoded::from_str::<SubtitleParameters>("max_line_chars=20&max_lines=2").unwrap();
+    let segments = vec![segment(0, 1_500, LONG_TEXT)];
+
+    let cues = layout::reflow(segments.clone(), &params);
+    assert!(cues.len() > 1);
+    assert_eq!(words(&cues), words(&segments));
+    for cue in &cues {
+        let lines = cue.text.lines().collect::<Vec<&str>>();
+        assert!(lines.len() <= 2, "{:?}", lines);
+        assert!(lines.iter().all(|line| line.chars().count() <= 20), "{:?}", lines);
+        assert!(cue.frame_end - cue.frame_start <= 700);
+        assert_eq!(cue.model, "default");
+    }
+
+    let ids = cues.iter().map(|cue| cue.frame_id).collect::<Vec<i32>>();
+    assert_eq!(ids, (0..cues.len() as i32).collect::<Vec<i32>>());
+    assert!(cues.windows(2).all(|pair| pair[0].frame_end <= pair[1].frame_start));
+}
+
+#[test]
+fn short_cues_are_extended_up_to_next_cue() {
+    let params = SubtitleParameters::default();
+    let segments = vec![
+        segment(0, 100, "Reading this cue takes longer than it is shown."),
+        segment(260, 280, "Ok"),
+    ];
+
+    let cues = layout::reflow(segments, &params);
+    let timings = cues.iter().map(|cue| (cue.frame_start, cue.frame_end)).collect::<Vec<(i64, i64)>>();
+    // Reading time is limited by the next cue, short cue lasts minimal duration.
+    assert_eq!(timings, [(0, 260), (260, 360)]);
+}
+
+#[test]
+fn new_sentence_starts_new_cue() {
+    let params = SubtitleParameters::default();
+    let segments = vec![segment(0, 200, "First sentence ends here. Second one")];
+
+    let cues = layout::reflow(segments, &params);
+    let texts = cues.iter().map(|cue| cue.text.as_str()).collect::<Vec<&str>>();
+    assert_eq!(texts, ["First sentence ends here.", "Second one"]);
+}
+
+#[actix_web::test]
+async fn invalid_reflow_parameters_are_rejected() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    for query in ["max_lines=0", "max_line_chars=500", "min_duration_ms=5000&max_duration_ms=1000"] {
+        let uri = format!("/recognize/file?enable_reflow=true&{}", query);
+        let req = common::upload_request(uri.as_str(), &[], &common::wav_bytes(1)).to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 422, "{}", query);
+    }
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn recognize_route_reflows_segments() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::new(vec![LONG_TEXT.to_string()]));
+    let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let uri = "/recognize/file?enable_reflow=true&max_line_chars=20&max_lines=1";
+    let req = common::upload_request(uri, &[], &common::wav_bytes(15)).to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    let cues = common::read_json(resp).await;
+    let cues = cues.as_array().unwrap();
+    assert!(cues.len() > 1);
+    assert!(cues.iter().all(|cue| {
+        let text = cue["text"].as_str().unwrap();
+        !text.contains('\n') && text.chars().count() <= 20
+    }));
+}
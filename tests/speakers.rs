@@ -0,0 +1,142 @@
+mod common;
+
+use audio_to_text::whisper;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::engine::RecognizeObserver;
+use audio_to_text::whisper::fake::FakeEngine;
+use audio_to_text::whisper::forms::{RecognizeParameters, RecognizeResponse, SubtitleParameters};
+use audio_to_text::whisper::{layout, subtitles};
+
+use actix_web::test::{call_service, init_service};
+use actix_web::web;
+use std::sync::{Arc, Mutex};
+
+/// Collects segments passed to observer.
+#[derive(Default)]
+struct SegmentsObserver {
+    segments: Mutex<Vec<RecognizeResponse>>,
+}
+
+impl RecognizeObserver for SegmentsObserver {
+    fn on_segment(&self, segment: &RecognizeResponse) {
+        self.segments.lock().unwrap().push(segment.clone());
+    }
+}
+
+fn dialog_client() -> WhisperAsyncClient {
+    let phrases = ["Hi", "Hello", "How are you"].map(str::to_string).to_vec();
+    WhisperAsyncClient::with_engine(FakeEngine::new(phrases))
+}
+
+fn parse_parameters(query: &str) -> RecognizeParameters {
+    let params = web::Query::<RecognizeParameters>::from_query(query).unwrap().into_inner();
+    params.validate().unwrap();
+    params
+}
+
+async fn recognize(query: &str, secs: usize) -> Vec<RecognizeResponse> {
+    let audio = vec![0.0; secs * common::SAMPLE_RATE as usize];
+    let client = dialog_client();
+    client.get_model(None).unwrap().recognize_audio(audio, &parse_parameters(query)).await.unwrap()
+}
+
+fn speakers(segments: &[RecognizeResponse]) -> Vec<Option<i32>> {
+    segments.iter().map(|segment| segment.speaker).collect()
+}
+
+#[actix_web::test]
+async fn speakers_alternate_on_turns() {
+    let segments = recognize("enable_speaker_turns=true", 3).await;
+    assert_eq!(speakers(&segments), [Some(0), Some(1), Some(0)]);
+    let turns = segments.iter().map(|segment| segment.speaker_turn_next).collect::<Vec<Option<bool>>>();
+    assert_eq!(turns, [Some(true), Some(true), Some(false)]);
+
+    // Zero speakers count labels every turn with a new speaker.
+    let segments = recognize("enable_speaker_turns=true&speakers_count=0", 3).await;
+    assert_eq!(speakers(&segments), [Some(0), Some(1), Some(2)]);
+
+    let segments = recognize("", 3).await;
+    assert_eq!(speakers(&segments), [None, None, None]);
+    assert!(segments.iter().all(|segment| segment.speaker_turn_next.is_none()));
+}
+
+#[actix_web::test]
+async fn observed_segments_are_labeled() {
+    let audio = vec![0.0; 3 * common::SAMPLE_RATE as usize];
+    let params = parse_parameters("enable_speaker_turns=true");
+    let observer = Arc::new(SegmentsObserver::default());
+
+    let client = dialog_client();
+    let model = client.get_model(None).unwrap();
+    let segments = model.recognize_audio_observed(audio, &params, observer.clone()).await.unwrap();
+
+    assert_eq!(speakers(&segments), [Some(0), Some(1), Some(0)]);
+    assert_eq!(speakers(&observer.segments.lock().unwrap()), [Some(0), Some(1), Some(0)]);
+}
+
+#[actix_web::test]
+async fn speakers_are_counted_over_parallel_chunks() {
+    let segments = recognize("enable_speaker_turns=true&enable_parallel=true&chunk_secs=30", 90).await;
+    assert!(segments.len() > 3);
+    assert_eq!(segments[0].speaker, Some(0));
+    for pair in segments.windows(2) {
+        let turn = pair[0].speaker_turn_next == Some(true);
+        let expected = (pair[0].speaker.unwrap() + turn as i32) % 2;
+        assert_eq!(pair[1].speaker, Some(expected));
+    }
+}
+
+#[actix_web::test]
+async fn subtitles_are_labeled_with_speakers() {
+    let segments = recognize("enable_speaker_turns=true", 3).await;
+
+    let srt = subtitles::to_srt(&segments);
+    let expected = "1\n00:00:00,000 --> 00:00:01,000\nSpeaker 0: Hi\n\n\
+        2\n00:00:01,000 --> 00:00:02,000\nSpeaker 1: Hello\n\n\
+        3\n00:00:02,000 --> 00:00:03,000\nSpeaker 0: How are you\n\n";
+    assert_eq!(srt, expected);
+
+    let vtt = subtitles::to_vtt(&segments);
+    assert!(vtt.contains("00:00:01.000 --> 00:00:02.000\n<v Speaker 1>Hello\n"), "{}", vtt);
+
+    // Speakers never share a cue, even if their words would fit in it.
+    let cues = layout::reflow(segments, &SubtitleParameters::default());
+    assert_eq!(speakers(&cues), [Some(0), Some(1), Some(0)]);
+    let texts = cues.iter().map(|cue| cue.text.as_str()).collect::<Vec<&str>>();
+    assert_eq!(texts, ["Hi", "Hello", "How are you"]);
+}
+
+#[actix_web::test]
+async fn invalid_speaker_parameters_are_rejected() {
+    let app = init_service(common::build_app(dialog_client()).service(whisper::build_scope())).await;
+
+    let cases = [
+        ("speakers_count=40", "speakers_count must be between 0 and"),
+        ("enable_speaker_turns=true&channels=split", "enable_speaker_turns requires mix channels mode"),
+    ];
+    for (query, message) in cases {
+        let uri = format!("/recognize/file?{}", query);
+        let req = common::upload_request(&uri, &[], &common::wav_bytes(1)).to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 422, "{}", query);
+
+        let error = common::read_json(resp).await;
+        assert!(error["message"].as_str().unwrap().contains(message), "{}", error);
+    }
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn speakers_are_reported_by_route() {
+    let app = init_service(common::build_app(dialog_client()).service(whisper::build_scope())).await;
+
+    let req = common::upload_request("/recognize/file?enable_speaker_turns=true", &[], &common::wav_bytes(3)).to_request();
+    let segments = common::read_json(call_service(&app, req).await).await;
+    assert_eq!(segments[1]["speaker"], 1);
+    assert_eq!(segments[1]["speaker_turn_next"], true);
+
+    let uri = "/recognize/file?enable_speaker_turns=true&format=srt";
+    let req = common::upload_request(uri, &[], &common::wav_bytes(3)).to_request();
+    let body = actix_web::test::read_body(call_service(&app, req).await).await;
+    assert!(String::from_utf8_lossy(&body).contains("Speaker 1: Hello"));
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use audio_to_text::jobs::config::JobsConfig;
+use audio_to_text::jobs::forms::{Job, JobState};
+use audio_to_text::jobs::store::{JobStore, SqliteJobStore};
+use audio_to_text::jobs::worker::JobQueue;
+use audio_to_text::uploads::file::TempFile;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::fake::FakeEngine;
+use audio_to_text::whisper::forms::{RecognizeParameters, RecognizeResponse};
+
+use std::path::Path;
+
+fn upload(dir: &Path, content: &[u8]) -> TempFile {
+    let upload = TempFile::unique(dir.to_str().unwrap(), Some("audio.wav"));
+    std::fs::write(upload.path(), content).unwrap();
+    upload
+}
+
+#[test]
+fn jobs_survive_reopening_store() {
+    let store_dir = common::temp_path("store-reopen");
+    let store_path = store_dir.to_str().unwrap();
+
+    let mut params = RecognizeParameters::default();
+    params.set_initial_prompt(Some("Glossary".to_string()));
+    let mut done = Job::new("default", "done.wav", params);
+    done.callback_url = Some("http://localhost/callback".to_string());
+    let queued = Job::new("tiny", "queued.wav", RecognizeParameters::default());
+    {
+        let store = SqliteJobStore::open(store_path).unwrap();
+        store.insert(&done).unwrap();
+        store.insert(&queued).unwrap();
+
+        let segment = RecognizeResponse {
+            text: "Hello".to_string(),
+            ..Default::default()
+        };
+        done.complete(vec![segment]);
+        done.callback_attempts = 2;
+        store.update(&done).unwrap();
+    }
+
+    let store = SqliteJobStore::open(store_path).unwrap();
+    let stored = store.get(done.id.as_str()).unwrap().unwrap();
+    assert_eq!(stored.state, JobState::Done);
+    assert_eq!(stored.model, "default");
+    assert_eq!(stored.params.get_initial_prompt(), Some("Glossary"));
+    assert_eq!(stored.callback_url.as_deref(), Some("http://localhost/callback"));
+    assert_eq!(stored.result.unwrap()[0].text, "Hello");
+    assert_eq!(stored.callback_attempts, 2);
+    assert!(!stored.callback_delivered);
+
+    let undelivered = store.list_undelivered().unwrap();
+    assert_eq!(undelivered.len(), 1);
+    assert_eq!(undelivered[0].id, done.id);
+
+    let unfinished = store.list_unfinished().unwrap();
+    assert_eq!(unfinished.len(), 1);
+    assert_eq!(unfinished[0].id, queued.id);
+    assert!(store.get("missing").unwrap().is_none());
+
+    let missing = Job::new("default", "missing.wav", RecognizeParameters::default());
+    assert!(store.update(&missing).is_err());
+
+    std::fs::remove_dir_all(&store_dir).unwrap();
+}
+
+#[test]
+fn audio_is_kept_in_store() {
+    let store_dir = common::temp_path("store-audio");
+    let upload_dir = common::temp_path("store-upload");
+    std::fs::create_dir_all(&upload_dir).unwrap();
+
+    let store = SqliteJobStore::open(store_dir.to_str().unwrap()).unwrap();
+    let upload = upload(&upload_dir, b"audio");
+    let upload_path = upload.path().to_string();
+    let job = Job::new("default", upload.path(), RecognizeParameters::default());
+
+    let kept_path = store.keep_audio(job.id.as_str(), upload).unwrap();
+    assert!(!Path::new(&upload_path).exists());
+    assert!(kept_path.starts_with(store_dir.to_str().unwrap()));
+    assert!(kept_path.ends_with(format!("{}.wav", job.id).as_str()));
+    assert_eq!(std::fs::read(&kept_path).unwrap(), b"audio");
+    assert_eq!(store.audio_dir(), Path::new(&kept_path).parent());
+
+    std::fs::remove_dir_all(&store_dir).unwrap();
+    std::fs::remove_dir_all(&upload_dir).unwrap();
+}
+
+#[actix_web::test]
+async fn unfinished_jobs_are_resumed_and_their_audio_kept() {
+    let store_dir = common::temp_path("store-resume");
+    let upload_dir = common::temp_path("store-resume-upload");
+    std::fs::create_dir_all(&upload_dir).unwrap();
+
+    // Job interrupted by restart while it was transcribed.
+    let mut job = Job::new("default", "", RecognizeParameters::default());
+    {
+        let store = SqliteJobStore::open(store_dir.to_str().unwrap()).unwrap();
+        job.file_path = store
+            .keep_audio(job.id.as_str(), upload(&upload_dir, &common::wav_bytes(2)))
+            .unwrap();
+        job.set_state(JobState::Transcribing);
+        store.insert(&job).unwrap();
+    }
+
+    let store = SqliteJobStore::open(store_dir.to_str().unwrap()).unwrap();
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let queue = JobQueue::new(store, client, &JobsConfig::default());
+    let resumed = common::wait_job(&queue, job.id.as_str()).await;
+
+    // The default build decodes files with ffmpeg, so job may fail without it.
+    if cfg!(feature = "enable-native-decoding") {
+        assert_eq!(resumed.state, JobState::Done);
+        assert_eq!(resumed.result.unwrap().len(), 2);
+    }
+    assert!(queue.list_audio_paths().await.unwrap().is_empty());
+
+    // Audio stays in the store with the finished job.
+    assert!(Path::new(&job.file_path).exists());
+
+    std::fs::remove_dir_all(&store_dir).unwrap();
+    std::fs::remove_dir_all(&upload_dir).unwrap();
+}
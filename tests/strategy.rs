@@ -0,0 +1,131 @@
+mod common;
+
+use audio_to_text::whisper;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::config::DecodingConfig;
+use audio_to_text::whisper::fake::FakeEngine;
+use audio_to_text::whisper::forms::{DecodingStrategy, RecognizeParameters};
+
+use actix_web::test::{call_service, init_service};
+use actix_web::web;
+use std::panic;
+
+const DECODING_VARS: [&str; 7] = [
+    "WHISPER_DECODING_STRATEGY",
+    "WHISPER_BEST_OF",
+    "WHISPER_BEAM_SIZE",
+    "WHISPER_PATIENCE",
+    "WHISPER_TEMPERATURE_INC",
+    "WHISPER_ENTROPY_THOLD",
+    "WHISPER_LOGPROB_THOLD",
+];
+
+fn parse_parameters(query: &str) -> RecognizeParameters {
+    web::Query::<RecognizeParameters>::from_query(query).unwrap().into_inner()
+}
+
+#[test]
+fn decoding_parameters_are_parsed_from_query() {
+    let params = parse_parameters("strategy=beam_search&beam_size=3&patience=1.5&entropy_thold=2.0");
+    assert!(matches!(params.get_strategy(), Some(DecodingStrategy::BeamSearch)));
+    assert_eq!(params.get_beam_size(), Some(3));
+    assert_eq!(params.get_patience(), Some(1.5));
+    assert_eq!(params.get_entropy_thold(), Some(2.0));
+    assert!(params.validate().is_ok());
+
+    let params = parse_parameters("strategy=greedy&best_of=4&temperature_inc=0");
+    assert!(matches!(params.get_strategy(), Some(DecodingStrategy::Greedy)));
+    assert_eq!(params.get_best_of(), Some(4));
+    assert_eq!(params.get_temperature_inc(), Some(0.0));
+
+    // Missing values are taken from server defaults.
+    let params = parse_parameters("");
+    assert!(params.get_strategy().is_none());
+    assert!(params.get_beam_size().is_none());
+    assert!(params.get_logprob_thold().is_none());
+}
+
+#[test]
+fn server_defaults_are_read_from_env() {
+    let default = DecodingConfig::default();
+    assert!(matches!(default.get_strategy(), DecodingStrategy::Greedy));
+    assert_eq!(default.get_best_of(), 1);
+    assert_eq!(default.get_beam_size(), 5);
+    assert_eq!(default.get_patience(), -1.0);
+    assert_eq!(default.get_temperature_inc(), 0.2);
+    assert_eq!(default.get_entropy_thold(), 2.4);
+    assert_eq!(default.get_logprob_thold(), -1.0);
+
+    // Env is process wide, so every case of this binary runs within the single test.
+    let vars = [
+        ("WHISPER_DECODING_STRATEGY", "beam_search"),
+        ("WHISPER_BEAM_SIZE", "8"),
+        ("WHISPER_PATIENCE", "2"),
+        ("WHISPER_TEMPERATURE_INC", "0.4"),
+        ("WHISPER_LOGPROB_THOLD", "-0.5"),
+    ];
+    for (name, value) in vars {
+        std::env::set_var(name, value);
+    }
+
+    let decoding = DecodingConfig::from_env();
+    assert!(matches!(decoding.get_strategy(), DecodingStrategy::BeamSearch));
+    assert_eq!(decoding.get_best_of(), 1);
+    assert_eq!(decoding.get_beam_size(), 8);
+    assert_eq!(decoding.get_patience(), 2.0);
+    assert_eq!(decoding.get_temperature_inc(), 0.4);
+    assert_eq!(decoding.get_entropy_thold(), 2.4);
+    assert_eq!(decoding.get_logprob_thold(), -0.5);
+
+    std::env::set_var("WHISPER_DECODING_STRATEGY", "sampling");
+    assert!(panic::catch_unwind(DecodingConfig::from_env).is_err());
+
+    std::env::set_var("WHISPER_DECODING_STRATEGY", "greedy");
+    std::env::set_var("WHISPER_BEAM_SIZE", "100");
+    assert!(panic::catch_unwind(DecodingConfig::from_env).is_err());
+
+    for name in DECODING_VARS {
+        std::env::remove_var(name);
+    }
+}
+
+#[actix_web::test]
+async fn invalid_decoding_parameters_are_rejected() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let cases = [
+        ("best_of=0", "best_of must be between 1 and"),
+        ("strategy=beam_search&beam_size=100", "beam_size must be between 1 and"),
+        ("strategy=beam_search&patience=0", "patience must be positive or -1 to disable"),
+        ("temperature_inc=2", "temperature_inc must be between 0.0 and 1.0"),
+        ("entropy_thold=0", "entropy_thold must be positive"),
+        ("logprob_thold=1", "logprob_thold must not be positive"),
+        ("strategy=sampling", "unknown variant `sampling`"),
+    ];
+    for (query, message) in cases {
+        let uri = format!("/recognize/file?{}", query);
+        let req = common::upload_request(&uri, &[], &common::wav_bytes(1)).to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 422, "{}", query);
+
+        let error = common::read_json(resp).await;
+        assert_eq!(error["error"], "InvalidParameters");
+        assert!(error["message"].as_str().unwrap().contains(message), "{}", error);
+    }
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn beam_search_request_is_recognized() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let uri = "/recognize/file?strategy=beam_search&beam_size=3&patience=1.5&temperature_inc=0";
+    let req = common::upload_request(uri, &[("entropy_thold", "2.0")], &common::wav_bytes(2)).to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    let segments = common::read_json(resp).await;
+    assert_eq!(segments[0]["text"], "Hello");
+}
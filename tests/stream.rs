@@ -0,0 +1,96 @@
+mod common;
+
+use audio_to_text::whisper;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::fake::FakeEngine;
+
+use actix_web::test::{call_service, init_service};
+
+#[actix_web::test]
+async fn stream_rejects_buffered_options() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let queries = [
+        "format=srt",
+        "concatenate=true",
+        "enable_reflow=true",
+        "channels=split",
+        "enable_parallel=true",
+    ];
+    for query in queries {
+        let uri = format!("/recognize/file?stream=true&{}", query);
+        let req = common::upload_request(uri.as_str(), &[], &common::wav_bytes(1)).to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 422, "{}", query);
+
+        let error = common::read_json(resp).await;
+        let message = error["message"].as_str().unwrap();
+        assert!(message.contains("stream supports json format"), "{}", message);
+    }
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn segments_are_streamed_as_lines_or_events() {
+    use actix_web::test::read_body;
+
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let req = common::upload_request("/recognize/file?stream=true", &[], &common::wav_bytes(2)).to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers().get("content-type").unwrap(), "application/x-ndjson");
+
+    let body = String::from_utf8(read_body(resp).await.to_vec()).unwrap();
+    let segments = body
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<serde_json::Value>>();
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[0]["text"], "Hello");
+    assert_eq!(segments[1]["frame_id"], 1);
+
+    let req = common::upload_request("/recognize/file?stream=true", &[], &common::wav_bytes(2))
+        .insert_header(("accept", "text/event-stream"))
+        .to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.headers().get("content-type").unwrap(), "text/event-stream");
+
+    let body = String::from_utf8(read_body(resp).await.to_vec()).unwrap();
+    let events = body.split_terminator("\n\n").collect::<Vec<&str>>();
+    assert_eq!(events.len(), 2);
+    assert!(events[1].starts_with("event: segment\ndata: {\"frame_id\":1,"), "{}", events[1]);
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn recognition_is_aborted_once_receiver_is_dropped() {
+    use audio_to_text::whisper::forms::RecognizeParameters;
+    use std::time::{Duration, Instant};
+
+    let file_path = common::temp_path("stream-cancel").with_extension("wav");
+    std::fs::write(&file_path, common::wav_bytes(2)).unwrap();
+    let file_path = file_path.to_str().unwrap();
+
+    // Recognition of all phrases takes a second.
+    let phrases = (0..20).map(|id| format!("phrase {}", id)).collect();
+    let client = WhisperAsyncClient::with_engine(FakeEngine::new(phrases).with_delay(Duration::from_millis(50)));
+    let model = client.get_model(None).unwrap();
+    let params = RecognizeParameters::default();
+
+    let mut receiver = model.stream_file(file_path, &params).await.unwrap();
+    let first = receiver.recv().await.unwrap().unwrap();
+    assert_eq!(first.text, "phrase 0");
+    drop(receiver);
+
+    // The next recognition waits for the engine until the cancelled one stops.
+    let started_at = Instant::now();
+    let recognized = model.recognize_file(file_path, &params).await.unwrap();
+    let elapsed = started_at.elapsed();
+    std::fs::remove_file(file_path).unwrap();
+
+    assert_eq!(recognized.len(), 20);
+    assert!(elapsed < Duration::from_millis(1_500), "{:?}", elapsed);
+}
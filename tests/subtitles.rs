@@ -0,0 +1,87 @@
+mod common;
+
+use audio_to_text::whisper;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::fake::FakeEngine;
+use audio_to_text::whisper::forms::RecognizeResponse;
+use audio_to_text::whisper::subtitles;
+
+use actix_web::test::{call_service, init_service};
+
+fn segment(frame_start: i64, frame_end: i64, text: &str) -> RecognizeResponse {
+    RecognizeResponse {
+        frame_start,
+        frame_end,
+        text: text.to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn srt_numbers_cues_and_skips_silence() {
+    let segments = [
+        segment(0, 150, " Hello there"),
+        segment(150, 200, "  "),
+        segment(366_012, 366_250, "world"),
+    ];
+
+    let expected = "1\n00:00:00,000 --> 00:00:01,500\nHello there\n\n\
+        2\n01:01:00,120 --> 01:01:02,500\nworld\n\n";
+    assert_eq!(subtitles::to_srt(&segments), expected);
+}
+
+#[test]
+fn vtt_escapes_timings_separator() {
+    let segments = [segment(5, 40, "big --> world")];
+
+    let expected = "WEBVTT\n\n00:00:00.050 --> 00:00:00.400\nbig --&gt; world\n\n";
+    assert_eq!(subtitles::to_vtt(&segments), expected);
+}
+
+#[test]
+fn vtt_escapes_tags_and_entities() {
+    let segments = [segment(5, 40, "a <b> & c > d")];
+
+    let expected = "WEBVTT\n\n00:00:00.050 --> 00:00:00.400\na &lt;b&gt; &amp; c &gt; d\n\n";
+    assert_eq!(subtitles::to_vtt(&segments), expected);
+    assert_eq!(subtitles::to_vtt_karaoke(&segments), expected);
+}
+
+#[test]
+fn empty_recognition_renders_empty_subtitles() {
+    assert_eq!(subtitles::to_srt(&[]), "");
+    assert_eq!(subtitles::to_vtt(&[]), "WEBVTT\n\n");
+}
+
+#[cfg(feature = "enable-native-decoding")]
+#[actix_web::test]
+async fn recognize_route_renders_requested_format() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let cases = [
+        ("srt", subtitles::SRT_CONTENT_TYPE, "1\n00:00:00,000 --> 00:00:01,000\nHello\n\n"),
+        ("vtt", subtitles::VTT_CONTENT_TYPE, "WEBVTT\n\n00:00:00.000 --> 00:00:01.000\nHello\n\n"),
+    ];
+    for (format, content_type, prefix) in cases {
+        let uri = format!("/recognize/file?format={}", format);
+        let req = common::upload_request(uri.as_str(), &[], &common::wav_bytes(2)).to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("content-type").unwrap(), content_type);
+
+        let body = String::from_utf8(actix_web::test::read_body(resp).await.to_vec()).unwrap();
+        assert!(body.starts_with(prefix), "{}", body);
+        assert!(body.contains("world"));
+    }
+}
+
+#[actix_web::test]
+async fn unknown_format_is_rejected() {
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let req = common::upload_request("/recognize/file?format=xml", &[], &common::wav_bytes(1)).to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), 422);
+}
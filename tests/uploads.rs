@@ -0,0 +1,288 @@
+mod common;
+
+use audio_to_text::jobs::config::JobsConfig;
+use audio_to_text::jobs::forms::Job;
+use audio_to_text::jobs::store::{JobStore, SqliteJobStore};
+use audio_to_text::jobs::worker::JobQueue;
+use audio_to_text::uploads::config::UploadsConfig;
+use audio_to_text::uploads::file::TempFile;
+use audio_to_text::uploads::sweeper;
+use audio_to_text::whisper;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::engine::SpeechEngine;
+use audio_to_text::whisper::errors::RecognizeResult;
+use audio_to_text::whisper::fake::FakeEngine;
+use audio_to_text::whisper::forms::{LanguageProbability, RecognizeParameters, RecognizeResponse};
+
+use actix_web::test::{call_service, init_service, TestRequest};
+use actix_web::{web, App};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+/// Recognizes names of files which are in upload dir while audio is recognized.
+struct UploadsEngine {
+    upload_dir: PathBuf,
+}
+
+impl SpeechEngine for UploadsEngine {
+    fn recognize(&self, _audio: &[f32], _params: &RecognizeParameters) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let segments = file_names(self.upload_dir.as_path())
+            .into_iter()
+            .map(|text| RecognizeResponse {
+                text,
+                ..Default::default()
+            })
+            .collect();
+        Ok(segments)
+    }
+
+    fn detect_language(&self, _audio: &[f32], _threads: i32) -> RecognizeResult<Vec<LanguageProbability>> {
+        Ok(Vec::new())
+    }
+}
+
+fn jobs_config(audio_retention_secs: &str) -> JobsConfig {
+    let _guard = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    std::env::set_var("JOBS_AUDIO_RETENTION_SECS", audio_retention_secs);
+    let cfg = JobsConfig::from_env();
+    std::env::remove_var("JOBS_AUDIO_RETENTION_SECS");
+    cfg
+}
+
+fn uploads_config(vars: &[(&str, &str)]) -> UploadsConfig {
+    let _guard = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let names = [
+        "UPLOAD_DIR",
+        "UPLOAD_MAX_AGE_SECS",
+        "UPLOAD_SWEEP_INTERVAL_SECS",
+        "UPLOAD_MAX_BYTES",
+        "UPLOAD_ALLOWED_TYPES",
+    ];
+    names.iter().for_each(|name| std::env::remove_var(name));
+    vars.iter().for_each(|(name, value)| std::env::set_var(name, value));
+    UploadsConfig::from_env()
+}
+
+fn file_names(dir: &Path) -> Vec<String> {
+    let mut names = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect::<Vec<String>>();
+    names.sort();
+    names
+}
+
+fn touch(path: &Path) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, b"audio").unwrap();
+}
+
+#[test]
+fn uploads_get_unique_sanitized_paths() {
+    let first = TempFile::unique("/srv/upload", Some("meeting.WAV"));
+    let second = TempFile::unique("/srv/upload", Some("meeting.WAV"));
+    assert_ne!(first.path(), second.path());
+
+    let cases = [
+        (Some("meeting.WAV"), ".wav"),
+        (Some("../../etc/passwd"), ""),
+        (Some("../../evil.mp3"), ".mp3"),
+        (Some("audio.php;rm -rf"), ""),
+        (Some("audio.verylongextension"), ""),
+        (Some("archive.tar.gz"), ".gz"),
+        (None, ""),
+    ];
+    for (filename, extension) in cases {
+        let upload = TempFile::unique("/srv/upload", filename);
+        let path = Path::new(upload.path());
+        assert_eq!(path.parent(), Some(Path::new("/srv/upload")), "{:?}", filename);
+
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        let (id, rest) = file_name.split_at(36);
+        assert!(uuid::Uuid::parse_str(id).is_ok(), "{}", file_name);
+        assert_eq!(rest, extension, "{:?}", filename);
+    }
+}
+
+#[test]
+fn temp_file_is_removed_unless_kept() {
+    let dir = common::temp_path("uploads-temp");
+    let upload = TempFile::unique(dir.to_str().unwrap(), Some("audio.wav"));
+    touch(Path::new(upload.path()));
+    let upload_path = PathBuf::from(upload.path());
+    drop(upload);
+    assert!(!upload_path.exists());
+
+    let upload = TempFile::unique(dir.to_str().unwrap(), Some("audio.wav"));
+    touch(Path::new(upload.path()));
+    let kept_path = PathBuf::from(upload.keep().unwrap());
+    assert_eq!(kept_path.parent(), Some(dir.join("kept").as_path()));
+    assert!(kept_path.exists());
+    assert_eq!(file_names(dir.as_path()), ["kept"]);
+
+    // Missing files are dropped quietly.
+    drop(TempFile::new(dir.join("missing.wav")));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn uploads_config_is_read_from_env() {
+    let default = UploadsConfig::default();
+    assert_eq!(default.get_upload_dir(), "./upload");
+    assert_eq!(default.get_max_age_secs(), 24 * 60 * 60);
+    assert_eq!(default.get_sweep_interval_secs(), 60 * 60);
+
+    let vars = [
+        ("UPLOAD_DIR", "/srv/upload"),
+        ("UPLOAD_MAX_AGE_SECS", "600"),
+        ("UPLOAD_SWEEP_INTERVAL_SECS", "30"),
+    ];
+    let cfg = uploads_config(&vars);
+    assert_eq!(cfg.get_upload_dir(), "/srv/upload");
+    assert_eq!(cfg.get_max_age_secs(), 600);
+    assert_eq!(cfg.get_sweep_interval_secs(), 30);
+
+    let zero_interval = std::panic::catch_unwind(|| uploads_config(&[("UPLOAD_SWEEP_INTERVAL_SECS", "0")]));
+    assert!(zero_interval.is_err());
+}
+
+#[actix_web::test]
+async fn upload_is_removed_after_request() {
+    let upload_dir = common::temp_path("uploads-request");
+    std::fs::create_dir_all(&upload_dir).unwrap();
+    let cfg = uploads_config(&[("UPLOAD_DIR", upload_dir.to_str().unwrap())]);
+
+    let engine = UploadsEngine {
+        upload_dir: upload_dir.clone(),
+    };
+    let app = App::new()
+        .app_data(web::Data::new(Box::new(WhisperAsyncClient::with_engine(engine))))
+        .app_data(web::Data::new(cfg))
+        .service(whisper::build_scope());
+    let app = init_service(app).await;
+
+    // Rejected and failed requests remove the upload as well as successful ones.
+    let req = common::upload_request("/recognize/file?temperature=3", &[], &common::wav_bytes(1)).to_request();
+    assert_eq!(call_service(&app, req).await.status(), 422);
+    let req = common::upload_request("/recognize/file", &[], b"RIFF\x10\0\0\0WAVEbroken").to_request();
+    assert!(!call_service(&app, req).await.status().is_success());
+    assert!(file_names(upload_dir.as_path()).is_empty());
+
+    // Client filename is not used for the upload path.
+    let boundary = "audio-to-text-boundary";
+    let mut body = format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"../../evil.wav\"\r\n\
+        Content-Type: audio/wav\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend(common::wav_bytes(1));
+    body.extend(format!("\r\n--{boundary}--\r\n").as_bytes());
+    let req = TestRequest::post()
+        .uri("/recognize/file")
+        .insert_header(("content-type", format!("multipart/form-data; boundary={boundary}")))
+        .set_payload(body)
+        .to_request();
+    let resp = call_service(&app, req).await;
+
+    #[cfg(feature = "enable-native-decoding")]
+    {
+        assert_eq!(resp.status(), 200);
+        let segments = common::read_json(resp).await;
+        let uploaded = segments[0]["text"].as_str().unwrap();
+        assert!(uploaded.ends_with(".wav") && !uploaded.contains("evil"), "{}", uploaded);
+    }
+    #[cfg(not(feature = "enable-native-decoding"))]
+    drop(resp);
+
+    assert!(file_names(upload_dir.as_path()).is_empty());
+    assert!(!upload_dir.parent().unwrap().join("evil.wav").exists());
+
+    std::fs::remove_dir_all(&upload_dir).unwrap();
+}
+
+#[actix_web::test]
+async fn sweeper_removes_orphaned_files() {
+    let upload_dir = common::temp_path("uploads-sweep");
+    let upload_path = upload_dir.to_str().unwrap();
+
+    // Job is stored by another process, so it stays unfinished and refers to its kept audio.
+    let store_dir = common::temp_path("uploads-sweep-store");
+    let store_path = store_dir.to_str().unwrap();
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let queue = JobQueue::new(SqliteJobStore::open(store_path).unwrap(), client, &JobsConfig::default());
+    let used_kept = upload_dir.join("kept").join("used.wav");
+    let job = Job::new("default", used_kept.to_str().unwrap(), RecognizeParameters::default());
+    SqliteJobStore::open(store_path).unwrap().insert(&job).unwrap();
+
+    let orphaned_upload = upload_dir.join("orphaned.wav");
+    let hidden = upload_dir.join(".keep");
+    let nested = upload_dir.join("nested").join("audio.wav");
+    let orphaned_kept = upload_dir.join("kept").join("orphaned.wav");
+    let orphaned_temp = std::env::temp_dir().join(format!("audio-to-text-{}.wav", uuid::Uuid::new_v4()));
+    let foreign_temp = common::temp_path("foreign").with_extension("wav");
+    for path in [&used_kept, &orphaned_upload, &hidden, &nested, &orphaned_kept, &orphaned_temp, &foreign_temp] {
+        touch(path);
+    }
+
+    let interval = [("UPLOAD_DIR", upload_path), ("UPLOAD_SWEEP_INTERVAL_SECS", "1")];
+    sweeper::spawn_sweeper(&uploads_config(&interval), queue.clone());
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    // Files are younger than the default max age.
+    assert!(orphaned_upload.exists() && orphaned_kept.exists() && orphaned_temp.exists());
+
+    let max_age = [interval[0], interval[1], ("UPLOAD_MAX_AGE_SECS", "0")];
+    sweeper::spawn_sweeper(&uploads_config(&max_age), queue.clone());
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    assert!(!orphaned_upload.exists());
+    assert!(!orphaned_kept.exists());
+    assert!(!orphaned_temp.exists());
+    assert!(hidden.exists() && nested.exists() && foreign_temp.exists());
+    assert!(used_kept.exists());
+
+    std::fs::remove_file(&foreign_temp).unwrap();
+    std::fs::remove_dir_all(&upload_dir).unwrap();
+    std::fs::remove_dir_all(&store_dir).unwrap();
+}
+
+#[actix_web::test]
+async fn sweeper_removes_audio_of_finished_jobs_after_retention() {
+    let upload_dir = common::temp_path("uploads-retention");
+    std::fs::create_dir_all(&upload_dir).unwrap();
+    let store_dir = common::temp_path("uploads-retention-store");
+    let store_path = store_dir.to_str().unwrap();
+
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let kept_queue = JobQueue::new(SqliteJobStore::open(store_path).unwrap(), client.clone(), &JobsConfig::default());
+    let queue = JobQueue::new(SqliteJobStore::open(store_path).unwrap(), client, &jobs_config("0"));
+    let audio_dir = queue.get_audio_dir().unwrap();
+    let used_audio = audio_dir.join("used.wav");
+    let job = Job::new("default", used_audio.to_str().unwrap(), RecognizeParameters::default());
+    SqliteJobStore::open(store_path).unwrap().insert(&job).unwrap();
+
+    let finished_audio = audio_dir.join("finished.wav");
+    touch(&used_audio);
+    touch(&finished_audio);
+
+    let max_age = [
+        ("UPLOAD_DIR", upload_dir.to_str().unwrap()),
+        ("UPLOAD_SWEEP_INTERVAL_SECS", "1"),
+        ("UPLOAD_MAX_AGE_SECS", "0"),
+    ];
+    // Audio is kept as long as its job without retention.
+    sweeper::spawn_sweeper(&uploads_config(&max_age), kept_queue);
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(finished_audio.exists());
+
+    sweeper::spawn_sweeper(&uploads_config(&max_age), queue);
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(!finished_audio.exists());
+    assert!(used_audio.exists());
+
+    std::fs::remove_dir_all(&upload_dir).unwrap();
+    std::fs::remove_dir_all(&store_dir).unwrap();
+}
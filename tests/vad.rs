@@ -0,0 +1,154 @@
+mod common;
+
+use audio_to_text::whisper;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::engine::SpeechEngine;
+use audio_to_text::whisper::errors::RecognizeResult;
+use audio_to_text::whisper::forms::{LanguageProbability, RecognizeParameters, RecognizeResponse};
+
+use actix_web::test::{call_service, init_service};
+use actix_web::web;
+
+/// Recognizes the whole passed audio as one segment, so segments show regions whisper is fed with.
+struct RegionEngine;
+
+impl SpeechEngine for RegionEngine {
+    fn recognize(&self, audio: &[f32], _params: &RecognizeParameters) -> RecognizeResult<Vec<RecognizeResponse>> {
+        let segment = RecognizeResponse {
+            frame_end: (audio.len() * 100 / common::SAMPLE_RATE as usize) as i64,
+            text: format!("{} samples", audio.len()),
+            ..Default::default()
+        };
+        Ok(vec![segment])
+    }
+
+    fn detect_language(&self, _audio: &[f32], _threads: i32) -> RecognizeResult<Vec<LanguageProbability>> {
+        Ok(Vec::new())
+    }
+}
+
+/// 16 kHz mono audio of tone parts and low noise parts of the given seconds.
+fn speech_pattern(parts: &[(f32, bool)]) -> Vec<f32> {
+    let mut audio = Vec::new();
+    for (secs, speech) in parts {
+        for _ in 0..(secs * common::SAMPLE_RATE as f32) as usize {
+            let sample = audio.len() as u64;
+            let value = match speech {
+                true => (sample as f32 * 0.05).sin() * 0.25,
+                false => ((sample * 7919 % 200) as f32 - 100.0) * 0.000_002,
+            };
+            audio.push(value);
+        }
+    }
+
+    audio
+}
+
+fn parse_parameters(query: &str) -> RecognizeParameters {
+    web::Query::<RecognizeParameters>::from_query(query).unwrap().into_inner()
+}
+
+async fn recognize(audio: Vec<f32>, query: &str) -> Vec<RecognizeResponse> {
+    let client = WhisperAsyncClient::with_engine(RegionEngine);
+    let params = parse_parameters(query);
+    params.validate().unwrap();
+    client.get_model(None).unwrap().recognize_audio(audio, &params).await.unwrap()
+}
+
+fn bounds(segments: &[RecognizeResponse]) -> Vec<(i64, i64)> {
+    segments
+        .iter()
+        .map(|segment| (segment.frame_start, segment.frame_end))
+        .collect()
+}
+
+fn assert_near(actual: (i64, i64), expected: (i64, i64)) {
+    let near = (actual.0 - expected.0).abs() <= 5 && (actual.1 - expected.1).abs() <= 5;
+    assert!(near, "{:?} is not near {:?}", actual, expected);
+}
+
+#[actix_web::test]
+async fn only_speech_regions_are_recognized() {
+    let audio = speech_pattern(&[(3.0, false), (2.0, true), (4.0, false), (1.0, true), (2.0, false)]);
+
+    let segments = recognize(audio.clone(), "enable_vad=true&enable_speech_regions=true").await;
+    let regions = bounds(&segments);
+    assert_eq!(regions.len(), 2, "{:?}", regions);
+    // Regions are padded by 200 ms and segments are moved onto the original timeline.
+    assert_near(regions[0], (280, 520));
+    assert_near(regions[1], (880, 1020));
+
+    let ids = segments.iter().map(|segment| segment.frame_id).collect::<Vec<i32>>();
+    assert_eq!(ids, [0, 1]);
+    for segment in &segments {
+        let region = segment.speech_region.unwrap();
+        assert_eq!((region.start, region.end), (segment.frame_start, segment.frame_end));
+    }
+
+    let segments = recognize(audio.clone(), "enable_vad=true").await;
+    assert_eq!(segments.len(), 2);
+    assert!(segments.iter().all(|segment| segment.speech_region.is_none()));
+
+    // Without VAD the whole audio including silence is recognized.
+    let segments = recognize(audio, "").await;
+    assert_eq!(bounds(&segments), [(0, 1200)]);
+}
+
+#[actix_web::test]
+async fn short_pauses_are_bridged_and_offset_is_applied() {
+    let audio = speech_pattern(&[(1.0, false), (1.0, true), (0.3, false), (1.0, true), (1.0, false)]);
+    let segments = recognize(audio.clone(), "enable_vad=true").await;
+    assert_eq!(segments.len(), 1);
+    assert_near(bounds(&segments)[0], (80, 350));
+
+    let segments = recognize(audio.clone(), "enable_vad=true&vad_min_silence_ms=100&vad_speech_pad_ms=0").await;
+    assert_eq!(segments.len(), 2);
+    assert_near(bounds(&segments)[1], (230, 330));
+
+    // Regions are detected and padded within requested part, timestamps stay on the original timeline.
+    let segments = recognize(audio, "enable_vad=true&enable_speech_regions=true&offset_ms=2200").await;
+    assert_eq!(segments.len(), 1);
+    assert_near(bounds(&segments)[0], (220, 350));
+}
+
+#[actix_web::test]
+async fn long_speech_is_split_into_whisper_windows() {
+    let audio = speech_pattern(&[(75.0, true)]);
+    let segments = recognize(audio, "enable_vad=true").await;
+
+    let windows = bounds(&segments);
+    assert!(windows.len() >= 3, "{:?}", windows);
+    assert!(windows.iter().all(|(start, end)| end - start <= 3000), "{:?}", windows);
+    assert_eq!(windows.first().unwrap().0, 0);
+    assert_eq!(windows.last().unwrap().1, 7500);
+    assert!(windows.windows(2).all(|pair| pair[0].1 <= pair[1].0), "{:?}", windows);
+}
+
+#[actix_web::test]
+async fn silence_is_not_recognized() {
+    let audio = speech_pattern(&[(5.0, false)]);
+    let segments = recognize(audio, "enable_vad=true").await;
+    assert!(segments.is_empty());
+}
+
+#[actix_web::test]
+async fn invalid_vad_parameters_are_rejected() {
+    let client = WhisperAsyncClient::with_engine(RegionEngine);
+    let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+    let cases = [
+        ("enable_speech_regions=true", "enable_speech_regions requires enable_vad"),
+        ("enable_vad=true&vad_threshold_db=100", "vad_threshold_db must be between 0.0 and"),
+        ("enable_vad=true&vad_min_silence_ms=-1", "vad_min_silence_ms must be between 0 and"),
+        ("enable_vad=true&vad_speech_pad_ms=100000", "vad_speech_pad_ms must be between 0 and"),
+    ];
+    for (query, message) in cases {
+        let uri = format!("/recognize/file?{}", query);
+        let req = common::upload_request(&uri, &[], &common::wav_bytes(1)).to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 422, "{}", query);
+
+        let error = common::read_json(resp).await;
+        assert!(error["message"].as_str().unwrap().contains(message), "{}", error);
+    }
+}
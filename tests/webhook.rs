@@ -0,0 +1,291 @@
+mod common;
+
+use audio_to_text::jobs::config::JobsConfig;
+use audio_to_text::jobs::forms::{Job, JobState};
+use audio_to_text::jobs::store::{JobStore, MemoryJobStore, SqliteJobStore};
+use audio_to_text::jobs::webhook::WebhookNotifier;
+use audio_to_text::jobs::worker::JobQueue;
+use audio_to_text::uploads::file::TempFile;
+use audio_to_text::whisper::client_async::WhisperAsyncClient;
+use audio_to_text::whisper::fake::FakeEngine;
+use audio_to_text::whisper::forms::RecognizeParameters;
+
+use actix_web::dev::ServerHandle;
+use actix_web::http::StatusCode;
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const SECRET: &str = "s3cret";
+const BACKOFF_MS: u64 = 100;
+
+/// Config is read from env only, so tests do not set it concurrently.
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+struct Hit {
+    path: String,
+    job_id: Option<String>,
+    signature: Option<String>,
+    body: Vec<u8>,
+    received_at: Instant,
+}
+
+/// Callback listener which fails the first `failures` requests of every path.
+struct Listener {
+    url: String,
+    hits: Arc<Mutex<Vec<Hit>>>,
+    server: ServerHandle,
+}
+
+impl Listener {
+    fn spawn(failures: usize) -> Self {
+        let hits = Arc::new(Mutex::new(Vec::<Hit>::new()));
+        let recorded = hits.clone();
+        let server = HttpServer::new(move || {
+            let recorded = recorded.clone();
+            App::new().default_service(web::to(move |req: HttpRequest, body: web::Bytes| {
+                let recorded = recorded.clone();
+                async move {
+                    let header = |name: &str| {
+                        req.headers()
+                            .get(name)
+                            .map(|value| value.to_str().unwrap().to_string())
+                    };
+
+                    let mut hits = recorded.lock().unwrap();
+                    let path = req.path().to_string();
+                    let attempt = hits.iter().filter(|hit| hit.path == path).count();
+                    hits.push(Hit {
+                        path,
+                        job_id: header("x-job-id"),
+                        signature: header("x-signature"),
+                        body: body.to_vec(),
+                        received_at: Instant::now(),
+                    });
+
+                    match attempt < failures {
+                        true => HttpResponse::new(StatusCode::SERVICE_UNAVAILABLE),
+                        false => HttpResponse::new(StatusCode::OK),
+                    }
+                }
+            }))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+
+        let url = format!("http://{}", server.addrs()[0]);
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        Listener { url, hits, server: handle }
+    }
+
+    fn hits_of(&self, path: &str) -> Vec<Hit> {
+        let mut hits = self.hits.lock().unwrap();
+        let (path_hits, rest) = hits.drain(..).partition(|hit| hit.path == path);
+        *hits = rest;
+        path_hits
+    }
+}
+
+fn jobs_config(vars: &[(&str, &str)]) -> JobsConfig {
+    let _guard = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let names = [
+        "JOBS_CALLBACK_URL",
+        "JOBS_CALLBACK_SECRET",
+        "JOBS_CALLBACK_RETRIES",
+        "JOBS_CALLBACK_BACKOFF_MS",
+    ];
+    names.iter().for_each(|name| std::env::remove_var(name));
+    vars.iter().for_each(|(name, value)| std::env::set_var(name, value));
+    JobsConfig::from_env()
+}
+
+fn finished_job(callback_url: Option<String>) -> Job {
+    let mut job = Job::new("default", "audio.wav", RecognizeParameters::default());
+    job.callback_url = callback_url;
+    job.complete(Vec::new());
+    job
+}
+
+/// Callback state is stored after the listener answers, so it is polled.
+async fn wait_delivered(queue: &JobQueue, id: &str) -> Job {
+    for _ in 0..100 {
+        let job = queue.get(id).await.unwrap();
+        if job.callback_delivered {
+            return job;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("callback of job {} is not delivered", id);
+}
+
+fn sign(body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes()).unwrap();
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[actix_web::test]
+async fn signed_callback_is_retried_with_backoff() {
+    let listener = Listener::spawn(2);
+    let backoff = BACKOFF_MS.to_string();
+    let cfg = jobs_config(&[("JOBS_CALLBACK_SECRET", SECRET), ("JOBS_CALLBACK_BACKOFF_MS", backoff.as_str())]);
+
+    let mut job = finished_job(Some(format!("{}/job", listener.url)));
+    WebhookNotifier::new(&cfg).notify(&mut job).await;
+    assert_eq!(job.callback_attempts, 3);
+    assert!(job.callback_delivered);
+
+    let hits = listener.hits_of("/job");
+    assert_eq!(hits.len(), 3);
+    for hit in &hits {
+        assert_eq!(hit.job_id.as_deref(), Some(job.id.as_str()));
+        assert_eq!(hit.signature.as_deref(), Some(sign(&hit.body).as_str()));
+
+        let callback = serde_json::from_slice::<serde_json::Value>(&hit.body).unwrap();
+        assert_eq!(callback["id"], job.id);
+        assert_eq!(callback["state"], "done");
+        assert_eq!(callback["result"], serde_json::json!([]));
+    }
+
+    // Every retry waits twice as long as the previous one.
+    let first_delay = hits[1].received_at - hits[0].received_at;
+    let second_delay = hits[2].received_at - hits[1].received_at;
+    assert!(first_delay >= Duration::from_millis(BACKOFF_MS));
+    assert!(second_delay >= Duration::from_millis(2 * BACKOFF_MS));
+
+    listener.server.stop(false).await;
+}
+
+#[actix_web::test]
+async fn callback_is_given_up_after_configured_retries() {
+    let listener = Listener::spawn(usize::MAX);
+    let cfg = jobs_config(&[("JOBS_CALLBACK_RETRIES", "2"), ("JOBS_CALLBACK_BACKOFF_MS", "10")]);
+
+    let notifier = WebhookNotifier::new(&cfg);
+    let mut job = finished_job(Some(format!("{}/job", listener.url)));
+    notifier.notify(&mut job).await;
+    assert_eq!(job.callback_attempts, 3);
+    assert!(!job.callback_delivered);
+
+    let hits = listener.hits_of("/job");
+    assert_eq!(hits.len(), 3);
+    assert!(hits.iter().all(|hit| hit.signature.is_none()));
+
+    // Attempts are not made again once they are over.
+    assert!(!notifier.is_pending(&job));
+    notifier.notify(&mut job).await;
+    assert!(listener.hits_of("/job").is_empty());
+
+    listener.server.stop(false).await;
+}
+
+#[actix_web::test]
+async fn default_url_is_used_unless_job_passes_its_own() {
+    let listener = Listener::spawn(0);
+    let default_url = format!("{}/default", listener.url);
+    let cfg = jobs_config(&[("JOBS_CALLBACK_URL", default_url.as_str())]);
+    let notifier = WebhookNotifier::new(&cfg);
+
+    let mut job = finished_job(None);
+    notifier.notify(&mut job).await;
+    let hits = listener.hits_of("/default");
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].job_id.as_deref(), Some(job.id.as_str()));
+
+    let mut job = finished_job(Some(format!("{}/own", listener.url)));
+    notifier.notify(&mut job).await;
+    assert_eq!(listener.hits_of("/own").len(), 1);
+    assert!(listener.hits_of("/default").is_empty());
+
+    listener.server.stop(false).await;
+}
+
+#[actix_web::test]
+async fn finished_job_of_queue_is_posted() {
+    let listener = Listener::spawn(0);
+    let default_url = format!("{}/default", listener.url);
+    let cfg = jobs_config(&[("JOBS_CALLBACK_URL", default_url.as_str()), ("JOBS_CALLBACK_SECRET", SECRET)]);
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let queue = JobQueue::new(MemoryJobStore::default(), client, &cfg);
+
+    // Broken audio fails the job, which is reported like a done one.
+    let upload_dir = common::temp_path("webhook-upload");
+    std::fs::create_dir_all(&upload_dir).unwrap();
+    let upload = TempFile::unique(upload_dir.to_str().unwrap(), Some("audio.wav"));
+    std::fs::write(upload.path(), b"RIFF\x10\0\0\0WAVEbroken").unwrap();
+    let mut job = Job::new("default", upload.path(), RecognizeParameters::default());
+    queue.submit(&mut job, upload).await.unwrap();
+
+    let job = common::wait_job(&queue, job.id.as_str()).await;
+    assert_eq!(job.state, JobState::Failed);
+
+    let mut hits = Vec::new();
+    for _ in 0..100 {
+        hits = listener.hits_of("/default");
+        if !hits.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].signature.as_deref(), Some(sign(&hits[0].body).as_str()));
+    let callback = serde_json::from_slice::<serde_json::Value>(&hits[0].body).unwrap();
+    assert_eq!(callback["id"], job.id);
+    assert_eq!(callback["state"], "failed");
+    assert_eq!(callback["error"].as_str(), job.error.as_deref());
+
+    let job = wait_delivered(&queue, job.id.as_str()).await;
+    assert_eq!(job.callback_attempts, 1);
+
+    std::fs::remove_dir_all(&upload_dir).unwrap();
+    listener.server.stop(false).await;
+}
+
+#[actix_web::test]
+async fn undelivered_callbacks_are_resumed_on_start() {
+    let listener = Listener::spawn(0);
+    let cfg = jobs_config(&[("JOBS_CALLBACK_RETRIES", "2")]);
+
+    // Jobs finished by previous process, which was stopped while callbacks were sent.
+    let store_dir = common::temp_path("webhook-store");
+    let store = SqliteJobStore::open(store_dir.to_str().unwrap()).unwrap();
+    let mut interrupted = finished_job(Some(format!("{}/interrupted", listener.url)));
+    interrupted.callback_attempts = 1;
+    let mut delivered = finished_job(Some(format!("{}/delivered", listener.url)));
+    delivered.callback_attempts = 1;
+    delivered.callback_delivered = true;
+    let mut exhausted = finished_job(Some(format!("{}/exhausted", listener.url)));
+    exhausted.callback_attempts = 3;
+    for job in [&interrupted, &delivered, &exhausted] {
+        store.insert(job).unwrap();
+    }
+
+    let client = WhisperAsyncClient::with_engine(FakeEngine::default());
+    let queue = JobQueue::new(store, client, &cfg);
+
+    let mut hits = Vec::new();
+    for _ in 0..100 {
+        hits = listener.hits_of("/interrupted");
+        if !hits.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].job_id.as_deref(), Some(interrupted.id.as_str()));
+    assert!(listener.hits_of("/delivered").is_empty());
+    assert!(listener.hits_of("/exhausted").is_empty());
+
+    let resumed = wait_delivered(&queue, interrupted.id.as_str()).await;
+    assert_eq!(resumed.callback_attempts, 2);
+
+    std::fs::remove_dir_all(&store_dir).unwrap();
+    listener.server.stop(false).await;
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use audio_to_text::whisper::forms::{RecognizeParameters, RecognizeResponse, RecognizedWord};
+use audio_to_text::whisper::subtitles;
+
+fn word(word: &str, start: i64, end: i64) -> RecognizedWord {
+    RecognizedWord {
+        word: word.to_string(),
+        start,
+        end,
+        probability: 0.9,
+    }
+}
+
+#[test]
+fn token_timestamps_are_computed_for_words_and_segment_length() {
+    let cases = [
+        ("", false),
+        ("enable_word_timestamps=true", true),
+        ("max_segment_len=20", true),
+        ("max_segment_len=20&enable_split_on_word=true", true),
+    ];
+    for (query, expected) in cases {
+        let params = serde_urlencoded::from_str::<RecognizeParameters>(query).unwrap();
+        assert_eq!(params.is_token_timestamps_enable(), expected, "{}", query);
+    }
+}
+
+#[test]
+fn karaoke_cues_tag_every_word_but_the_first() {
+    let segments = [
+        RecognizeResponse {
+            frame_start: 0,
+            frame_end: 150,
+            text: "Hello <big> world".to_string(),
+            words: Some(vec![word("Hello", 0, 40), word("<big>", 40, 90), word("world", 90, 150)]),
+            ..Default::default()
+        },
+        RecognizeResponse {
+            frame_start: 150,
+            frame_end: 200,
+            text: " plain".to_string(),
+            ..Default::default()
+        },
+    ];
+
+    let expected = "WEBVTT\n\n\
+        00:00:00.000 --> 00:00:01.500\n\
+        <c>Hello</c> <00:00:00.400><c>&lt;big&gt;</c> <00:00:00.900><c>world</c>\n\n\
+        00:00:01.500 --> 00:00:02.000\nplain\n\n";
+    assert_eq!(subtitles::to_vtt_karaoke(&segments), expected);
+}
+
+#[test]
+fn karaoke_keeps_line_breaks_of_cue() {
+    let segments = [RecognizeResponse {
+        frame_start: 0,
+        frame_end: 100,
+        text: "one two\nthree".to_string(),
+        words: Some(vec![word("one", 0, 30), word("two", 30, 60), word("three", 60, 100)]),
+        ..Default::default()
+    }];
+
+    let karaoke = subtitles::to_vtt_karaoke(&segments);
+    assert!(karaoke.contains("<c>one</c> <00:00:00.300><c>two</c>\n<00:00:00.600><c>three</c>\n\n"), "{}", karaoke);
+}
+
+#[cfg(feature = "enable-native-decoding")]
+mod routes {
+    use super::common;
+
+    use audio_to_text::jobs;
+    use audio_to_text::jobs::config::JobsConfig;
+    use audio_to_text::jobs::store::MemoryJobStore;
+    use audio_to_text::jobs::worker::JobQueue;
+    use audio_to_text::whisper;
+    use audio_to_text::whisper::client_async::WhisperAsyncClient;
+    use audio_to_text::whisper::fake::FakeEngine;
+
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::web;
+
+    #[actix_web::test]
+    async fn words_are_reported_when_requested() {
+        let client = WhisperAsyncClient::with_engine(FakeEngine::new(vec!["Hello big world".to_string()]));
+        let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+        let req = common::upload_request("/recognize/file", &[], &common::wav_bytes(3)).to_request();
+        let segments = common::read_json(call_service(&app, req).await).await;
+        assert!(segments[0].get("words").is_none());
+
+        let uri = "/recognize/file?enable_word_timestamps=true";
+        let req = common::upload_request(uri, &[], &common::wav_bytes(3)).to_request();
+        let segments = common::read_json(call_service(&app, req).await).await;
+        let words = segments[0]["words"].as_array().unwrap();
+        let timings = words
+            .iter()
+            .map(|word| (word["word"].as_str().unwrap(), word["start"].as_i64().unwrap(), word["end"].as_i64().unwrap()))
+            .collect::<Vec<(&str, i64, i64)>>();
+        assert_eq!(timings, [("Hello", 0, 100), ("big", 100, 200), ("world", 200, 300)]);
+    }
+
+    #[actix_web::test]
+    async fn karaoke_format_enables_word_timestamps() {
+        let client = WhisperAsyncClient::with_engine(FakeEngine::new(vec!["Hello big world".to_string()]));
+        let app = init_service(common::build_app(client).service(whisper::build_scope())).await;
+
+        let req = common::upload_request("/recognize/file?format=vtt_karaoke", &[], &common::wav_bytes(3)).to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let body = String::from_utf8(read_body(resp).await.to_vec()).unwrap();
+        let expected = "WEBVTT\n\n00:00:00.000 --> 00:00:03.000\n\
+            <c>Hello</c> <00:00:01.000><c>big</c> <00:00:02.000><c>world</c>\n\n";
+        assert_eq!(body, expected);
+    }
+
+    #[actix_web::test]
+    async fn karaoke_format_enables_word_timestamps_of_job() {
+        let client = WhisperAsyncClient::with_engine(FakeEngine::new(vec!["Hello big world".to_string()]));
+        let queue = JobQueue::new(MemoryJobStore::default(), client.clone(), &JobsConfig::default());
+        let app = init_service(
+            common::build_app(client)
+                .app_data(web::Data::new(queue.clone()))
+                .service(jobs::build_scope()),
+        )
+        .await;
+
+        let req = common::upload_request("/jobs?format=vtt_karaoke", &[], &common::wav_bytes(3)).to_request();
+        let status = common::read_json(call_service(&app, req).await).await;
+        let job_id = status["id"].as_str().unwrap();
+        let job = common::wait_job(&queue, job_id).await;
+        std::fs::remove_file(&job.file_path).unwrap();
+
+        let uri = format!("/jobs/{}/result?format=vtt_karaoke", job_id);
+        let resp = call_service(&app, TestRequest::get().uri(uri.as_str()).to_request()).await;
+        let body = String::from_utf8(read_body(resp).await.to_vec()).unwrap();
+        assert!(body.contains("<c>Hello</c> <00:00:01.000><c>big</c>"), "{}", body);
+    }
+}